@@ -0,0 +1,138 @@
+//! Encodes and decodes a 1 KB payload with different encoder settings and image sizes, and
+//! encodes a short payload into a 4MP image where copying the source pixels dominates.
+//!
+//! ```sh
+//! cargo bench --bench encoding
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use image::DynamicImage;
+use seagul_core::{decoder::ImageDecoder, encoder::ImageEncoder, prelude::*};
+
+const MARKER: &[u8] = b"\0\0";
+
+/// A 1 KB payload of lowercase letters, followed by `MARKER`
+fn payload() -> Vec<u8> {
+    (0..1024)
+        .map(|i| b'a' + (i % 26) as u8)
+        .chain(MARKER.iter().copied())
+        .collect()
+}
+
+fn source_image() -> DynamicImage {
+    DynamicImage::new_rgb8(1024, 1024)
+}
+
+fn encode_lsb(c: &mut Criterion) {
+    let payload = payload();
+    let mut group = c.benchmark_group("encode 1KB by lsb");
+    group.throughput(Throughput::Bytes(payload.len() as u64));
+
+    for lsb in [1, 2, 4, 8] {
+        let encoder = ImageEncoder::from(source_image()).with_lsb(lsb);
+        group.bench_with_input(BenchmarkId::from_parameter(lsb), &encoder, |b, encoder| {
+            b.iter(|| encoder.encode_bytes(&payload).expect("Encoding failed"))
+        });
+    }
+    group.finish();
+}
+
+fn decode_lsb(c: &mut Criterion) {
+    let payload = payload();
+    let mut group = c.benchmark_group("decode 1KB by lsb");
+    group.throughput(Throughput::Bytes(payload.len() as u64));
+
+    for lsb in [1, 2, 4, 8] {
+        let encoded = ImageEncoder::from(source_image())
+            .with_lsb(lsb)
+            .encode_bytes(&payload)
+            .expect("Encoding failed");
+        let mut decoder = ImageDecoder::from(encoded.altered_image().clone());
+        decoder.set_use_n_lsb(lsb).until_marker(Some(MARKER));
+
+        group.bench_with_input(BenchmarkId::from_parameter(lsb), &decoder, |b, decoder| {
+            b.iter(|| decoder.decode().expect("Decoding failed"))
+        });
+    }
+    group.finish();
+}
+
+fn decode_long_marker(c: &mut Criterion) {
+    let marker: Vec<u8> = (0..64).map(|i| b'A' + (i % 26) as u8).collect();
+    let payload: Vec<u8> = (0..10 * 1024)
+        .map(|i| b'a' + (i % 26) as u8)
+        .chain(marker.iter().copied())
+        .collect();
+    let encoded = ImageEncoder::from(source_image())
+        .encode_bytes(&payload)
+        .expect("Encoding failed");
+    let mut decoder = ImageDecoder::from(encoded.altered_image().clone());
+    decoder.until_marker(Some(&marker));
+
+    c.bench_function("decode 10KB until a 64B marker", |b| {
+        b.iter(|| decoder.decode().expect("Decoding failed"))
+    });
+}
+
+fn encode_spread(c: &mut Criterion) {
+    let payload = payload();
+    let mut group = c.benchmark_group("encode 1KB by spread");
+
+    for spread in [false, true] {
+        let encoder = ImageEncoder::from(source_image()).with_spread(spread);
+        group.bench_with_input(BenchmarkId::from_parameter(spread), &encoder, |b, encoder| {
+            b.iter(|| encoder.encode_bytes(&payload).expect("Encoding failed"))
+        });
+    }
+    group.finish();
+}
+
+fn encode_channels(c: &mut Criterion) {
+    let payload = payload();
+    let mut group = c.benchmark_group("encode 1KB by channel");
+
+    for channel in [RgbChannel::Blue, RgbChannel::All] {
+        let encoder = ImageEncoder::from(source_image()).with_channel(channel.clone());
+        group.bench_with_input(BenchmarkId::from_parameter(channel), &encoder, |b, encoder| {
+            b.iter(|| encoder.encode_bytes(&payload).expect("Encoding failed"))
+        });
+    }
+    group.finish();
+}
+
+fn encode_image_size(c: &mut Criterion) {
+    let payload = payload();
+    let mut group = c.benchmark_group("encode 1KB by image size");
+    group.sample_size(10);
+
+    for size in [256, 1024, 4096] {
+        let encoder = ImageEncoder::from(DynamicImage::new_rgb8(size, size));
+        group.throughput(Throughput::Elements(size as u64 * size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{}x{}", size, size)),
+            &encoder,
+            |b, encoder| b.iter(|| encoder.encode_bytes(&payload).expect("Encoding failed")),
+        );
+    }
+    group.finish();
+}
+
+fn encode_4mp_rgb8(c: &mut Criterion) {
+    let encoder = ImageEncoder::from(DynamicImage::new_rgb8(2048, 2048));
+
+    c.bench_function("encode 16B into 2048x2048 RGB8", |b| {
+        b.iter(|| encoder.encode_bytes(b"Midway upon the ").expect("Encoding failed"))
+    });
+}
+
+criterion_group!(
+    benches,
+    encode_lsb,
+    decode_lsb,
+    decode_long_marker,
+    encode_spread,
+    encode_channels,
+    encode_image_size,
+    encode_4mp_rgb8
+);
+criterion_main!(benches);
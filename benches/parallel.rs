@@ -0,0 +1,24 @@
+//! Encodes a 10 KB payload into a 4K image.
+//!
+//! Run with and without the `rayon` feature to compare sequential and parallel encoding:
+//!
+//! ```sh
+//! cargo bench --bench parallel
+//! cargo bench --bench parallel --features rayon
+//! ```
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use image::DynamicImage;
+use seagul_core::{encoder::ImageEncoder, prelude::*};
+
+fn encode_4k(c: &mut Criterion) {
+    let payload: Vec<u8> = (0..10 * 1024).map(|i| (i % 251) as u8).collect();
+    let encoder = ImageEncoder::from(DynamicImage::new_rgb8(3840, 2160)).with_lsb(2);
+
+    c.bench_function("encode 10KB into 3840x2160", |b| {
+        b.iter(|| encoder.encode_bytes(&payload).expect("Encoding failed"))
+    });
+}
+
+criterion_group!(benches, encode_4k);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use seagul_core::decoder::ImageDecoder;
+
+// Loads the input as an image and tries every decoding method on it. Malformed images,
+// headers and metadata must be reported as errors
+fuzz_target!(|data: &[u8]| {
+    let decoder = match ImageDecoder::from_bytes(data) {
+        Ok(decoder) => decoder,
+        Err(_) => return,
+    };
+
+    let _ = decoder.decode();
+    let _ = decoder.decode_with_header();
+    let _ = decoder.decode_with_crc();
+    let _ = decoder.decode_self_describing();
+});
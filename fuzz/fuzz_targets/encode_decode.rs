@@ -0,0 +1,24 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use seagul_core::{decoder::ImageDecoder, encoder::ImageEncoder, prelude::ImageRules};
+
+// Encodes the input into a small image, writes it as PNG and decodes it back. Payloads
+// that do not fit must be refused with an error, and the others must be recovered intact
+fuzz_target!(|data: &[u8]| {
+    let source = image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(32, 32, |x, y| {
+        image::Rgb([(x * 8) as u8, (y * 8) as u8, (x ^ y) as u8])
+    }));
+
+    let encoded = match ImageEncoder::from_image(source).with_lsb(2).encode_bytes(data) {
+        Ok(encoded) => encoded,
+        Err(_) => return,
+    };
+    let png = encoded.to_png_bytes().expect("Encoded images can always be written");
+
+    let decoded = ImageDecoder::from_bytes(&png)
+        .expect("Written images can always be read")
+        .with_lsb(2)
+        .decode()
+        .expect("Images holding a payload can always be decoded");
+    assert!(decoded.embedded_data().starts_with(data));
+});
@@ -0,0 +1,61 @@
+use tokio::task::{spawn_blocking, JoinHandle};
+
+use crate::{
+    decoder::{DecodedImage, ImageDecoder},
+    encoder::{EncodedImage, ImageEncoder},
+    error::SteganographyError,
+};
+
+/// Encodes `data` into the source image of `encoder` on the blocking thread pool of the
+/// current tokio runtime, so that the CPU bound encoding does not stall other tasks.
+///
+/// Must be called from within a tokio runtime
+pub async fn encode_string_async(encoder: ImageEncoder, data: String) -> Result<EncodedImage, SteganographyError> {
+    join(spawn_blocking(move || encoder.encode_string(data))).await?
+}
+
+/// Decodes the source image of `decoder` on the blocking thread pool of the current tokio
+/// runtime, so that the CPU bound decoding does not stall other tasks.
+///
+/// Must be called from within a tokio runtime
+pub async fn decode_async(decoder: ImageDecoder) -> Result<DecodedImage, SteganographyError> {
+    join(spawn_blocking(move || decoder.decode())).await?
+}
+
+/// Waits for a blocking task, resuming its panic if it panicked. Blocking tasks can only
+/// be cancelled before starting, when the runtime shuts down
+async fn join<T>(task: JoinHandle<T>) -> Result<T, SteganographyError> {
+    match task.await {
+        Ok(output) => Ok(output),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(SteganographyError::IoError(std::io::Error::new(
+            std::io::ErrorKind::Interrupted,
+            e,
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::DynamicImage;
+
+    use super::{decode_async, encode_string_async};
+    use crate::{decoder::ImageDecoder, encoder::ImageEncoder, prelude::*};
+
+    #[test]
+    fn encode_decode() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            let encoder = ImageEncoder::from_image(DynamicImage::new_rgb8(64, 64)).with_lsb(2);
+            let encoded = encode_string_async(encoder, String::from("Midway upon the journey"))
+                .await
+                .expect("Encoding failed");
+
+            let decoder = ImageDecoder::from_image(encoded.altered_image().clone())
+                .with_lsb(2)
+                .with_max_bytes(23);
+            let decoded = decode_async(decoder).await.expect("Decoding failed");
+            assert_eq!(decoded.embedded_data().as_slice(), b"Midway upon the journey");
+        });
+    }
+}
@@ -0,0 +1,372 @@
+use crate::{header::BYTE_STEP, pixel_selectors::{clamp_region, region_dimensions}, prelude::{channel_indices, ImageRules}};
+
+/// Describes how a payload fits into an image, as computed by `analyze_capacity`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityReport {
+    /// The number of bits written to encode the payload, error correction included
+    pub payload_bits_needed: u64,
+
+    /// The number of bits the image can hold with the rules used
+    pub image_bits_available: u64,
+
+    /// Wheter the payload fits into the image
+    pub fits: bool,
+
+    /// The number of payload bytes that would still fit after this payload, negative
+    /// when the payload does not fit
+    pub headroom_bytes: i64,
+}
+
+/// Computes how a payload of `payload_len` bytes fits into an image with the given
+/// `(width, height)` dimensions when encoded with the provided `rules`
+pub fn analyze_capacity<R>(payload_len: usize, image_dimensions: (u32, u32), rules: &R) -> CapacityReport
+where
+    R: ImageRules,
+{
+    let encoded_len = if rules.get_ecc() { payload_len * 2 } else { payload_len };
+    let payload_bits_needed = (encoded_len * BYTE_STEP) as u64;
+    let image_bits_available = bits_available(image_dimensions, rules) as u64;
+
+    CapacityReport {
+        payload_bits_needed,
+        image_bits_available,
+        fits: payload_bits_needed <= image_bits_available,
+        headroom_bytes: bytes_available(image_dimensions, rules) as i64 - payload_len as i64,
+    }
+}
+
+/// Computes how many whole payload bytes fit into an image with the given `(width, height)`
+/// dimensions when encoded with the provided `rules`.
+///
+/// Only the pixels inside the region are available, if one is set. Pixels before the starting
+/// position and the offset are not available either, and only one pixel every
+/// `step_by_n_pixels` is used after that, or every `step_by_n_pixels` times the spread
+/// stride if one is set. Each used pixel stores `use_n_lsb` bits
+/// in every channel of the channel order. Error correction, when enabled, halves
+/// the capacity.
+pub fn bytes_available<R>(image_dimensions: (u32, u32), rules: &R) -> usize
+where
+    R: ImageRules,
+{
+    let capacity = bits_available(image_dimensions, rules) / BYTE_STEP;
+    if rules.get_ecc() {
+        capacity / 2
+    } else {
+        capacity
+    }
+}
+
+#[deprecated(note = "renamed to `bytes_available`")]
+pub fn max_payload_bytes<R>(image_dimensions: (u32, u32), rules: &R) -> usize
+where
+    R: ImageRules,
+{
+    bytes_available(image_dimensions, rules)
+}
+
+/// Computes how many pixels are walked through to encode `payload_len` bytes with the
+/// provided `rules`, from the first pixel holding data to the last one. The pixels skipped
+/// in between because of `step_by_n_pixels` are counted too.
+///
+/// A payload fits into an image when this does not exceed the pixels left after the
+/// starting position and the offset
+pub fn pixels_consumed<R>(payload_len: usize, rules: &R) -> usize
+where
+    R: ImageRules,
+{
+    pixels_consumed_by_step(payload_len, rules, rules.get_step_by_n_pixels())
+}
+
+/// Like `pixels_consumed`, using one pixel every `step` regardless of the rules
+pub(crate) fn pixels_consumed_by_step<R>(payload_len: usize, rules: &R, step: usize) -> usize
+where
+    R: ImageRules,
+{
+    match used_pixels(payload_len, rules) {
+        0 => 0,
+        used_pixels => (used_pixels - 1) * step.max(1) + 1,
+    }
+}
+
+/// The number of selected pixels between two pixels holding data when spreading a payload
+/// of `payload_len` bytes with the provided `rules`, so that the payload covers the whole
+/// image. Always 1 if spreading is not enabled.
+///
+/// The stride set with `set_spread_stride` is used if there is one, otherwise it is computed
+/// from the pixels left after the starting position and the offset
+pub fn spread_stride<R>(image_dimensions: (u32, u32), payload_len: usize, rules: &R) -> usize
+where
+    R: ImageRules,
+{
+    spread_stride_after(image_dimensions, payload_len, rules, 0)
+}
+
+/// Like `spread_stride`, for a payload starting `reserved_pixels` pixels after the
+/// starting position and the offset
+pub(crate) fn spread_stride_after<R>(
+    image_dimensions: (u32, u32),
+    payload_len: usize,
+    rules: &R,
+    reserved_pixels: usize,
+) -> usize
+where
+    R: ImageRules,
+{
+    if !rules.get_spread() || rules.get_spread_stride() > 0 {
+        return fixed_spread_stride(rules);
+    }
+
+    computed_spread_stride(image_dimensions, payload_len, rules, reserved_pixels)
+}
+
+/// The stride spreading a payload of `payload_len` bytes over the pixels left after the
+/// starting position, the offset and `reserved_pixels`, whatever the spread settings of `rules`
+pub(crate) fn computed_spread_stride<R>(
+    image_dimensions: (u32, u32),
+    payload_len: usize,
+    rules: &R,
+    reserved_pixels: usize,
+) -> usize
+where
+    R: ImageRules,
+{
+    let area_dimensions = region_dimensions(clamp_region(image_dimensions, rules.get_region()));
+    let total_pixels = area_dimensions.0 as usize * area_dimensions.1 as usize;
+    let start_pixel =
+        rules.get_position().pixel_offset(area_dimensions) + rules.get_offset() + reserved_pixels;
+    let step = rules.get_step_by_n_pixels().max(1);
+    let selectable_pixels = total_pixels.saturating_sub(start_pixel).div_ceil(step);
+
+    match used_pixels(payload_len, rules) {
+        0 => 1,
+        used_pixels => (selectable_pixels / used_pixels).max(1),
+    }
+}
+
+/// The number of selected pixels from the first one holding a payload of `payload_len` bytes
+/// to the first one after it, when it is not spread
+pub(crate) fn pixels_spanned<R>(payload_len: usize, rules: &R) -> usize
+where
+    R: ImageRules,
+{
+    used_pixels(payload_len, rules) * rules.get_step_by_n_pixels().max(1)
+}
+
+/// The number of bits that can be written into an image with the given `(width, height)`
+/// dimensions, error correction included
+fn bits_available<R>(image_dimensions: (u32, u32), rules: &R) -> usize
+where
+    R: ImageRules,
+{
+    let area_dimensions = region_dimensions(clamp_region(image_dimensions, rules.get_region()));
+    let total_pixels = area_dimensions.0 as usize * area_dimensions.1 as usize;
+    let start_pixel = rules.get_position().pixel_offset(area_dimensions) + rules.get_offset();
+    let remaining_pixels = total_pixels.saturating_sub(start_pixel);
+    let step = rules.get_step_by_n_pixels().max(1) * fixed_spread_stride(rules);
+
+    // The first remaining pixel is always used, then one every `step`
+    let usable_pixels = remaining_pixels.div_ceil(step);
+
+    usable_pixels * bits_per_pixel(rules)
+}
+
+/// The stride set on `rules` when spreading, or 1
+fn fixed_spread_stride<R>(rules: &R) -> usize
+where
+    R: ImageRules,
+{
+    if rules.get_spread() {
+        rules.get_spread_stride().max(1)
+    } else {
+        1
+    }
+}
+
+/// The number of pixels holding data needed to encode `payload_len` bytes
+fn used_pixels<R>(payload_len: usize, rules: &R) -> usize
+where
+    R: ImageRules,
+{
+    let payload_len = if rules.get_ecc() { payload_len * 2 } else { payload_len };
+    match bits_per_pixel(rules) {
+        0 => 0,
+        bits_per_pixel => (payload_len * BYTE_STEP).div_ceil(bits_per_pixel),
+    }
+}
+
+/// The number of bits stored in each used pixel
+fn bits_per_pixel<R>(rules: &R) -> usize
+where
+    R: ImageRules,
+{
+    rules.get_use_n_lsb() * channel_indices(rules.get_channel_order()).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{analyze_capacity, bytes_available, pixels_consumed};
+    use crate::{encoder::ImageEncoder, prelude::*};
+
+    #[test]
+    fn default_rules() {
+        let encoder = ImageEncoder::default();
+        assert_eq!(bytes_available((16, 16), &encoder), 32);
+    }
+
+    #[test]
+    fn offset_equal_to_image_size() {
+        let mut encoder = ImageEncoder::default();
+        encoder.set_offset(256);
+        assert_eq!(bytes_available((16, 16), &encoder), 0);
+
+        encoder.set_offset(1000);
+        assert_eq!(bytes_available((16, 16), &encoder), 0);
+    }
+
+    #[test]
+    fn all_bits_used() {
+        let mut encoder = ImageEncoder::default();
+        encoder.set_use_n_lsb(8);
+        assert_eq!(bytes_available((16, 16), &encoder), 256);
+
+        encoder.set_channel_order(vec![RgbChannel::Red, RgbChannel::Green, RgbChannel::Blue]);
+        assert_eq!(bytes_available((16, 16), &encoder), 768);
+
+        encoder.set_use_channel(RgbChannel::All);
+        assert_eq!(bytes_available((16, 16), &encoder), 768);
+    }
+
+    #[test]
+    fn step_larger_than_image() {
+        let mut encoder = ImageEncoder::default();
+        encoder.set_step_by_n_pixels(1000);
+        assert_eq!(bytes_available((16, 16), &encoder), 0);
+
+        encoder.set_use_n_lsb(8);
+        assert_eq!(bytes_available((16, 16), &encoder), 1);
+    }
+
+    #[test]
+    fn capacity_report() {
+        let encoder = ImageEncoder::default();
+        let report = analyze_capacity(30, (16, 16), &encoder);
+        assert_eq!(report.payload_bits_needed, 240);
+        assert_eq!(report.image_bits_available, 256);
+        assert!(report.fits);
+        assert_eq!(report.headroom_bytes, 2);
+
+        let report = analyze_capacity(30, (16, 16), &encoder.with_ecc());
+        assert_eq!(report.payload_bits_needed, 480);
+        assert!(!report.fits);
+        assert_eq!(report.headroom_bytes, -14);
+    }
+
+    #[test]
+    fn error_correction() {
+        let encoder = ImageEncoder::default().with_ecc();
+        assert_eq!(bytes_available((16, 16), &encoder), 16);
+    }
+
+    #[test]
+    fn position_and_step() {
+        let mut encoder = ImageEncoder::default();
+        encoder
+            .set_position(ImagePosition::TopRight)
+            .set_step_by_n_pixels(3);
+
+        // 240 pixels after the first row, one every 3 is used
+        assert_eq!(bytes_available((16, 16), &encoder), 10);
+    }
+
+    #[test]
+    fn region() {
+        let mut encoder = ImageEncoder::default();
+        encoder.set_region(4, 4, 12, 12);
+        assert_eq!(bytes_available((16, 16), &encoder), 8);
+
+        // Only the part inside the image is usable
+        encoder.set_region(8, 8, 100, 100);
+        assert_eq!(bytes_available((16, 16), &encoder), 8);
+
+        encoder.set_region(20, 20, 30, 30);
+        assert_eq!(bytes_available((16, 16), &encoder), 0);
+    }
+
+    #[test]
+    fn consumed_pixels() {
+        let mut encoder = ImageEncoder::default();
+        assert_eq!(pixels_consumed(0, &encoder), 0);
+        assert_eq!(pixels_consumed(2, &encoder), 16);
+
+        // The pixels skipped after the last one holding data are not consumed
+        encoder.set_step_by_n_pixels(3);
+        assert_eq!(pixels_consumed(2, &encoder), 46);
+
+        encoder.set_use_n_lsb(3).set_ecc(true);
+        assert_eq!(pixels_consumed(1, &encoder), 16);
+    }
+
+    proptest! {
+        #[test]
+        fn consumed_pixels_match_capacity(
+            (width, height) in (1u32..48, 1u32..48),
+            lsb in 1usize..=8,
+            step in 1usize..6,
+            offset in 0usize..64,
+            channels in 1usize..=3,
+            ecc: bool,
+            payload_len in 0usize..600,
+        ) {
+            let mut encoder = ImageEncoder::default();
+            encoder
+                .set_use_n_lsb(lsb)
+                .set_step_by_n_pixels(step)
+                .set_offset(offset)
+                .set_channel_order(vec![RgbChannel::Red, RgbChannel::Green, RgbChannel::Blue][..channels].to_vec())
+                .set_ecc(ecc);
+
+            let remaining_pixels = (width as usize * height as usize).saturating_sub(offset);
+            let fits = pixels_consumed(payload_len, &encoder) <= remaining_pixels;
+            prop_assert_eq!(fits, payload_len <= bytes_available((width, height), &encoder));
+        }
+
+        #[test]
+        fn consumed_pixels_grow_with_payload(
+            lsb in 1usize..=8,
+            step in 1usize..6,
+            payload_len in 0usize..10_000,
+        ) {
+            let mut encoder = ImageEncoder::default();
+            encoder.set_use_n_lsb(lsb).set_step_by_n_pixels(step);
+
+            let consumed = pixels_consumed(payload_len, &encoder);
+            prop_assert!(consumed <= pixels_consumed(payload_len + 1, &encoder));
+            prop_assert!(consumed <= (payload_len * 8).div_ceil(lsb) * step);
+        }
+
+        #[test]
+        fn encoding_fits_capacity(
+            (width, height) in (1u32..24, 1u32..24),
+            lsb in prop::sample::select(vec![1usize, 2, 4, 8]),
+            step in 1usize..4,
+            payload_len in 0usize..200,
+        ) {
+            let encoder = ImageEncoder::from_image(image::DynamicImage::new_rgb8(width, height))
+                .with_lsb(lsb)
+                .with_step_by_n_pixels(step);
+            let result = encoder.encode_bytes(vec![0xa5; payload_len]);
+
+            if payload_len <= bytes_available((width, height), &encoder) {
+                prop_assert!(result.is_ok());
+            } else {
+                let is_insufficient = matches!(
+                    result,
+                    Err(SteganographyError::InsufficientCapacity { .. })
+                );
+                prop_assert!(is_insufficient);
+            }
+        }
+    }
+}
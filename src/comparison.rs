@@ -0,0 +1,93 @@
+use bitvec::prelude::*;
+use image::DynamicImage;
+
+use crate::{decoder::ImageDecoder, error::SteganographyError, prelude::ImageRules};
+
+/// Reads the least significant bits of `img` from the pixels and channels described by
+/// `rules`, in the order an encoder following the same rules writes them. The whole image
+/// is read, so the stream goes on past the end of any payload.
+///
+/// Error correction is not applied. Spread payloads are read with their explicit stride, or
+/// from every selected pixel when there is none, as the computed stride depends on the
+/// payload length
+pub fn extract_lsb_stream<R>(img: &DynamicImage, rules: &R) -> Result<BitVec<Lsb0, u8>, SteganographyError>
+where
+    R: ImageRules,
+{
+    let mut decoder = ImageDecoder::from_rules(img.clone(), rules);
+    decoder.set_ecc(false);
+    if rules.get_spread_stride() == 0 {
+        decoder.set_spread(false);
+    }
+
+    Ok(decoder.decode()?.as_bitvec())
+}
+
+/// The fraction of matching bits between the LSB streams of `a` and `b`, as extracted by
+/// `extract_lsb_stream`, from `0.0` to `1.0`. Streams of different lengths are compared
+/// over the shorter one, and empty streams have a similarity of `0.0`.
+///
+/// Values near `1.0` suggest both images hold the same payload, while unrelated payloads
+/// score around `0.5`. Since the streams include the pixels past the payload, images
+/// encoded from the same cover also match there
+pub fn payload_similarity<R>(a: &DynamicImage, b: &DynamicImage, rules: &R) -> Result<f64, SteganographyError>
+where
+    R: ImageRules,
+{
+    let a = extract_lsb_stream(a, rules)?;
+    let b = extract_lsb_stream(b, rules)?;
+    let compared = a.len().min(b.len());
+    if compared == 0 {
+        return Ok(0.0);
+    }
+
+    let matching = a.iter().zip(b.iter()).filter(|(a, b)| **a == **b).count();
+    Ok(matching as f64 / compared as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::prelude::*;
+    use image::DynamicImage;
+
+    use super::{extract_lsb_stream, payload_similarity};
+    use crate::{encoder::ImageEncoder, error::SteganographyError, prelude::*};
+
+    #[test]
+    fn lsb_stream() {
+        let encoder = ImageEncoder::from_image(DynamicImage::new_rgb8(8, 4))
+            .with_channel_order(vec![RgbChannel::Blue])
+            .with_lsb(2);
+        let encoded = encoder.encode_bytes([0b1110_0100u8]).unwrap();
+        let stream = extract_lsb_stream(encoded.altered_image(), &encoder).unwrap();
+
+        // 2 bits from each of the 32 pixels
+        assert_eq!(stream.len(), 64);
+        assert_eq!(stream[..8].load_le::<u8>(), 0b1110_0100);
+        assert!(stream[8..].not_any());
+
+        let luma = ImageEncoder::default().with_channel_order(vec![RgbChannel::Luma]);
+        assert!(matches!(
+            extract_lsb_stream(encoded.altered_image(), &luma),
+            Err(SteganographyError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn similarity() {
+        let cover = DynamicImage::new_rgb8(16, 16);
+        let encoder = ImageEncoder::from_image(cover);
+        let payload: Vec<u8> = (0..16u8).collect();
+        let a = encoder.encode_bytes(&payload).unwrap();
+        let b = encoder.encode_bytes(&payload).unwrap();
+        let inverted: Vec<u8> = payload.iter().map(|byte| !byte).collect();
+        let c = encoder.encode_bytes(&inverted).unwrap();
+
+        let same = payload_similarity(a.altered_image(), b.altered_image(), &encoder).unwrap();
+        assert_eq!(same, 1.0);
+        let different = payload_similarity(a.altered_image(), c.altered_image(), &encoder).unwrap();
+        assert!(different < 1.0);
+        // The 128 payload bits differ, the rest of the stream comes from the same cover
+        assert_eq!(different, 0.5);
+    }
+}
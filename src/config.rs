@@ -0,0 +1,383 @@
+use std::str::FromStr;
+
+use crate::{decoder::ImageDecoder, encoder::ImageEncoder, error::SteganographyError, prelude::{BitOrder, CompressionType, FilterType, ImagePosition, ImageRules, Region, RgbChannel}};
+
+/// The options of an `ImageEncoder`, detached from its source image.
+///
+/// Pixel selectors and passwords are not part of the configuration and must be
+/// shared separately
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncoderConfig {
+    /// Number of least significant bits to modify on each channel
+    pub lsb_c: usize,
+
+    /// One pixel every `skip_c` pixels is used
+    pub skip_c: usize,
+
+    /// Number of pixels to skip before starting encode
+    pub offset: usize,
+
+    /// Wheter the message is spread across all image
+    pub spread: bool,
+
+    /// The number of selected pixels between two pixels holding data when spreading,
+    /// computed from the message length if 0
+    pub spread_stride: usize,
+
+    /// The color channels used for encoding, in order
+    pub channel_order: Vec<RgbChannel>,
+
+    /// The position on the image to start encoding from
+    pub encoding_position: ImagePosition,
+
+    /// The string used to pad the pixels not holding data, if any
+    pub padding: Option<String>,
+
+    /// The only part of the image to modify, if any
+    pub region: Option<Region>,
+
+    /// The order in which the bits of each payload byte are stored
+    pub bit_order: BitOrder,
+
+    /// Wheter the data is protected by an error correction code
+    pub ecc: bool,
+
+    /// The compression to use when saving the encoded image
+    pub compression_type: CompressionType,
+
+    /// The filter to use when saving the encoded image
+    pub filter_type: FilterType,
+}
+
+/// The options of an `ImageDecoder`, detached from its source image.
+///
+/// Pixel selectors and passwords are not part of the configuration and must be
+/// shared separately
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecoderConfig {
+    /// Number of least significant bits to read on each channel
+    pub lsb_c: usize,
+
+    /// One pixel every `skip_c` pixels is read
+    pub skip_c: usize,
+
+    /// Number of pixels to skip before starting decode
+    pub offset: usize,
+
+    /// Wheter the message is spread across all image
+    pub spread: bool,
+
+    /// The number of selected pixels between two pixels holding data when spreading,
+    /// computed from the message length if 0
+    pub spread_stride: usize,
+
+    /// The color channels used for decoding, in order
+    pub channel_order: Vec<RgbChannel>,
+
+    /// The position on the image to start decoding from
+    pub encoding_position: ImagePosition,
+
+    /// The only part of the image holding data, if any
+    pub region: Option<Region>,
+
+    /// The order in which the bits of each payload byte are stored
+    pub bit_order: BitOrder,
+
+    /// Wheter the data is protected by an error correction code
+    pub ecc: bool,
+
+    /// The string the encoder padded the unused pixels with, if any
+    pub padding: Option<String>,
+
+    /// The byte sequence decoding stops at, if any
+    pub marker: Option<Vec<u8>>,
+
+    /// The maximum number of bytes read when the payload length is unknown, if any
+    pub max_bytes: Option<usize>,
+}
+
+impl FromStr for EncoderConfig {
+    type Err = SteganographyError;
+
+    /// Parses a configuration written as comma separated `key=value` pairs, such as
+    /// `lsb=2,channel=blue,skip=3,position=top-left`. Options not listed keep the values
+    /// of `ImageEncoder::default`. The keys are:
+    ///
+    /// - `lsb`, `skip` (or `step`), `offset` and `stride`: numbers
+    /// - `channel`: a channel name, or several joined by `+` such as `red+blue`
+    /// - `position`: `top-left`, `top-right`, `bottom-left`, `bottom-right`, `center` or `at(x, y)`
+    /// - `spread` and `ecc`: `true` or `false`
+    /// - `bit-order`: `lsb-first` or `msb-first`
+    /// - `padding`: any string without commas
+    /// - `region`: `x1:y1:x2:y2`
+    fn from_str(repr: &str) -> Result<Self, Self::Err> {
+        let mut encoder = ImageEncoder::default();
+        for (key, value) in pairs(repr)? {
+            if !apply_rule(&mut encoder, key, value)? {
+                return Err(unknown_key(key));
+            }
+        }
+        Ok(encoder.config())
+    }
+}
+
+impl FromStr for DecoderConfig {
+    type Err = SteganographyError;
+
+    /// Parses a configuration written like the ones of `EncoderConfig`, which also
+    /// accepts a `marker` string and a `max-bytes` number. Options not listed keep the
+    /// values of `ImageDecoder::default`
+    fn from_str(repr: &str) -> Result<Self, Self::Err> {
+        let mut decoder = ImageDecoder::default();
+        for (key, value) in pairs(repr)? {
+            match key {
+                "marker" => {
+                    decoder.until_marker(Some(value));
+                }
+                "max-bytes" => {
+                    decoder.set_max_bytes(parse_value(key, value)?);
+                }
+                _ => {
+                    if !apply_rule(&mut decoder, key, value)? {
+                        return Err(unknown_key(key));
+                    }
+                }
+            }
+        }
+        Ok(decoder.config())
+    }
+}
+
+/// Splits a configuration string into its `key=value` pairs. Commas between parentheses,
+/// as in `at(x, y)`, do not separate pairs
+fn pairs(repr: &str) -> Result<Vec<(&str, &str)>, SteganographyError> {
+    let mut entries = vec![];
+    let (mut depth, mut start) = (0_usize, 0);
+    for (index, c) in repr.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                entries.push(&repr[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(&repr[start..]);
+
+    entries
+        .into_iter()
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| {
+                    SteganographyError::InvalidConfiguration(format!("Expected key=value, found \"{}\"", entry))
+                })
+        })
+        .collect()
+}
+
+/// Applies `value` through the `ImageRules` setter matching `key`. Returns `false` if
+/// `key` is not the name of a rule
+fn apply_rule<R: ImageRules>(rules: &mut R, key: &str, value: &str) -> Result<bool, SteganographyError> {
+    match key {
+        "lsb" => rules.set_use_n_lsb(parse_value(key, value)?),
+        "skip" | "step" => rules.set_step_by_n_pixels(parse_value(key, value)?),
+        "offset" => rules.set_offset(parse_value(key, value)?),
+        "stride" => rules.set_spread_stride(parse_value(key, value)?),
+        "spread" => rules.set_spread(parse_value(key, value)?),
+        "ecc" => rules.set_ecc(parse_value(key, value)?),
+        "channel" => rules.set_channel_order(
+            value
+                .split('+')
+                .map(|channel| channel.trim().parse())
+                .collect::<Result<_, _>>()?,
+        ),
+        "position" => rules.set_position(value.parse()?),
+        "bit-order" => rules.set_bit_order(value.parse()?),
+        "padding" => rules.set_padding(value),
+        "region" => {
+            let bounds: Vec<u32> = value
+                .split(':')
+                .map(|bound| parse_value(key, bound.trim()))
+                .collect::<Result<_, _>>()?;
+            match bounds[..] {
+                [x1, y1, x2, y2] => rules.set_region(x1, y1, x2, y2),
+                _ => return Err(invalid_value(key, value)),
+            }
+        }
+        _ => return Ok(false),
+    };
+    Ok(true)
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, SteganographyError> {
+    value.parse().map_err(|_| invalid_value(key, value))
+}
+
+fn invalid_value(key: &str, value: &str) -> SteganographyError {
+    SteganographyError::InvalidConfiguration(format!("Invalid value \"{}\" for \"{}\"", value, key))
+}
+
+fn unknown_key(key: &str) -> SteganographyError {
+    SteganographyError::InvalidConfiguration(format!("Unknown configuration key \"{}\"", key))
+}
+
+/// The quality used when writing JPEG images, unless configured otherwise
+const DEFAULT_JPEG_QUALITY: u8 = 75;
+
+/// The options of an `ImageWriter`, detached from the image it writes. Unlike a writer,
+/// a configuration can be stored and reused for any number of images
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageWriterConfig {
+    /// The compression used when writing PNG images
+    pub compression_type: CompressionType,
+
+    /// The filter applied before compression when writing PNG images
+    pub filter_type: FilterType,
+
+    /// The quality, from 1 to 100, used when writing JPEG images. Values out of range
+    /// are clamped when writing
+    pub jpeg_quality: u8,
+}
+
+impl Default for ImageWriterConfig {
+    fn default() -> Self {
+        Self {
+            compression_type: CompressionType::Default,
+            filter_type: FilterType::NoFilter,
+            jpeg_quality: DEFAULT_JPEG_QUALITY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImageWriterConfig;
+    use crate::{decoder::ImageDecoder, encoder::{ImageEncoder, ImageWriter}, error::SteganographyError, prelude::*};
+
+    #[test]
+    fn writer_config_reuse() {
+        let config = ImageWriterConfig {
+            compression_type: CompressionType::Best,
+            ..ImageWriterConfig::default()
+        };
+        assert_eq!(config.filter_type, FilterType::NoFilter);
+
+        let first = ImageEncoder::default().encode_bytes(b"seagul").unwrap();
+        let second = ImageEncoder::default().encode_bytes(b"seagull").unwrap();
+        for encoded in [&first, &second] {
+            let writer = ImageWriter::from_config(encoded, config.clone());
+            assert_eq!(writer.config(), config);
+            let png = writer.to_vec(ImageFormat::Png).unwrap();
+            assert!(image::load_from_memory(&png).unwrap() == *encoded.altered_image());
+        }
+    }
+
+    #[test]
+    fn config_mirrors_options() {
+        let encoder = ImageEncoder::default().with_lsb(2).with_offset(7).with_padding("pad");
+        let config = encoder.config();
+        assert_eq!(config.lsb_c, 2);
+        assert_eq!(config.offset, 7);
+        assert_eq!(config.padding.as_deref(), Some("pad"));
+        assert_eq!(config.channel_order, vec![RgbChannel::Blue]);
+
+        let decoder = ImageDecoder::default().with_region(1, 2, 3, 4).with_marker(Some("--"));
+        let config = decoder.config();
+        assert_eq!(config.region, Some((1, 2, 3, 4)));
+        assert_eq!(config.marker.as_deref(), Some(&b"--"[..]));
+        assert_eq!(config.encoding_position, ImagePosition::TopLeft);
+    }
+
+    #[test]
+    fn parse_configs() {
+        use super::{DecoderConfig, EncoderConfig};
+
+        let config: EncoderConfig = "lsb=2,channel=blue,skip=3,position=top-left".parse().unwrap();
+        assert_eq!(
+            config,
+            ImageEncoder::default()
+                .with_lsb(2)
+                .with_channel(RgbChannel::Blue)
+                .with_step_by_n_pixels(3)
+                .with_position(ImagePosition::TopLeft)
+                .config()
+        );
+
+        let config: EncoderConfig = "channel=red+green, position=at(3, 4), region=1:2:10:12, bit-order=msb-first, ecc=true"
+            .parse()
+            .unwrap();
+        assert_eq!(config.channel_order, vec![RgbChannel::Red, RgbChannel::Green]);
+        assert_eq!(config.encoding_position, ImagePosition::At(3, 4));
+        assert_eq!(config.region, Some((1, 2, 10, 12)));
+        assert_eq!(config.bit_order, BitOrder::MsbFirst);
+        assert!(config.ecc);
+
+        let config: DecoderConfig = "lsb=3,marker=--,max-bytes=64".parse().unwrap();
+        assert_eq!(config.lsb_c, 3);
+        assert_eq!(config.marker.as_deref(), Some(&b"--"[..]));
+        assert_eq!(config.max_bytes, Some(64));
+
+        for invalid in ["lsb=two", "colour=blue", "channel=purple", "position=middle", "region=1:2:3", "lsb"] {
+            assert!(
+                matches!(invalid.parse::<EncoderConfig>(), Err(SteganographyError::InvalidConfiguration(_))),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn apply_parsed_config() {
+        let mut encoder = ImageEncoder::default();
+        encoder.apply_config("lsb=2,skip=3,offset=5,padding=pad".parse().unwrap());
+        assert_eq!(encoder.get_use_n_lsb(), 2);
+        assert_eq!(encoder.get_step_by_n_pixels(), 3);
+        assert_eq!(encoder.get_offset(), 5);
+        assert_eq!(encoder.get_padding(), Some("pad"));
+        let encoded = encoder.encode_bytes(b"seagul").unwrap();
+
+        let mut decoder = ImageDecoder::from(encoded.altered_image().clone());
+        decoder.apply_config("lsb=2,skip=3,offset=5,max-bytes=6".parse().unwrap());
+        assert_eq!(decoder.decode().unwrap().as_raw(), "seagul");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_configs() {
+        use super::{DecoderConfig, EncoderConfig};
+
+        let encoder = ImageEncoder::default()
+            .with_lsb(2)
+            .with_channel_order(vec![RgbChannel::Red, RgbChannel::Blue])
+            .with_position(ImagePosition::At(3, 4))
+            .with_padding("pad")
+            .with_region(1, 2, 10, 12);
+        let encoder_config = encoder.config();
+        let json = serde_json::to_string(&encoder_config).unwrap();
+        assert_eq!(serde_json::from_str::<EncoderConfig>(&json).unwrap(), encoder_config);
+
+        let decoder = ImageDecoder::default()
+            .with_step_by_n_pixels(3)
+            .with_spread(true)
+            .with_marker(Some(b"--"));
+        let decoder_config = decoder.config();
+        let json = serde_json::to_string(&decoder_config).unwrap();
+        assert_eq!(serde_json::from_str::<DecoderConfig>(&json).unwrap(), decoder_config);
+
+        let writer_config = ImageWriterConfig {
+            filter_type: FilterType::Paeth,
+            ..ImageWriterConfig::default()
+        };
+        let json = serde_json::to_string(&writer_config).unwrap();
+        assert_eq!(serde_json::from_str::<ImageWriterConfig>(&json).unwrap(), writer_config);
+    }
+}
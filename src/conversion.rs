@@ -1,11 +1,42 @@
-use bitvec::{order::Lsb0, ptr::BitPtr, slice::BitSlice};
+use bitvec::{order::Lsb0, slice::BitSlice, view::BitView};
 
-pub fn byte_to_bits(byte: &u8) -> Option<&BitSlice<Lsb0, u8>> {
-    let raw_bits = bitvec::ptr::bitslice_from_raw_parts::<Lsb0, u8>(BitPtr::from_ref(byte), 8);
-    let bits;
-    unsafe {
-        bits = raw_bits.as_ref();
+/// Views the bits of `byte`, least significant first. The returned slice borrows from `byte`.
+pub fn byte_to_bits(byte: &u8) -> &BitSlice<Lsb0, u8> {
+    byte.view_bits::<Lsb0>()
+}
+
+/// XORs each byte of `data` with the byte of `key` at the same index, counted from `start`
+/// and wrapping around the end of `key`. Does nothing if `key` is empty
+pub(crate) fn apply_xor_key(data: &mut [u8], key: &[u8], start: usize) {
+    if key.is_empty() {
+        return;
+    }
+
+    for (index, byte) in data.iter_mut().enumerate() {
+        *byte ^= key[(start + index) % key.len()];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_xor_key, byte_to_bits};
+
+    #[test]
+    fn bits_least_significant_first() {
+        for byte in 0..=u8::MAX {
+            for (i, bit) in byte_to_bits(&byte).iter().enumerate() {
+                assert_eq!(*bit, (byte >> i) & 1 == 1);
+            }
+        }
     }
 
-    bits
+    #[test]
+    fn xor_key() {
+        let mut data = *b"seagul";
+        apply_xor_key(&mut data, &[0x01, 0x02], 1);
+        assert_eq!(data, [b's' ^ 0x02, b'e' ^ 0x01, b'a' ^ 0x02, b'g' ^ 0x01, b'u' ^ 0x02, b'l' ^ 0x01]);
+
+        apply_xor_key(&mut data, &[0x01, 0x02], 1);
+        assert_eq!(&data, b"seagul");
+    }
 }
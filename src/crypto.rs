@@ -0,0 +1,128 @@
+use std::fmt::Display;
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+
+/// Size in bytes of the keys used to encrypt payloads
+pub const KEY_SIZE: usize = 32;
+
+/// Size in bytes of the nonce prepended to an encrypted payload
+pub const NONCE_SIZE: usize = 12;
+
+/// Size in bytes of the authentication tag appended to the ciphertext
+pub const TAG_SIZE: usize = 16;
+
+/// The number of bytes encryption adds to a payload, nonce included
+pub const OVERHEAD: usize = NONCE_SIZE + TAG_SIZE;
+
+/// A key held by encoders and decoders, which is not printed by `Debug`
+#[derive(Clone, PartialEq)]
+pub(crate) struct SecretKey(pub(crate) [u8; KEY_SIZE]);
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+/// Enumerates the reasons encrypted data can not be decrypted
+#[derive(Debug, Clone, PartialEq)]
+pub enum CryptoError {
+    /// The data has the given length, which is too short to hold a nonce and a tag
+    TruncatedData(usize),
+
+    /// The data has been tampered with, or it was encrypted with a different key
+    AuthenticationFailed,
+}
+
+impl Display for CryptoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CryptoError::TruncatedData(len) => {
+                write!(f, "{} bytes are too few to hold encrypted data", len)
+            }
+            CryptoError::AuthenticationFailed => {
+                write!(f, "The data is corrupted or the key is wrong")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+/// Encrypts `data` with ChaCha20-Poly1305. The returned ciphertext is followed by
+/// a `TAG_SIZE` bytes authentication tag.
+///
+/// A nonce must never be used twice with the same key
+pub fn encrypt(data: &[u8], key: &[u8; KEY_SIZE], nonce: &[u8; NONCE_SIZE]) -> Vec<u8> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(nonce), data)
+        .expect("Payload too large to be encrypted")
+}
+
+/// Decrypts data produced by `encrypt` with the same key and nonce, failing if
+/// it does not pass authentication
+pub fn decrypt(data: &[u8], key: &[u8; KEY_SIZE], nonce: &[u8; NONCE_SIZE]) -> Result<Vec<u8>, CryptoError> {
+    if data.len() < TAG_SIZE {
+        return Err(CryptoError::TruncatedData(data.len()));
+    }
+
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), data)
+        .map_err(|_| CryptoError::AuthenticationFailed)
+}
+
+/// Encrypts `data` with a random nonce, which is prepended to the ciphertext
+pub(crate) fn seal(data: &[u8], key: &[u8; KEY_SIZE]) -> Vec<u8> {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce.copy_from_slice(&ChaCha20Poly1305::generate_nonce(&mut OsRng));
+
+    let mut sealed = Vec::with_capacity(data.len() + OVERHEAD);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&encrypt(data, key, &nonce));
+    sealed
+}
+
+/// Decrypts data produced by `seal`
+pub(crate) fn open(sealed: &[u8], key: &[u8; KEY_SIZE]) -> Result<Vec<u8>, CryptoError> {
+    if sealed.len() < OVERHEAD {
+        return Err(CryptoError::TruncatedData(sealed.len()));
+    }
+
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce.copy_from_slice(&sealed[..NONCE_SIZE]);
+    decrypt(&sealed[NONCE_SIZE..], key, &nonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt, encrypt, open, seal, CryptoError, OVERHEAD, TAG_SIZE};
+
+    const KEY: [u8; 32] = [7; 32];
+
+    #[test]
+    fn roundtrip() {
+        let nonce = [1; 12];
+        let ciphertext = encrypt(b"seagul", &KEY, &nonce);
+        assert_eq!(ciphertext.len(), 6 + TAG_SIZE);
+        assert_ne!(&ciphertext[..6], b"seagul");
+        assert_eq!(decrypt(&ciphertext, &KEY, &nonce).unwrap(), b"seagul");
+
+        let sealed = seal(b"seagul", &KEY);
+        assert_eq!(sealed.len(), 6 + OVERHEAD);
+        assert_ne!(seal(b"seagul", &KEY), sealed, "Nonces should be random");
+        assert_eq!(open(&sealed, &KEY).unwrap(), b"seagul");
+    }
+
+    #[test]
+    fn rejects_tampering() {
+        let mut sealed = seal(b"seagul", &KEY);
+        assert_eq!(open(&sealed, &[8; 32]), Err(CryptoError::AuthenticationFailed));
+
+        sealed[OVERHEAD] ^= 1;
+        assert_eq!(open(&sealed, &KEY), Err(CryptoError::AuthenticationFailed));
+        assert_eq!(open(&sealed[..10], &KEY), Err(CryptoError::TruncatedData(10)));
+    }
+}
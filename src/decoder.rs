@@ -1,37 +1,134 @@
-use std::{borrow::Cow, fs::File, string::FromUtf8Error, time::Duration};
+use std::{borrow::Cow, convert::{TryFrom, TryInto}, fs::File, io::{Cursor, Read}, path::{Path, PathBuf}, string::FromUtf8Error, time::Duration};
 
-use bitvec::{order::Lsb0, view::BitView};
-use image::{DynamicImage, EncodableLayout};
+use bitvec::{order::Lsb0, vec::BitVec, view::BitView};
+use image::{DynamicImage, EncodableLayout, GenericImageView};
 
-use crate::prelude::{ImagePosition, ImageRules, RgbChannel};
-
-const BYTE_STEP: usize = std::mem::size_of::<u8>() * 8;
+#[cfg(feature = "crypto")]
+use crate::crypto::{self, SecretKey, KEY_SIZE};
+#[cfg(feature = "crypto")]
+use crate::key_derivation::{derive_key, DEFAULT_ITERATIONS, SALT_SIZE};
+use crate::{capacity, config::DecoderConfig, ecc::decode_pair, error::SteganographyError, header::{crc32, read_length_framed, FileHeader, Header, BYTE_STEP, CRC_SIZE, HEADER_SIZE, LENGTH_SIZE}, metadata::{Metadata, METADATA_SIZE}, permutation::pixel_order, pixel_selectors::{clamp_region, region_dimensions, LinearPixelSelector, PixelSelector, Region, SelectorConfig}, prelude::{channel_indices, is_grayscale, BitOrder, ImagePosition, ImageRules, RgbChannel}};
 
+#[derive(Clone)]
 pub struct DecodedImage {
-    data: Vec<u8>,
+    data: Cursor<Vec<u8>>,
     hit_marker: bool,
     elapsed: std::time::Duration,
+    pixels_read: usize,
+    bits: BitVec<Lsb0, u8>,
+    config: DecoderConfig,
 }
 
 impl DecodedImage {
     /// The time it took to decode the image
-    pub fn decode_time(&self) -> &Duration {
-        &self.elapsed
+    pub fn decode_time(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The time it took to decode the image, in microseconds
+    pub fn decode_time_micros(&self) -> u128 {
+        self.elapsed.as_micros()
+    }
+
+    /// The number of pixels read to decode the data
+    pub fn decoded_pixel_count(&self) -> usize {
+        self.pixels_read
+    }
+
+    /// The bits read from the image, in the order they were read and before being assembled
+    /// into bytes. The bit order and error correction are not applied to them
+    pub fn as_bitvec(&self) -> BitVec<Lsb0, u8> {
+        self.bits.clone()
+    }
+
+    /// The configuration of the decoder that produced this image, at the time of decoding.
+    /// For self describing images, this is the configuration read from their metadata
+    pub fn decoder_config(&self) -> DecoderConfig {
+        self.config.clone()
+    }
+
+    /// The number of bits read from the image. Bits of a trailing incomplete byte are
+    /// counted, even if they are not part of `embedded_data`
+    pub fn decoded_bit_count(&self) -> usize {
+        self.bits.len()
     }
 
     /// Decoded data as a raw string
-    pub fn as_raw(&self) -> Cow<str> {
-        String::from_utf8_lossy(&self.data)
+    pub fn as_raw(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.data.get_ref())
     }
 
     /// Tries to view the decoded data as valid Utf8
     pub fn as_string(&self) -> Result<String, FromUtf8Error> {
-        String::from_utf8(self.data.clone())
+        String::from_utf8(self.data.get_ref().clone())
+    }
+
+    /// Tries to view the decoded data up to the first null byte, excluded, as valid Utf8.
+    /// The decoded data is left untouched
+    pub fn as_string_trimmed(&self) -> Result<String, FromUtf8Error> {
+        String::from_utf8(self.data.get_ref()[..self.null_position()].to_vec())
+    }
+
+    /// Discards the decoded data from the first null byte onwards. Useful when the
+    /// payload is shorter than the decoded area, whose unused bits are usually zero
+    pub fn truncate_at_null(&mut self) -> &mut Self {
+        let null_position = self.null_position();
+        self.data.get_mut().truncate(null_position);
+        self
+    }
+
+    /// The index of the first null byte in the decoded data, or its length if there is none
+    fn null_position(&self) -> usize {
+        let data = self.data.get_ref();
+        data.iter().position(|byte| *byte == 0).unwrap_or(data.len())
+    }
+
+    /// Discards the longest tail of the decoded data that is a prefix of `padding` repeated
+    /// over and over. The tail does not have to end on a full repetition, so any trailing
+    /// part of the padding is stripped too: with `gull` as padding, `seagul` becomes `sea`.
+    ///
+    /// The tail is found in linear time, by matching the data against the repeated padding
+    /// with the Knuth-Morris-Pratt algorithm: once every byte is consumed, the length matched
+    /// is the one of the longest tail
+    fn strip_padding(&mut self, padding: &[u8]) {
+        if padding.is_empty() {
+            return;
+        }
+
+        let data = self.data.get_mut();
+        let repeated = |index: usize| padding[index % padding.len()];
+
+        // For each prefix of the repeated padding, the length of its longest proper prefix
+        // that is also a suffix of it
+        let mut fallback = vec![0; data.len()];
+        let mut matched = 0;
+        for index in 1..data.len() {
+            while matched > 0 && repeated(index) != repeated(matched) {
+                matched = fallback[matched - 1];
+            }
+            if repeated(index) == repeated(matched) {
+                matched += 1;
+            }
+            fallback[index] = matched;
+        }
+
+        let mut matched = 0;
+        for byte in data.iter() {
+            while matched > 0 && *byte != repeated(matched) {
+                matched = fallback[matched - 1];
+            }
+            if *byte == repeated(matched) {
+                matched += 1;
+            }
+        }
+
+        let padding_start = data.len() - matched;
+        data.truncate(padding_start);
     }
 
     /// Gets a reference to the decoded byte array
     pub fn embedded_data(&self) -> &Vec<u8> {
-        &self.data
+        self.data.get_ref()
     }
 
     /// If this is true, decoding stopped by hitting a marker specified in the
@@ -45,132 +142,775 @@ impl DecodedImage {
     where
         W: std::io::Write,
     {
-        w.write_all(self.data.as_bytes())
+        w.write_all(self.data.get_ref().as_bytes())
+    }
+}
+
+/// Reads the decoded bytes sequentially. The read position is independent from the other
+/// accessors, which always expose the whole decoded data
+impl Read for DecodedImage {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.data.read(buf)
     }
 }
 
 /// An image decoder tries to find data encoded into an image's pixels. Supports the same
 /// configuration options as the `ImageEncoder`
-#[derive(Debug)]
-pub struct ImageDecoder<'a> {
+#[derive(Debug, Clone)]
+pub struct ImageDecoder {
     lsb_c: usize,
     skip_c: usize,
-    encoding_channel: RgbChannel,
+    channel_order: Vec<RgbChannel>,
     offset: usize,
     spread: bool,
+    spread_stride: usize,
     encoding_position: ImagePosition,
-    marker: Option<&'a [u8]>,
+    permutation_seed: Option<u64>,
+    pixel_selector: Box<dyn PixelSelector>,
+    region: Option<Region>,
+    bit_order: BitOrder,
+    ecc: bool,
+    #[cfg(feature = "crypto")]
+    key: Option<SecretKey>,
+    xor_key: Option<Vec<u8>>,
+    padding: Option<String>,
+    marker: Option<Vec<u8>>,
+    max_bytes: Option<usize>,
     source_image: DynamicImage,
 }
 
-impl<'a> From<&str> for ImageDecoder<'a> {
-    fn from(path: &str) -> Self {
-        let mut file = File::open(path).expect("Image not found");
-        Self::from(&mut file as &mut dyn std::io::Read)
+impl TryFrom<&str> for ImageDecoder {
+    type Error = SteganographyError;
+
+    /// Loads the image at `path`, failing if it can not be read or is not a supported image
+    fn try_from(path: &str) -> Result<Self, Self::Error> {
+        Self::try_from(Path::new(path))
     }
 }
 
-impl<'a, R: std::io::Read + ?Sized> From<&mut R> for ImageDecoder<'a> {
-    fn from(readable: &mut R) -> Self {
-        let mut source_data: Vec<u8> = Vec::new();
-        readable
-            .read_to_end(&mut source_data)
-            .expect("Cannot load image from this path");
+impl TryFrom<&Path> for ImageDecoder {
+    type Error = SteganographyError;
+
+    /// Loads the image at `path`, failing if it can not be read or is not a supported image
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let mut file = File::open(path).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("Cannot open {}: {}", path.display(), e))
+        })?;
+        Self::from_reader(&mut file)
+    }
+}
 
-        let img = image::load_from_memory(source_data.as_bytes()).unwrap();
+impl TryFrom<PathBuf> for ImageDecoder {
+    type Error = SteganographyError;
 
-        let mut this = Self::default();
-        this.source_image = img;
-        this
+    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        Self::try_from(path.as_path())
     }
 }
 
-impl<'a> Default for ImageDecoder<'a> {
+impl TryFrom<&mut dyn std::io::Read> for ImageDecoder {
+    type Error = SteganographyError;
+
+    fn try_from(readable: &mut dyn std::io::Read) -> Result<Self, Self::Error> {
+        Self::from_reader(readable)
+    }
+}
+
+impl TryFrom<&[u8]> for ImageDecoder {
+    type Error = SteganographyError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(data)
+    }
+}
+
+impl From<DynamicImage> for ImageDecoder {
+    fn from(img: DynamicImage) -> Self {
+        Self::from_image(img)
+    }
+}
+
+impl Default for ImageDecoder {
+    /// Same as `ImageDecoder::new`
     fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImageDecoder {
+    /// Creates a decoder with the default settings: one least significant bit of the blue
+    /// channel of every pixel, from the top left corner, up to the end of the image.
+    ///
+    /// Its source image is a blank 16x16 image. To decode an actual image, load it with
+    /// `TryFrom<&Path>`, `from_reader`, `from_bytes` or `from_image` instead, which use
+    /// these same settings
+    pub fn new() -> Self {
         Self {
             lsb_c: 1,
             skip_c: 1,
             offset: 0,
             spread: false,
+            spread_stride: 0,
             marker: None,
+            max_bytes: None,
             encoding_position: ImagePosition::TopLeft,
-            encoding_channel: RgbChannel::Blue,
+            permutation_seed: None,
+            pixel_selector: Box::new(LinearPixelSelector),
+            region: None,
+            bit_order: BitOrder::LsbFirst,
+            ecc: false,
+            #[cfg(feature = "crypto")]
+            key: None,
+            xor_key: None,
+            padding: None,
+            channel_order: vec![RgbChannel::Blue],
             source_image: DynamicImage::new_rgb8(16, 16),
         }
     }
-}
 
-impl<'a> ImageDecoder<'a> {
-    pub fn new() -> Self {
-        Self::default()
+    /// Creates a decoder for an image already loaded in memory, taking ownership of it.
+    /// Grayscale images are read from `RgbChannel::Luma` by default
+    pub fn from_image(img: DynamicImage) -> Self {
+        let mut decoder = Self {
+            source_image: img,
+            ..Self::new()
+        };
+        if is_grayscale(&decoder.source_image) {
+            decoder.channel_order = vec![RgbChannel::Luma];
+        }
+
+        decoder
+    }
+
+    /// Creates a decoder for `img` reading the pixels and channels described by `rules`.
+    /// The padding, marker and key are not part of the rules and are left unset
+    pub(crate) fn from_rules<R>(img: DynamicImage, rules: &R) -> Self
+    where
+        R: ImageRules,
+    {
+        let mut decoder = ImageDecoder::from_image(img);
+        decoder
+            .set_use_n_lsb(rules.get_use_n_lsb())
+            .set_offset(rules.get_offset())
+            .set_step_by_n_pixels(rules.get_step_by_n_pixels())
+            .set_channel_order(rules.get_channel_order().to_vec())
+            .set_spread(rules.get_spread())
+            .set_spread_stride(rules.get_spread_stride())
+            .set_position(rules.get_position().clone())
+            .set_permutation_seed(rules.get_permutation_seed())
+            .set_pixel_selector(rules.get_pixel_selector().clone_box())
+            .set_bit_order(rules.get_bit_order())
+            .set_ecc(rules.get_ecc());
+        if let Some((x1, y1, x2, y2)) = rules.get_region() {
+            decoder.set_region(x1, y1, x2, y2);
+        }
+
+        decoder
+    }
+
+    /// Creates a decoder for an encoded image (PNG, JPEG, BMP...) read from `reader`, failing
+    /// if it can not be read or is not a supported image
+    pub fn from_reader<R>(reader: &mut R) -> Result<Self, SteganographyError>
+    where
+        R: std::io::Read + ?Sized,
+    {
+        let mut source_data: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut source_data)?;
+
+        Self::from_bytes(&source_data)
+    }
+
+    /// Creates a decoder for an encoded image (PNG, JPEG, BMP...) held in memory, failing
+    /// if it is not a supported image
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        let img = image::load_from_memory(data)?;
+
+        Ok(Self::from_image(img))
+    }
+
+    /// The options of this decoder, detached from its source image
+    pub fn config(&self) -> DecoderConfig {
+        DecoderConfig {
+            lsb_c: self.lsb_c,
+            skip_c: self.skip_c,
+            offset: self.offset,
+            spread: self.spread,
+            spread_stride: self.spread_stride,
+            channel_order: self.channel_order.clone(),
+            encoding_position: self.encoding_position.clone(),
+            region: self.region,
+            bit_order: self.bit_order,
+            ecc: self.ecc,
+            padding: self.padding.clone(),
+            marker: self.marker.clone(),
+            max_bytes: self.max_bytes,
+        }
+    }
+
+    /// Applies the options of `config` to this decoder, keeping its source image,
+    /// pixel selector and password
+    pub fn apply_config(&mut self, config: DecoderConfig) -> &mut Self {
+        self.lsb_c = config.lsb_c;
+        self.skip_c = config.skip_c;
+        self.offset = config.offset;
+        self.spread = config.spread;
+        self.spread_stride = config.spread_stride;
+        self.channel_order = config.channel_order;
+        self.encoding_position = config.encoding_position;
+        self.region = config.region;
+        self.bit_order = config.bit_order;
+        self.ecc = config.ecc;
+        self.padding = config.padding;
+        self.marker = config.marker;
+        self.max_bytes = config.max_bytes;
+        self
     }
 
     /// Specifies a byte sequence to look for and stop deconding when found.
-    pub fn until_marker(&mut self, marker_sequence: Option<&'a [u8]>) -> &mut Self {
-        self.marker = marker_sequence;
+    pub fn until_marker<M>(&mut self, marker_sequence: Option<M>) -> &mut Self
+    where
+        M: AsRef<[u8]>,
+    {
+        self.marker = marker_sequence.map(|marker| marker.as_ref().to_vec());
+        self
+    }
+
+    /// Consuming version of `until_marker`
+    pub fn with_marker<M>(mut self, marker_sequence: Option<M>) -> Self
+    where
+        M: AsRef<[u8]>,
+    {
+        self.until_marker(marker_sequence);
+        self
+    }
+
+    /// Sets the maximum number of bytes read by `decode`, which stops without error once
+    /// they are reached. Bounds the memory used to decode untrusted
+    /// images, whose end is not known in advance. Unlike `decode_exact`, fewer bytes are
+    /// returned when the image holds fewer. Decoding methods reading a known length
+    /// are not affected
+    pub fn set_max_bytes(&mut self, n: usize) -> &mut Self {
+        self.max_bytes = Some(n);
+        self
+    }
+
+    /// Consuming version of `set_max_bytes`
+    pub fn with_max_bytes(mut self, n: usize) -> Self {
+        self.set_max_bytes(n);
+        self
+    }
+
+    /// Sets the key to XOR the decoded data with, undoing `ImageEncoder::set_xor_key` with
+    /// the same key. Markers are looked for in the data once XORed back. An empty key
+    /// disables it.
+    ///
+    /// This is only meant to prevent casual reading of the payload, and provides no secrecy
+    pub fn set_xor_key(&mut self, key: &[u8]) -> &mut Self {
+        self.xor_key = Some(key.to_vec()).filter(|key| !key.is_empty());
+        self
+    }
+
+    /// Consuming version of `set_xor_key`
+    pub fn with_xor_key(mut self, key: &[u8]) -> Self {
+        self.set_xor_key(key);
         self
     }
 
-    pub fn decode(&self) -> Result<DecodedImage, String> {
+    /// Sets the key used to decrypt payloads encrypted by an `ImageEncoder` with the same key
+    #[cfg(feature = "crypto")]
+    pub fn set_key(&mut self, key: &[u8; KEY_SIZE]) -> &mut Self {
+        self.key = Some(SecretKey(*key));
+        self
+    }
+
+    /// Consuming version of `set_key`
+    #[cfg(feature = "crypto")]
+    pub fn with_key(mut self, key: &[u8; KEY_SIZE]) -> Self {
+        self.set_key(key);
+        self
+    }
+
+    /// Sets the key used to decrypt the payload to the one derived from `password` and `salt`
+    /// by `key_derivation::derive_key`, with `key_derivation::DEFAULT_ITERATIONS` rounds.
+    /// Unlike `set_password`, this does not change the order pixels are visited in
+    #[cfg(feature = "crypto")]
+    pub fn set_password_with_salt(&mut self, password: &str, salt: &[u8; SALT_SIZE]) -> &mut Self {
+        self.set_key(&derive_key(password, salt, DEFAULT_ITERATIONS))
+    }
+
+    /// Decodes data from the source image, until either the configured marker
+    /// is found or the image ends.
+    ///
+    /// When a padding is configured and no marker is hit, the trailing padding written
+    /// by the encoder is discarded. Data that ends with a full repetition of the padding
+    /// loses it too.
+    ///
+    /// With error correction enabled, decoding also stops at the first block that can not
+    /// be corrected, as it usually marks the end of the encoded data.
+    ///
+    /// With a key set, all the data read is decrypted, so the end of the encrypted payload
+    /// must be found through the padding. Prefer the other decoding methods in this case.
+    ///
+    /// Spread payloads can only be decoded this way with an explicit spread stride, as the
+    /// computed one depends on the payload length. Use `decode_spread` when it is known
+    pub fn decode(&self) -> Result<DecodedImage, SteganographyError> {
+        let step = self.spread_step()?;
+        let mut decoded = self.decode_data(self.marker.as_deref().unwrap_or(&[]), None, 0, step)?;
+        if let Some(padding) = self.padding.as_deref() {
+            if !decoded.hit_marker {
+                decoded.strip_padding(padding.as_bytes());
+            }
+        }
+
+        self.open(decoded)
+    }
+
+    /// Decodes exactly `n` bytes from the source image, ignoring any configured marker.
+    /// Fails if the image does not hold enough data.
+    ///
+    /// With a key set, `n` is the length of the decrypted payload. Spread payloads are read
+    /// like `decode_spread` does
+    pub fn decode_exact(&self, n: usize) -> Result<DecodedImage, SteganographyError> {
+        if self.spread {
+            return self.decode_spread(n);
+        }
+
+        let n = n + self.encryption_overhead();
+        let decoded = self.read_exact(n, 0, self.skip_c)?;
+        self.open(decoded)
+    }
+
+    /// Decodes exactly `payload_len` bytes spread across the source image, whether spreading
+    /// is enabled on this decoder or not. Unless a spread stride is set, the stride is
+    /// computed from `payload_len` the same way the encoder does.
+    ///
+    /// With a key set, `payload_len` is the length of the decrypted payload
+    pub fn decode_spread(&self, payload_len: usize) -> Result<DecodedImage, SteganographyError> {
+        let n = payload_len + self.encryption_overhead();
+        let stride = match self.spread_stride {
+            0 => capacity::computed_spread_stride(self.source_image.dimensions(), n, self, 0),
+            stride => stride,
+        };
+        let decoded = self.read_exact(n, 0, self.skip_c * stride)?;
+        self.open(decoded)
+    }
+
+    /// Reads exactly `n` bytes from the source image, without decrypting them, skipping
+    /// `reserved_pixels` more pixels than the configured ones and using one pixel every `step`
+    fn read_exact(&self, n: usize, reserved_pixels: usize, step: usize) -> Result<DecodedImage, SteganographyError> {
+        let decoded = self.decode_data(&[], Some(n), reserved_pixels, step)?;
+
+        if decoded.embedded_data().len() < n {
+            Err(SteganographyError::InsufficientCapacity {
+                required: n,
+                available: decoded.embedded_data().len(),
+            })
+        } else {
+            Ok(decoded)
+        }
+    }
+
+    /// Decodes data encoded with `ImageEncoder::encode_with_header`, reading the `Header`
+    /// first and then exactly the payload it describes. Fails if the header version is not
+    /// supported or the payload does not match its checksum. Any configured marker is ignored.
+    ///
+    /// Encrypted payloads are decrypted with the configured key, and fail to decode without one.
+    /// Whether the payload is spread is read from the header too
+    pub fn decode_with_header(&self) -> Result<DecodedImage, SteganographyError> {
+        let header_bytes = self.read_exact(HEADER_SIZE, 0, self.skip_c)?;
+        let mut encoded_header = [0u8; HEADER_SIZE];
+        encoded_header.copy_from_slice(header_bytes.embedded_data());
+        let header = Header::decode(&encoded_header)?;
+        let required = HEADER_SIZE + header.payload_len as usize;
+
+        // Avoids reading the whole image when the header is not valid
+        let available = capacity::bytes_available(self.source_image.dimensions(), self);
+        if required > available {
+            return Err(SteganographyError::InsufficientCapacity {
+                required,
+                available,
+            });
+        }
+
+        let decoded = if header.is_spread() {
+            // The header is not spread, the payload starts right after it
+            let header_pixels = capacity::pixels_spanned(HEADER_SIZE, self);
+            let payload_len = header.payload_len as usize;
+            let stride = match self.spread_stride {
+                0 => capacity::computed_spread_stride(
+                    self.source_image.dimensions(),
+                    payload_len,
+                    self,
+                    header_pixels,
+                ),
+                stride => stride,
+            };
+            let mut decoded = self.read_exact(payload_len, header_pixels, self.skip_c * stride)?;
+            decoded.pixels_read += header_bytes.pixels_read;
+            decoded
+        } else {
+            let mut decoded = self.read_exact(required, 0, self.skip_c)?;
+            decoded.data.get_mut().drain(..HEADER_SIZE);
+            decoded
+        };
+        let data = decoded.data.into_inner();
+        header.verify(&data)?;
+
+        let decoded = DecodedImage {
+            data: Cursor::new(data),
+            hit_marker: false,
+            elapsed: header_bytes.elapsed + decoded.elapsed,
+            pixels_read: decoded.pixels_read,
+            bits: decoded.bits,
+            config: decoded.config,
+        };
+
+        if header.is_encrypted() {
+            if !self.has_key() {
+                return Err(SteganographyError::InvalidConfiguration(String::from(
+                    "The payload is encrypted, a key is required to decode it",
+                )));
+            }
+            self.open(decoded)
+        } else {
+            Ok(decoded)
+        }
+    }
+
+    /// Decodes the shards produced by `ImageEncoder::encode_split`, given as encoded images
+    /// (PNG, BMP...) in the order they were encoded, and concatenates their payloads. Each
+    /// shard is decoded with `decode_with_header` and the settings of this decoder.
+    ///
+    /// The returned image reports the time, pixels and bits of all the shards
+    pub fn decode_multi(&self, images: Vec<&[u8]>) -> Result<DecodedImage, SteganographyError> {
+        let mut data = vec![];
+        let mut elapsed = Duration::default();
+        let mut pixels_read = 0;
+        let mut bits = BitVec::new();
+        for image in images {
+            let shard = ImageDecoder {
+                source_image: image::load_from_memory(image)?,
+                ..self.clone()
+            };
+            let mut decoded = shard.decode_with_header()?;
+            data.append(decoded.data.get_mut());
+            elapsed += decoded.elapsed;
+            pixels_read += decoded.pixels_read;
+            bits.extend_from_bitslice(&decoded.bits);
+        }
+
+        Ok(DecodedImage {
+            data: Cursor::new(data),
+            hit_marker: false,
+            elapsed,
+            pixels_read,
+            bits,
+            config: self.config(),
+        })
+    }
+
+    /// Decodes a file encoded with `ImageEncoder::encode_file` and writes it into `output_dir`,
+    /// under the name stored in its `FileHeader`. Returns the path of the written file.
+    ///
+    /// The `FileHeader` is read first, and then exactly the file it describes, so any
+    /// configured marker or padding is ignored. Fails if the image holds less data than the
+    /// header describes or if the stored name is not a plain file name. Encryption is not
+    /// supported
+    pub fn decode_file(&self, output_dir: &Path) -> Result<PathBuf, SteganographyError> {
+        if self.encryption_overhead() > 0 {
+            return Err(SteganographyError::InvalidConfiguration(String::from(
+                "Encryption is not supported when decoding files",
+            )));
+        }
+
+        let step = self.spread_step()?;
+        let filename_len = self.read_exact(1, 0, step)?.embedded_data()[0] as usize;
+        let header_bytes = self.read_exact(1 + filename_len + 4, 0, step)?;
+        let header = FileHeader::decode(header_bytes.embedded_data())?;
+        let required = header.encoded_len() + header.file_len as usize;
+
+        // Avoids reading the whole image when the header is not valid
+        let available = capacity::bytes_available(self.source_image.dimensions(), self);
+        if required > available {
+            return Err(SteganographyError::InsufficientCapacity {
+                required,
+                available,
+            });
+        }
+
+        let decoded = self.read_exact(required, 0, step)?;
+        let path = output_dir.join(header.file_name()?);
+        std::fs::write(&path, &decoded.embedded_data()[header.encoded_len()..])?;
+        Ok(path)
+    }
+
+    /// Decodes data encoded with `ImageEncoder::encode_self_describing`. The settings are
+    /// read from the `Metadata` stored in the image, and the ones of this decoder are ignored,
+    /// except for the keys. Fails if the image holds no valid metadata
+    pub fn decode_self_describing(&self) -> Result<DecodedImage, SteganographyError> {
+        let metadata_bytes = ImageDecoder::from_image(self.source_image.clone()).read_exact(METADATA_SIZE, 0, 1)?;
+        let mut encoded_metadata = [0u8; METADATA_SIZE];
+        encoded_metadata.copy_from_slice(metadata_bytes.embedded_data());
+        let metadata = Metadata::decode_from(&encoded_metadata)?;
+
+        let mut decoder = ImageDecoder::from_image(self.source_image.clone());
+        metadata.apply_to(&mut decoder);
+        decoder.xor_key = self.xor_key.clone();
+        #[cfg(feature = "crypto")]
+        {
+            decoder.key = self.key.clone();
+        }
+
+        let decoded = decoder.decode_with_header()?;
+        Ok(DecodedImage {
+            elapsed: metadata_bytes.elapsed + decoded.elapsed,
+            pixels_read: metadata_bytes.pixels_read + decoded.pixels_read,
+            ..decoded
+        })
+    }
+
+    /// Decodes data encoded with `ImageEncoder::encode_with_crc`, reading the payload length
+    /// first and then exactly the payload and its CRC32. Fails if the payload does not match
+    /// its checksum. Any configured marker is ignored.
+    ///
+    /// With a key set, the data is decrypted after being checked
+    pub fn decode_with_crc(&self) -> Result<DecodedImage, SteganographyError> {
+        let step = self.spread_step()?;
+        let length_bytes = self.read_exact(LENGTH_SIZE, 0, step)?;
+        let payload_len = u32::from_le_bytes(length_bytes.embedded_data().as_slice().try_into().unwrap()) as usize;
+        let required = LENGTH_SIZE + payload_len + CRC_SIZE;
+
+        // Avoids reading the whole image when the length is not valid
+        let available = capacity::bytes_available(self.source_image.dimensions(), self);
+        if required > available {
+            return Err(SteganographyError::InsufficientCapacity {
+                required,
+                available,
+            });
+        }
+
+        let decoded = self.read_exact(required, 0, step)?;
+        let data = decoded.embedded_data();
+        let payload = read_length_framed(data)?;
+        let expected = u32::from_le_bytes(data[LENGTH_SIZE + payload_len..].try_into().unwrap());
+        let got = crc32(payload);
+        if got != expected {
+            return Err(SteganographyError::ChecksumMismatch { expected, got });
+        }
+
+        let payload = payload.to_vec();
+        self.open(DecodedImage {
+            data: Cursor::new(payload),
+            ..decoded
+        })
+    }
+
+    /// Wheter a key is set to decrypt payloads
+    fn has_key(&self) -> bool {
+        #[cfg(feature = "crypto")]
+        if self.key.is_some() {
+            return true;
+        }
+
+        false
+    }
+
+    /// The number of bytes encryption adds to a payload, or 0 without a key
+    fn encryption_overhead(&self) -> usize {
+        #[cfg(feature = "crypto")]
+        if self.key.is_some() {
+            return crypto::OVERHEAD;
+        }
+
+        0
+    }
+
+    /// The step between two pixels holding a payload of unknown length. Spread payloads
+    /// can only be read this way with an explicit spread stride
+    fn spread_step(&self) -> Result<usize, SteganographyError> {
+        if self.spread && self.spread_stride == 0 {
+            return Err(SteganographyError::InvalidConfiguration(String::from(
+                "Spread payloads of unknown length require an explicit spread stride, use `decode_spread` instead",
+            )));
+        }
+
+        Ok(self.skip_c * capacity::spread_stride(self.source_image.dimensions(), 0, self))
+    }
+
+    /// Decrypts the data of `decoded` if a key is set, leaving it untouched otherwise
+    fn open(&self, decoded: DecodedImage) -> Result<DecodedImage, SteganographyError> {
+        #[cfg(feature = "crypto")]
+        if let Some(key) = &self.key {
+            let data = crypto::open(decoded.embedded_data(), &key.0)?;
+            return Ok(DecodedImage {
+                data: Cursor::new(data),
+                ..decoded
+            });
+        }
+
+        Ok(decoded)
+    }
+
+    /// Decodes data skipping `reserved_pixels` more pixels than the configured ones and
+    /// using one pixel every `step`
+    fn decode_data(
+        &self,
+        target_sequence: &[u8],
+        max_len: Option<usize>,
+        reserved_pixels: usize,
+        step: usize,
+    ) -> Result<DecodedImage, SteganographyError> {
         let start = std::time::Instant::now();
-        let decoding_channel = self.get_use_channel().into();
+        let channel_order = channel_indices(self.get_channel_order());
+
+        if channel_order.is_empty() {
+            return Err(SteganographyError::InvalidConfiguration(String::from(
+                "At least one color channel must be used for decoding",
+            )));
+        }
+
+        if !(1..=8).contains(&self.lsb_c) {
+            return Err(SteganographyError::InvalidConfiguration(format!(
+                "The number of least significant bits must be between 1 and 8, got {}",
+                self.lsb_c
+            )));
+        }
+
+        // `set_step_by_n_pixels` never sets a step of 0, which `step_by` would panic on
+        if step == 0 {
+            return Err(SteganographyError::InvalidConfiguration(String::from(
+                "The pixel step must be at least 1",
+            )));
+        }
+
         let mut decoded: Vec<u8> = Vec::with_capacity(100);
         let mut hit_marker = false;
-        let target_sequence = self.marker.unwrap_or(&[]);
-        let target_sequence_len = target_sequence.len();
         let img = &self.source_image;
-        let mut sequence_hint: Vec<u8> = Vec::with_capacity(target_sequence_len);
-        let mut current_byte: u8 = 0b0000_0000;
-        let mut current_byte_as_bits = current_byte.view_bits_mut::<Lsb0>();
-        let mut iter_count: usize = 0;
-        let rgb_img = img.to_rgb8();
-        'pixel_iter: for pixel in rgb_img
-            .enumerate_pixels()
-            .skip(self.offset)
-            .step_by(self.skip_c)
-        {
-            let pixel_lsb = pixel.2[decoding_channel].view_bits::<Lsb0>();
+        let mut marker_matcher = MarkerMatcher::new(target_sequence);
+        // The bits read and not assembled into a byte yet, the first one being the least
+        // significant. Less than a byte is left after each channel, so at most 15 are pending
+        let mut pending_bits: u16 = 0;
+        let mut pending_count: usize = 0;
+        let lsb_mask: u16 = (1 << self.lsb_c) - 1;
+        let mut bits: BitVec<Lsb0, u8> = BitVec::new();
+        // With error correction, the first block of the byte being decoded
+        let mut pending_block: Option<u8> = None;
+        let mut pixels_read: usize = 0;
+        let area = clamp_region(img.dimensions(), self.region);
+        let real_offset =
+            self.encoding_position.pixel_offset(region_dimensions(area)) + self.offset + reserved_pixels;
 
-            // take lsb_c from this pixel target channel
-            for i in 0..self.lsb_c {
-                current_byte_as_bits.set(iter_count, pixel_lsb[i]);
-                iter_count += 1;
+        // Grayscale images are read from their luma channel, and images with transparency
+        // including their alpha channel
+        let (raw_pixels, pixel_size) = if self.channel_order.contains(&RgbChannel::Luma) {
+            if !is_grayscale(img) {
+                return Err(SteganographyError::InvalidConfiguration(String::from(
+                    "The luma channel can only be used on grayscale images",
+                )));
             }
+            (img.to_luma8().into_raw(), 1)
+        } else if let DynamicImage::ImageRgb16(buffer) = img {
+            // 16 bit images hold data in the low byte of each channel
+            (buffer.iter().map(|value| *value as u8).collect(), 3)
+        } else if let DynamicImage::ImageRgba16(buffer) = img {
+            (buffer.iter().map(|value| *value as u8).collect(), 4)
+        } else if img.color().has_alpha() {
+            (img.to_rgba8().into_raw(), 4)
+        } else {
+            (img.to_rgb8().into_raw(), 3)
+        };
 
-            // Check if a single output byte is completed
-            if iter_count == BYTE_STEP {
-                decoded.push(current_byte);
-                if target_sequence_len != 0 {
-                    sequence_hint.push(current_byte);
+        if channel_order.iter().any(|channel| *channel >= pixel_size) {
+            return Err(SteganographyError::InvalidConfiguration(String::from(
+                "The alpha channel can only be used on images with transparency",
+            )));
+        }
 
-                    if sequence_hint.len() > target_sequence_len {
-                        sequence_hint.remove(0);
-                    }
+        let image_width = img.width() as usize;
+        let config = SelectorConfig {
+            start: real_offset,
+            step,
+        };
+        let pixel_order = pixel_order(
+            self.pixel_selector.as_ref(),
+            area,
+            &config,
+            self.permutation_seed,
+        );
+
+        'pixel_iter: for (x, y) in pixel_order {
+            let pixel_index = y as usize * image_width + x as usize;
+            let pixel = &raw_pixels[pixel_index * pixel_size..(pixel_index + 1) * pixel_size];
+            if max_len == Some(decoded.len()) {
+                break 'pixel_iter;
+            }
+            pixels_read += 1;
+
+            for decoding_channel in channel_order.iter() {
+                let pixel_lsb = pixel[*decoding_channel].view_bits::<Lsb0>();
+
+                // take lsb_c from this pixel target channel
+                pending_bits |= (pixel[*decoding_channel] as u16 & lsb_mask) << pending_count;
+                pending_count += self.lsb_c;
+                bits.extend_from_bitslice(&pixel_lsb[..self.lsb_c]);
+
+                // Check if a single output byte is completed. When `lsb_c` does not divide
+                // a byte, the bits left over belong to the next one
+                while pending_count >= BYTE_STEP {
+                    let raw_byte = self.bit_order.arrange(pending_bits as u8);
+                    pending_bits >>= BYTE_STEP;
+                    pending_count -= BYTE_STEP;
 
-                    if sequence_hint.len() == target_sequence_len {
-                        if sequence_hint.as_slice() == target_sequence {
-                            hit_marker = true;
-                            break 'pixel_iter;
+                    let current_byte = if !self.ecc {
+                        raw_byte
+                    } else if let Some(low_block) = pending_block.take() {
+                        match decode_pair(low_block, raw_byte, decoded.len()) {
+                            Ok(byte) => byte,
+                            // Without a known length, the first uncorrectable block is
+                            // taken as the end of the encoded data
+                            Err(_) if max_len.is_none() => break 'pixel_iter,
+                            Err(e) => return Err(e.into()),
                         }
+                    } else {
+                        pending_block = Some(raw_byte);
+                        continue;
+                    };
+
+                    let current_byte = match self.xor_key.as_deref() {
+                        Some(key) => current_byte ^ key[decoded.len() % key.len()],
+                        None => current_byte,
+                    };
+
+                    // Reads of unknown length stop early when a maximum is set
+                    if max_len.is_none() && matches!(self.max_bytes, Some(max) if decoded.len() >= max) {
+                        break 'pixel_iter;
+                    }
+                    decoded.push(current_byte);
+                    if max_len == Some(decoded.len()) {
+                        break 'pixel_iter;
+                    }
+                    if marker_matcher.push(current_byte) {
+                        hit_marker = true;
+                        break 'pixel_iter;
                     }
                 }
-                iter_count = 0;
-                current_byte = 0b0000_0000;
-                current_byte_as_bits = current_byte.view_bits_mut::<Lsb0>();
             }
         }
 
         let end = std::time::Instant::now();
         Ok(DecodedImage {
-            data: decoded,
+            data: Cursor::new(decoded),
             hit_marker,
             elapsed: (end - start),
+            pixels_read,
+            bits,
+            config: self.config(),
         })
     }
 }
 
-impl<'a> ImageRules for ImageDecoder<'_> {
-    /// Skip the first `offset` bytes in the source buffer
+impl ImageRules for ImageDecoder {
+    /// Skip the first `offset` pixels of the traversal
     fn set_offset(&mut self, offset: usize) -> &mut Self {
         self.offset = offset;
         self
@@ -185,12 +925,17 @@ impl<'a> ImageRules for ImageDecoder<'_> {
 
     /// Specifies wich color channel will be the one used to store information bits.
     fn set_use_channel(&mut self, channel: RgbChannel) -> &mut Self {
-        self.encoding_channel = channel;
+        self.channel_order = vec![channel];
+        self
+    }
+
+    fn set_channel_order(&mut self, order: Vec<RgbChannel>) -> &mut Self {
+        self.channel_order = order;
         self
     }
 
-    /// When decoding data, `n` pixels will be skipped after each edited pixel.
-    /// If `n < 1` is passed, it defaults to `1`.
+    /// When decoding data, one pixel every `n` pixels will be read, starting from the
+    /// offset pixel. If `n < 1` is passed, it defaults to `1`.
     fn set_step_by_n_pixels(&mut self, n: usize) -> &mut Self {
         // Not using `clamp` because we don't want to panic
         if n < 1 {
@@ -224,18 +969,415 @@ impl<'a> ImageRules for ImageDecoder<'_> {
     }
 
     fn get_use_channel(&self) -> &RgbChannel {
-        &self.encoding_channel
+        self.channel_order.first().unwrap_or(&RgbChannel::Blue)
+    }
+
+    fn get_channel_order(&self) -> &[RgbChannel] {
+        &self.channel_order
     }
 
     fn get_spread(&self) -> bool {
         self.spread
     }
 
+    fn set_spread_stride(&mut self, stride: usize) -> &mut Self {
+        self.spread_stride = stride;
+        self
+    }
+
+    fn get_spread_stride(&self) -> usize {
+        self.spread_stride
+    }
+
     fn get_position(&self) -> &ImagePosition {
         &self.encoding_position
     }
 
-    fn set_padding(&mut self, _: &str) -> &mut Self {
+    fn set_padding(&mut self, value: impl AsRef<str>) -> &mut Self {
+        self.padding = Some(String::from(value.as_ref()));
+        self
+    }
+
+    fn get_padding(&self) -> Option<&str> {
+        self.padding.as_deref()
+    }
+
+    fn set_permutation_seed(&mut self, seed: Option<u64>) -> &mut Self {
+        self.permutation_seed = seed;
+        self
+    }
+
+    fn get_permutation_seed(&self) -> Option<u64> {
+        self.permutation_seed
+    }
+
+    fn set_pixel_selector(&mut self, selector: Box<dyn PixelSelector>) -> &mut Self {
+        self.pixel_selector = selector;
+        self
+    }
+
+    fn get_pixel_selector(&self) -> &dyn PixelSelector {
+        self.pixel_selector.as_ref()
+    }
+
+    fn set_region(&mut self, x1: u32, y1: u32, x2: u32, y2: u32) -> &mut Self {
+        self.region = Some((x1, y1, x2, y2));
+        self
+    }
+
+    fn get_region(&self) -> Option<Region> {
+        self.region
+    }
+
+    fn set_bit_order(&mut self, order: BitOrder) -> &mut Self {
+        self.bit_order = order;
+        self
+    }
+
+    fn get_bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    fn set_ecc(&mut self, enabled: bool) -> &mut Self {
+        self.ecc = enabled;
         self
     }
+
+    fn get_ecc(&self) -> bool {
+        self.ecc
+    }
+}
+
+/// Finds a marker in a sequence of bytes pushed one at a time, using the Knuth-Morris-Pratt
+/// algorithm so that each byte is checked in amortized constant time
+struct MarkerMatcher<'a> {
+    marker: &'a [u8],
+    /// For each prefix of the marker, the length of its longest proper prefix that is
+    /// also a suffix of it
+    failure: Vec<usize>,
+    /// The length of the marker prefix matching the last bytes pushed
+    matched: usize,
+}
+
+impl<'a> MarkerMatcher<'a> {
+    fn new(marker: &'a [u8]) -> Self {
+        let mut failure = vec![0; marker.len()];
+        let mut matched = 0;
+        for i in 1..marker.len() {
+            while matched > 0 && marker[i] != marker[matched] {
+                matched = failure[matched - 1];
+            }
+            if marker[i] == marker[matched] {
+                matched += 1;
+            }
+            failure[i] = matched;
+        }
+
+        Self {
+            marker,
+            failure,
+            matched: 0,
+        }
+    }
+
+    /// Pushes the next byte, returning whether the bytes pushed so far end with the marker.
+    /// An empty marker is never found
+    fn push(&mut self, byte: u8) -> bool {
+        if self.marker.is_empty() {
+            return false;
+        }
+
+        while self.matched > 0 && self.marker[self.matched] != byte {
+            self.matched = self.failure[self.matched - 1];
+        }
+        if self.marker[self.matched] == byte {
+            self.matched += 1;
+        }
+
+        if self.matched == self.marker.len() {
+            self.matched = self.failure[self.matched - 1];
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use bitvec::vec::BitVec;
+    use proptest::prelude::*;
+
+    use super::{DecodedImage, ImageDecoder, MarkerMatcher};
+    use crate::encoder::ImageEncoder;
+    use crate::prelude::*;
+
+    #[test]
+    fn marker_matching() {
+        let positions = |marker: &[u8], data: &[u8]| {
+            let mut matcher = MarkerMatcher::new(marker);
+            data.iter()
+                .enumerate()
+                .filter(|(_, byte)| matcher.push(**byte))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(positions(b"aab", b"aaab"), vec![3]);
+        assert_eq!(positions(b"abab", b"abababab"), vec![3, 5, 7]);
+        assert_eq!(positions(b"--", b"seagul"), Vec::<usize>::new());
+        assert_eq!(positions(b"", b"seagul"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn long_marker() {
+        let marker: Vec<u8> = (0..64).map(|i| if i % 2 == 0 { b'-' } else { b'=' }).collect();
+        let payload: Vec<u8> = (0..10 * 1024).map(|i| b"-=-+"[i % 4]).collect();
+        let data = [payload.as_slice(), &marker].concat();
+
+        let encoded = ImageEncoder::from_image(image::DynamicImage::new_rgb8(256, 256))
+            .with_lsb(2)
+            .encode_bytes(&data)
+            .expect("Encoding failed");
+        let mut decoder = ImageDecoder::from_image(encoded.altered_image().clone());
+        decoder.set_use_n_lsb(2).until_marker(Some(&marker));
+        let decoded = decoder.decode().expect("Decoding failed");
+
+        assert!(decoded.hit_marker());
+        assert_eq!(decoded.embedded_data(), data.as_slice());
+    }
+
+    #[test]
+    fn invalid_lsb() {
+        for lsb in [0, 9] {
+            assert!(matches!(
+                ImageDecoder::default().with_lsb(lsb).decode(),
+                Err(SteganographyError::InvalidConfiguration(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_step() {
+        let decoder = ImageDecoder {
+            skip_c: 0,
+            ..ImageDecoder::from_image(image::DynamicImage::new_rgb8(8, 8))
+        };
+        assert!(matches!(decoder.decode(), Err(SteganographyError::InvalidConfiguration(_))));
+        assert!(matches!(decoder.decode_exact(1), Err(SteganographyError::InvalidConfiguration(_))));
+
+        // A step larger than the image reads its first pixel only
+        let decoded = ImageDecoder::from_image(image::DynamicImage::new_rgb8(8, 8))
+            .with_step_by_n_pixels(1000)
+            .decode()
+            .expect("Decoding failed");
+        assert_eq!(decoded.decoded_pixel_count(), 1);
+    }
+
+    #[test]
+    fn max_bytes() {
+        let encoded = ImageEncoder::from_image(image::DynamicImage::new_rgb8(64, 64))
+            .encode_bytes(b"Midway upon the journey--")
+            .expect("Encoding failed");
+        let decoder = ImageDecoder::from_image(encoded.altered_image().clone()).with_max_bytes(6);
+        assert_eq!(decoder.config().max_bytes, Some(6));
+        let decoded = decoder.decode().expect("Decoding failed");
+        assert_eq!(decoded.embedded_data().as_slice(), b"Midway");
+        assert!(!decoded.hit_marker());
+
+        // A marker found before the limit still stops decoding
+        let decoded = decoder.with_max_bytes(100).with_marker(Some("--")).decode().unwrap();
+        assert_eq!(decoded.embedded_data().as_slice(), b"Midway upon the journey--");
+
+        // Reads of a known length ignore the limit
+        let decoder = ImageDecoder::from_image(encoded.altered_image().clone()).with_max_bytes(2);
+        assert_eq!(decoder.decode_exact(6).unwrap().embedded_data().as_slice(), b"Midway");
+    }
+
+    #[test]
+    fn partial_reads() {
+        let mut decoded = DecodedImage {
+            data: Cursor::new(b"seagul".to_vec()),
+            hit_marker: false,
+            elapsed: std::time::Duration::default(),
+            pixels_read: 0,
+            bits: BitVec::new(),
+            config: ImageDecoder::default().config(),
+        };
+
+        let mut head = [0u8; 4];
+        decoded.read_exact(&mut head).unwrap();
+        assert_eq!(&head, b"seag");
+
+        // A copy resumes reading where the original was
+        let mut copy = decoded.clone();
+        let mut tail = vec![];
+        copy.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, b"ul");
+
+        let mut tail = vec![];
+        decoded.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, b"ul");
+
+        assert_eq!(decoded.read(&mut head).unwrap(), 0);
+        assert_eq!(decoded.embedded_data().as_slice(), b"seagul");
+    }
+
+    #[test]
+    fn padding_stripping() {
+        let mut decoded = DecodedImage {
+            data: Cursor::new(b"seagulababa".to_vec()),
+            hit_marker: false,
+            elapsed: std::time::Duration::default(),
+            pixels_read: 0,
+            bits: BitVec::new(),
+            config: ImageDecoder::default().config(),
+        };
+
+        decoded.strip_padding(b"ab");
+        assert_eq!(decoded.embedded_data().as_slice(), b"seagul");
+        decoded.strip_padding(b"");
+        assert_eq!(decoded.embedded_data().as_slice(), b"seagul");
+        decoded.strip_padding(b"gull");
+        assert_eq!(decoded.embedded_data().as_slice(), b"sea");
+        decoded.strip_padding(b"sea");
+        assert_eq!(decoded.embedded_data().as_slice(), b"");
+
+        decoded.data = Cursor::new(b"aabaaabaaab".to_vec());
+        decoded.strip_padding(b"aab");
+        assert_eq!(decoded.embedded_data().as_slice(), b"aabaaaba");
+    }
+
+    #[test]
+    fn decoded_pixel_count() {
+        let decoded = ImageDecoder::default().decode_exact(2).unwrap();
+        assert_eq!(decoded.decoded_pixel_count(), 16);
+        assert_eq!(decoded.decode_time_micros(), decoded.decode_time().as_micros());
+
+        let decoded = ImageDecoder::default()
+            .with_lsb(2)
+            .with_channel_order(vec![RgbChannel::Red, RgbChannel::Blue])
+            .decode_exact(3)
+            .unwrap();
+        assert_eq!(decoded.decoded_pixel_count(), 6);
+
+        assert_eq!(ImageDecoder::default().decode().unwrap().decoded_pixel_count(), 256);
+    }
+
+    #[test]
+    fn decoder_config_snapshot() {
+        let mut decoder = ImageDecoder::default()
+            .with_lsb(2)
+            .with_position(ImagePosition::TopRight);
+        let decoded = decoder.decode_exact(2).unwrap();
+
+        decoder.set_position(ImagePosition::Center);
+        let config = decoded.decoder_config();
+        assert_eq!(config.encoding_position, ImagePosition::TopRight);
+        assert_eq!(config.lsb_c, 2);
+        assert_eq!(config, decoder.with_position(ImagePosition::TopRight).config());
+    }
+
+    #[test]
+    fn raw_bit_stream() {
+        let encoded = ImageEncoder::default()
+            .with_bit_order(BitOrder::MsbFirst)
+            .encode_bytes([0b0000_0011u8])
+            .unwrap();
+        let decoder = ImageDecoder::from_image(encoded.altered_image().clone()).with_bit_order(BitOrder::MsbFirst);
+
+        let decoded = decoder.decode_exact(1).unwrap();
+        assert_eq!(decoded.embedded_data().as_slice(), [0b0000_0011]);
+        assert_eq!(decoded.decoded_bit_count(), 8);
+        assert_eq!(decoded.as_bitvec().as_raw_slice(), [0b1100_0000]);
+
+        let decoded = ImageDecoder::default()
+            .with_lsb(2)
+            .with_channel_order(vec![RgbChannel::Red, RgbChannel::Blue])
+            .decode_exact(3)
+            .unwrap();
+        assert_eq!(decoded.decoded_bit_count(), 24);
+    }
+
+    #[test]
+    fn null_trimming() {
+        let mut decoded = DecodedImage {
+            data: Cursor::new(b"seagul\0\0\0gull\0".to_vec()),
+            hit_marker: false,
+            elapsed: std::time::Duration::default(),
+            pixels_read: 0,
+            bits: BitVec::new(),
+            config: ImageDecoder::default().config(),
+        };
+
+        assert_eq!(decoded.as_string_trimmed().unwrap(), "seagul");
+        assert_eq!(decoded.embedded_data().len(), 14);
+
+        decoded.truncate_at_null();
+        assert_eq!(decoded.as_string().unwrap(), "seagul");
+        assert_eq!(decoded.truncate_at_null().embedded_data().as_slice(), b"seagul");
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_any_lsb(
+            lsb in 1usize..=8,
+            step in 1usize..4,
+            channels in prop::sample::subsequence(vec![RgbChannel::Red, RgbChannel::Green, RgbChannel::Blue], 1..=3),
+            msb_first: bool,
+            payload in prop::collection::vec(any::<u8>(), 0..64),
+        ) {
+            // A noisy image, so that every bit written is checked
+            let source = image::DynamicImage::ImageRgb8(image::ImageBuffer::from_fn(32, 32, |x, y| {
+                image::Rgb([(x * 37 + y * 11) as u8, (x * 7 + y * 53) as u8, (x * y) as u8])
+            }));
+            let bit_order = if msb_first { BitOrder::MsbFirst } else { BitOrder::LsbFirst };
+            let encoder = ImageEncoder::from_image(source)
+                .with_lsb(lsb)
+                .with_step_by_n_pixels(step)
+                .with_channel_order(channels.clone())
+                .with_bit_order(bit_order);
+            prop_assume!(payload.len() <= encoder.estimate_capacity());
+
+            let encoded = encoder.encode_bytes(&payload).unwrap();
+            let decoded = ImageDecoder::from_image(encoded.altered_image().clone())
+                .with_lsb(lsb)
+                .with_step_by_n_pixels(step)
+                .with_channel_order(channels)
+                .with_bit_order(bit_order)
+                .decode_exact(payload.len())
+                .unwrap();
+            prop_assert_eq!(decoded.embedded_data(), &payload);
+            // The last channel read can hold bits past the end of the payload
+            prop_assert_eq!(decoded.decoded_bit_count(), (payload.len() * 8).div_ceil(lsb) * lsb);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(10_000))]
+
+        #[test]
+        fn bits_assemble_into_payload(
+            lsb in 1usize..=8,
+            payload in prop::collection::vec(any::<u8>(), 0..=24),
+        ) {
+            let source = image::DynamicImage::ImageRgb8(image::ImageBuffer::from_fn(8, 8, |x, y| {
+                image::Rgb([(x * 37 + y * 11) as u8, (x * 7 + y * 53) as u8, (x * y) as u8])
+            }));
+            let encoder = ImageEncoder::from_image(source)
+                .with_lsb(lsb)
+                .with_channel(RgbChannel::All);
+            prop_assume!(payload.len() <= encoder.estimate_capacity());
+
+            let encoded = encoder.encode_bytes(&payload).unwrap();
+            let decoded = ImageDecoder::from_image(encoded.altered_image().clone())
+                .with_lsb(lsb)
+                .with_channel(RgbChannel::All)
+                .decode_exact(payload.len())
+                .unwrap();
+            prop_assert_eq!(decoded.embedded_data(), &payload);
+        }
+    }
 }
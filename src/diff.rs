@@ -0,0 +1,134 @@
+use image::{imageops, DynamicImage, GenericImageView, ImageBuffer, RgbImage};
+
+use crate::prelude::Rgb;
+
+/// The factor applied to the channel differences in the image produced by `ImageDiff::as_image`
+const DIFF_AMPLIFICATION: u16 = 128;
+
+/// The differences between two images, compared pixel by pixel over their RGB8 representation.
+///
+/// When the images have different dimensions only the area they have in common,
+/// starting from the top left corner, is compared
+#[derive(Debug, Clone)]
+pub struct ImageDiff {
+    before: RgbImage,
+    after: RgbImage,
+}
+
+impl ImageDiff {
+    /// Compares image `a`, taken as the original, with image `b`
+    pub fn between(a: &DynamicImage, b: &DynamicImage) -> ImageDiff {
+        let width = a.width().min(b.width());
+        let height = a.height().min(b.height());
+
+        Self {
+            before: common_area(a, width, height),
+            after: common_area(b, width, height),
+        }
+    }
+
+    /// The coordinates of every pixel that differs between the two images, along
+    /// with its color before and after
+    pub fn changed_pixels(&self) -> Vec<(u32, u32, Rgb<u8>, Rgb<u8>)> {
+        self.before
+            .enumerate_pixels()
+            .zip(self.after.pixels())
+            .filter(|((_, _, before), after)| before != after)
+            .map(|((x, y, before), after)| (x, y, Rgb::from(*before), Rgb::from(*after)))
+            .collect()
+    }
+
+    /// The number of pixels that differ between the two images
+    pub fn changed_pixel_count(&self) -> usize {
+        self.before
+            .pixels()
+            .zip(self.after.pixels())
+            .filter(|(before, after)| before != after)
+            .count()
+    }
+
+    /// The number of bits that differ between the channels of the two images
+    pub fn total_bits_changed(&self) -> u64 {
+        self.before
+            .as_raw()
+            .iter()
+            .zip(self.after.as_raw().iter())
+            .map(|(a, b)| (a ^ b).count_ones() as u64)
+            .sum()
+    }
+
+    /// The ratio of changed pixels over the compared ones, between `0.0` and `1.0`
+    pub fn ratio_changed(&self) -> f64 {
+        let total_pixels = self.before.width() as u64 * self.before.height() as u64;
+        if total_pixels == 0 {
+            0.0
+        } else {
+            self.changed_pixel_count() as f64 / total_pixels as f64
+        }
+    }
+
+    /// Generates an image with the absolute per-channel difference between the two images,
+    /// amplified so that changes in the least significant bits become visible.
+    /// Unchanged pixels are black
+    pub fn as_image(&self) -> DynamicImage {
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(self.before.width(), self.before.height(), |x, y| {
+            let before = self.before.get_pixel(x, y);
+            let after = self.after.get_pixel(x, y);
+            let mut diff_pixel = image::Rgb([0u8; 3]);
+            for (channel, value) in diff_pixel.0.iter_mut().enumerate() {
+                let diff = (before[channel] as i16 - after[channel] as i16).unsigned_abs();
+                *value = (diff * DIFF_AMPLIFICATION).min(255) as u8;
+            }
+            diff_pixel
+        }))
+    }
+}
+
+/// The top left `width` x `height` area of `img`, as RGB8
+fn common_area(img: &DynamicImage, width: u32, height: u32) -> RgbImage {
+    let rgb = img.to_rgb8();
+    if rgb.dimensions() == (width, height) {
+        rgb
+    } else {
+        imageops::crop_imm(&rgb, 0, 0, width, height).to_image()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView, RgbImage};
+
+    use super::ImageDiff;
+
+    #[test]
+    fn compares_pixels() {
+        let before = RgbImage::new(4, 2);
+        let mut after = before.clone();
+        after.put_pixel(1, 0, image::Rgb([0, 0, 1]));
+        after.put_pixel(3, 1, image::Rgb([3, 0, 2]));
+
+        let diff = ImageDiff::between(&DynamicImage::ImageRgb8(before), &DynamicImage::ImageRgb8(after));
+        let changed = diff.changed_pixels();
+        assert_eq!(changed.len(), 2);
+        assert_eq!((changed[0].0, changed[0].1), (1, 0));
+        assert_eq!(changed[1].3, [3, 0, 2].into());
+        assert_eq!(diff.changed_pixel_count(), 2);
+        assert_eq!(diff.total_bits_changed(), 4);
+        assert!((diff.ratio_changed() - 0.25).abs() < f64::EPSILON);
+
+        let image = diff.as_image().to_rgb8();
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 128]);
+        assert_eq!(image.get_pixel(3, 1).0, [255, 0, 255]);
+    }
+
+    #[test]
+    fn different_dimensions() {
+        let diff = ImageDiff::between(&DynamicImage::new_rgb8(4, 6), &DynamicImage::new_rgb8(5, 3));
+        assert_eq!(diff.as_image().dimensions(), (4, 3));
+        assert_eq!(diff.changed_pixel_count(), 0);
+
+        let empty = ImageDiff::between(&DynamicImage::new_rgb8(0, 6), &DynamicImage::new_rgb8(5, 3));
+        assert_eq!(empty.ratio_changed(), 0.0);
+    }
+}
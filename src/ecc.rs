@@ -0,0 +1,131 @@
+use std::fmt::Display;
+
+/// Bits of a block holding the 4 data bits, from the least significant one.
+/// Bits 0, 1 and 3 hold the parity bits and bit 7 the parity of the whole block
+const DATA_BITS: [u8; 4] = [2, 4, 5, 6];
+
+/// Enumerates the reasons error corrected data can not be decoded
+#[derive(Debug, Clone, PartialEq)]
+pub enum EccError {
+    /// The block at the given index holds more than one flipped bit
+    UncorrectableError(usize),
+
+    /// The data has the given length, which is not a whole number of encoded bytes
+    IncompleteData(usize),
+}
+
+impl Display for EccError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EccError::UncorrectableError(block) => {
+                write!(f, "Block {} has too many errors to be corrected", block)
+            }
+            EccError::IncompleteData(len) => {
+                write!(f, "{} bytes do not make a whole number of encoded bytes", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EccError {}
+
+/// Encodes `data` with a Hamming(7,4) code, extended with an overall parity bit.
+///
+/// Each byte becomes two blocks, one per nibble starting from the least significant one,
+/// and each block takes a whole byte. Up to one flipped bit per block can be corrected,
+/// and two flipped bits are detected
+pub fn hamming_encode(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .flat_map(|byte| [encode_block(byte & 0x0f), encode_block(byte >> 4)])
+        .collect()
+}
+
+/// Decodes data encoded with `hamming_encode`, correcting single bit errors.
+/// Fails with the index of the first block holding more than one error
+pub fn hamming_decode(encoded: &[u8]) -> Result<Vec<u8>, EccError> {
+    if !encoded.len().is_multiple_of(2) {
+        return Err(EccError::IncompleteData(encoded.len()));
+    }
+
+    encoded
+        .chunks(2)
+        .enumerate()
+        .map(|(index, blocks)| decode_pair(blocks[0], blocks[1], index))
+        .collect()
+}
+
+/// Decodes the two blocks of the byte at `byte_index` of the original data
+pub(crate) fn decode_pair(low: u8, high: u8, byte_index: usize) -> Result<u8, EccError> {
+    let low = decode_block(low).ok_or(EccError::UncorrectableError(byte_index * 2))?;
+    let high = decode_block(high).ok_or(EccError::UncorrectableError(byte_index * 2 + 1))?;
+    Ok(low | high << 4)
+}
+
+fn encode_block(nibble: u8) -> u8 {
+    let bit = |i: u8| (nibble >> i) & 1;
+    let (d1, d2, d3, d4) = (bit(0), bit(1), bit(2), bit(3));
+    let p1 = d1 ^ d2 ^ d4;
+    let p2 = d1 ^ d3 ^ d4;
+    let p3 = d2 ^ d3 ^ d4;
+
+    let block = p1 | p2 << 1 | d1 << 2 | p3 << 3 | d2 << 4 | d3 << 5 | d4 << 6;
+    block | ((block.count_ones() as u8 & 1) << 7)
+}
+
+/// The nibble held by `block`, or `None` if it holds more than one error
+fn decode_block(block: u8) -> Option<u8> {
+    // The syndrome is the 1-based position of the flipped bit, if any
+    let syndrome = (1..=7u8)
+        .filter(|position| block & (1 << (position - 1)) != 0)
+        .fold(0, |syndrome, position| syndrome ^ position);
+    let parity_error = !block.count_ones().is_multiple_of(2);
+
+    let corrected = match (syndrome, parity_error) {
+        (0, _) => block,
+        (position, true) => block ^ (1 << (position - 1)),
+        (_, false) => return None,
+    };
+
+    Some(
+        DATA_BITS
+            .iter()
+            .enumerate()
+            .fold(0, |nibble, (i, bit)| nibble | ((corrected >> bit) & 1) << i),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hamming_decode, hamming_encode, EccError};
+
+    #[test]
+    fn roundtrip() {
+        let data: Vec<u8> = (0..=255).collect();
+        let encoded = hamming_encode(&data);
+        assert_eq!(encoded.len(), 512);
+        assert_eq!(hamming_decode(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn corrects_single_errors() {
+        let data = b"seagul";
+        let encoded = hamming_encode(data);
+
+        for block in 0..encoded.len() {
+            for bit in 0..8 {
+                let mut corrupted = encoded.clone();
+                corrupted[block] ^= 1 << bit;
+                assert_eq!(hamming_decode(&corrupted).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn detects_double_errors() {
+        let mut encoded = hamming_encode(b"seagul");
+        encoded[3] ^= 0b0001_0010;
+
+        assert_eq!(hamming_decode(&encoded), Err(EccError::UncorrectableError(3)));
+        assert_eq!(hamming_decode(&encoded[..5]), Err(EccError::IncompleteData(5)));
+    }
+}
@@ -1,12 +1,25 @@
-use std::{fmt::Display, fs::File};
+use std::{borrow::Cow, collections::HashSet, convert::TryFrom, fmt::Display, fs::File, io::{ErrorKind, Read}, path::{Path, PathBuf}};
 
-use bitvec::{prelude::*, view::AsBits};
-use image::{DynamicImage, EncodableLayout, GenericImageView, Pixel};
+use bitvec::prelude::*;
+use image::{DynamicImage, GenericImageView, GrayImage, ImageBuffer, Pixel, RgbImage, RgbaImage};
 
-use crate::{conversion::byte_to_bits, prelude::{CompressionType, FilterType, ImageFormat, ImagePosition, ImageRules, Rgb, RgbChannel}};
+#[cfg(feature = "crypto")]
+use crate::crypto::{self, SecretKey, KEY_SIZE};
+#[cfg(feature = "crypto")]
+use crate::key_derivation::{derive_key, DEFAULT_ITERATIONS, SALT_SIZE};
+#[cfg(feature = "hash")]
+use crate::io::TeeWriter;
+use crate::{capacity::{self, CapacityReport}, config::{EncoderConfig, ImageWriterConfig}, conversion::{apply_xor_key, byte_to_bits}, decoder::ImageDecoder, diff::ImageDiff, ecc::hamming_encode, error::SteganographyError, header::{crc32, frame_with_length, FileHeader, Header, CRC_SIZE, FLAG_ECC, FLAG_ENCRYPTED, FLAG_SPREAD, HEADER_SIZE}, metadata::{Metadata, METADATA_PIXELS}, permutation::pixel_order, pixel_selectors::{clamp_region, region_dimensions, LinearPixelSelector, PixelSelector, Region, SelectorConfig}, prelude::{channel_indices, is_grayscale, BitOrder, CompressionType, FilterType, ImageFormat, ImagePosition, ImageRules, Rgb, RgbChannel}};
+
+/// The number of bytes read at once by `ImageEncoder::encode_reader`, unless configured otherwise
+const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// Marks the pixels that are not part of the selection in the parallel encoder
+#[cfg(feature = "rayon")]
+const UNSELECTED: usize = usize::MAX;
 
 /// Describes a color change for a pixel at coordinates `(.0, .1)` from color `.2` to color `.3`
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ColorChange(u32, u32, Rgb<u8>, Rgb<u8>);
 
 impl Display for ColorChange {
@@ -16,76 +29,324 @@ impl Display for ColorChange {
 }
 
 /// Describes how a single byte is encoded
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ByteEncodeMap {
-    pub encoded_byte: u8,
+    /// The byte encoded, or `None` until the map is assigned one
+    pub encoded_byte: Option<u8>,
     pub affected_points: Vec<ColorChange>,
 }
 
+impl Default for ByteEncodeMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ByteEncodeMap {
     pub fn new() -> Self {
         Self {
-            encoded_byte: 0,
+            encoded_byte: None,
             affected_points: vec![],
         }
     }
 
+    /// The number of pixels altered to encode the byte. A map with no byte assigned yet
+    /// has no altered pixels
     pub fn len(&self) -> usize {
         self.affected_points.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.affected_points.is_empty()
+    }
 }
 
 /// Represents the result of an image encoded with `ImageEncoder` and offers saving methods
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EncodedImage {
     altered_image: image::DynamicImage,
     original_image: image::DynamicImage,
     map: Vec<ByteEncodeMap>,
+    compression_type: CompressionType,
+    filter_type: FilterType,
 }
 
 impl EncodedImage {
+    #[deprecated(note = "use `EncodedImage::diff` to compare the original and the altered image")]
     pub fn changes(&self) -> &Vec<ByteEncodeMap> {
         &self.map
     }
 
+    #[deprecated(note = "renamed to `color_channel_writes`, use `pixels_modified` to count distinct pixels")]
     pub fn pixels_changed(&self) -> usize {
-        *&self.map.iter().fold(0, |acc, item| acc + item.len())
+        self.color_channel_writes()
+    }
+
+    /// The number of times a color channel has been written into, a channel holding bits
+    /// of two payload bytes being counted twice
+    pub fn color_channel_writes(&self) -> usize {
+        self.map.iter().map(ByteEncodeMap::len).sum()
+    }
+
+    /// The number of distinct pixels written into, including the ones whose color did not
+    /// change. Use `EncodedImage::diff` to count only the pixels that actually changed
+    pub fn pixels_modified(&self) -> usize {
+        self.map
+            .iter()
+            .flat_map(|byte_map| byte_map.affected_points.iter())
+            .map(|color_change| (color_change.0, color_change.1))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// The number of payload bytes encoded, error correction and headers included
+    pub fn bytes_encoded(&self) -> usize {
+        self.map.len()
+    }
+
+    /// The image holding the encoded data
+    pub fn altered_image(&self) -> &DynamicImage {
+        &self.altered_image
+    }
+
+    /// The image the data has been encoded into, as it was before encoding
+    pub fn original_image(&self) -> &DynamicImage {
+        &self.original_image
+    }
+
+    /// Consumes this `EncodedImage`, returning the image holding the encoded data
+    pub fn into_altered_image(self) -> DynamicImage {
+        self.altered_image
+    }
+
+    /// Compares the original image with the altered one
+    pub fn diff(&self) -> ImageDiff {
+        ImageDiff::between(&self.original_image, &self.altered_image)
+    }
+
+    /// The mean squared error between the original and the altered image, computed
+    /// over every channel of their RGB8 representation
+    pub fn mse(&self) -> f64 {
+        let original = self.original_image.to_rgb8();
+        let altered = self.altered_image.to_rgb8();
+        let samples = original.as_raw().len();
+
+        if samples == 0 {
+            return 0.0;
+        }
+
+        let squared_error_sum: f64 = original
+            .as_raw()
+            .iter()
+            .zip(altered.as_raw().iter())
+            .map(|(a, b)| {
+                let diff = *a as f64 - *b as f64;
+                diff * diff
+            })
+            .sum();
+
+        squared_error_sum / samples as f64
+    }
+
+    /// The peak signal-to-noise ratio, in decibels, between the original and the altered image.
+    /// Higher is better, and `f64::INFINITY` is returned when the images are identical
+    pub fn psnr(&self) -> f64 {
+        let mse = self.mse();
+        if mse == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (255.0_f64.powi(2) / mse).log10()
+        }
+    }
+
+    /// Generates an image with the absolute per-channel difference between the original
+    /// and the altered image, amplified so that changes in the least significant bits
+    /// become visible. Unchanged pixels are black
+    pub fn generate_diff_image(&self) -> DynamicImage {
+        self.diff().as_image()
+    }
+
+    /// Generates an image where every changed pixel is colored in red, brighter as more of
+    /// its bits have been changed, alpha channel included. Unchanged pixels are black
+    pub fn generate_heatmap(&self) -> DynamicImage {
+        let original = self.original_image.to_rgba8();
+        let altered = self.altered_image.to_rgba8();
+
+        let bits_changed: Vec<u32> = original
+            .pixels()
+            .zip(altered.pixels())
+            .map(|(original_pixel, altered_pixel)| {
+                original_pixel
+                    .0
+                    .iter()
+                    .zip(altered_pixel.0.iter())
+                    .map(|(a, b)| (a ^ b).count_ones())
+                    .sum()
+            })
+            .collect();
+        let max_bits_changed = bits_changed.iter().copied().max().unwrap_or(0).max(1);
+        let width = original.width();
+
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, original.height(), |x, y| {
+            let bits = bits_changed[(y * width + x) as usize];
+            image::Rgb([(bits * 255 / max_bits_changed) as u8, 0, 0])
+        }))
+    }
+
+    /// Decodes the altered image using `rules` and checks that the result matches `expected`
+    /// byte for byte. Useful to detect a decoder configuration that does not match the
+    /// one used for encoding
+    pub fn verify_roundtrip<R>(&self, expected: &[u8], rules: &R) -> Result<bool, SteganographyError>
+    where
+        R: ImageRules,
+    {
+        let decoder = ImageDecoder::from_rules(self.altered_image.clone(), rules);
+        match decoder.decode_exact(expected.len()) {
+            Ok(decoded) => Ok(decoded.embedded_data().as_slice() == expected),
+            Err(SteganographyError::InsufficientCapacity { .. }) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
     /// Writes decoded bytes into a new file at `path`, with the specified image format.
-    /// If the file exists it is overwritten.
-    pub fn save(&self, path: &str, format: ImageFormat) -> Result<(), std::io::Error> {
-        let mut output_file = File::create(path).unwrap();
-        self.write(&mut output_file, format)
+    /// If the file exists it is overwritten. Lossy formats are refused, see `force_save`
+    pub fn save(&self, path: impl AsRef<Path>, format: ImageFormat) -> Result<(), SteganographyError> {
+        ensure_lossless(format)?;
+        self.force_save(path, format)
+    }
+
+    /// Like `save`, but also accepts lossy formats. The encoded data will most likely
+    /// not be recoverable from the saved image
+    pub fn force_save(&self, path: impl AsRef<Path>, format: ImageFormat) -> Result<(), SteganographyError> {
+        let mut output_file = File::create(path)?;
+        self.image_writer().write(&mut output_file, format)
+    }
+
+    /// Like `save`, returning the BLAKE3 hash of the bytes written to `path`
+    #[cfg(feature = "hash")]
+    pub fn save_with_hash(&self, path: impl AsRef<Path>, format: ImageFormat) -> Result<[u8; 32], SteganographyError> {
+        ensure_lossless(format)?;
+        let mut writer = TeeWriter::<_, blake3::Hasher>::new(std::io::BufWriter::new(File::create(path)?));
+        self.image_writer().write(&mut writer, format)?;
+
+        let (mut output_file, digest) = writer.finalize_hash();
+        std::io::Write::flush(&mut output_file)?;
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&digest);
+        Ok(hash)
     }
 
-    /// Writes decoded bytes into an arbitraty `std::io::Write`, with the specified image format
-    pub fn write<W>(&self, writable: &mut W, format: ImageFormat) -> Result<(), std::io::Error>
+    /// Writes decoded bytes into an arbitraty `std::io::Write`, with the specified image format.
+    /// Lossy formats are refused
+    pub fn write<W>(&self, writable: &mut W, format: ImageFormat) -> Result<(), SteganographyError>
     where
         W: std::io::Write,
     {
-        ImageWriter::new(&self).write(writable, format)
+        ensure_lossless(format)?;
+        self.image_writer().write(writable, format)
+    }
+
+    /// The altered image encoded as PNG, in memory
+    pub fn to_png_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        self.to_bytes(ImageFormat::Png)
+    }
+
+    /// The altered image encoded as BMP, in memory
+    pub fn to_bmp_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        self.to_bytes(ImageFormat::Bmp)
+    }
+
+    fn to_bytes(&self, format: ImageFormat) -> Result<Vec<u8>, std::io::Error> {
+        self.image_writer().to_vec(format)
+    }
+
+    fn image_writer(&self) -> ImageWriter<'_> {
+        ImageWriter::from_config(
+            self,
+            ImageWriterConfig {
+                compression_type: self.compression_type.clone(),
+                filter_type: self.filter_type.clone(),
+                ..ImageWriterConfig::default()
+            },
+        )
+    }
+}
+
+impl AsRef<DynamicImage> for EncodedImage {
+    fn as_ref(&self) -> &DynamicImage {
+        &self.altered_image
+    }
+}
+
+fn ensure_lossless(format: ImageFormat) -> Result<(), SteganographyError> {
+    if format.is_lossless() {
+        Ok(())
+    } else {
+        Err(SteganographyError::LossyFormat(format))
     }
 }
 
 /// Writes an `EncodedImage`to a `Writable`
 pub struct ImageWriter<'a> {
     image: &'a EncodedImage,
-    compression_type: CompressionType,
-    filter_type: FilterType
+    config: ImageWriterConfig,
 }
 
 impl<'a> ImageWriter<'a> {
     pub fn new(image_ref: &'a EncodedImage) -> Self {
+        Self::from_config(image_ref, ImageWriterConfig::default())
+    }
+
+    /// Creates a writer for `image_ref` using the settings of `config`
+    pub fn from_config(image_ref: &'a EncodedImage, config: ImageWriterConfig) -> Self {
         Self {
             image: image_ref,
-            compression_type: CompressionType::Default,
-            filter_type: FilterType::NoFilter
+            config,
         }
     }
 
-    /// Writes decoded bytes into an arbitraty `std::io::Write`, with the specified image format
-    pub fn write<W>(&self, writable: &mut W, format: ImageFormat) -> Result<(), std::io::Error>
+    /// The settings of this writer, detached from the image it writes
+    pub fn config(&self) -> ImageWriterConfig {
+        self.config.clone()
+    }
+
+    /// Sets the compression used when writing PNG images
+    pub fn with_compression(mut self, compression_type: CompressionType) -> Self {
+        self.config.compression_type = compression_type;
+        self
+    }
+
+    /// Sets the filter applied before compression when writing PNG images
+    pub fn with_filter(mut self, filter_type: FilterType) -> Self {
+        self.config.filter_type = filter_type;
+        self
+    }
+
+    /// Sets the quality, from 1 to 100, used when writing JPEG images. Values out of
+    /// range are clamped. Encoded data does not survive JPEG compression at any quality,
+    /// so this is only useful for images that are not meant to be decoded
+    pub fn with_jpeg_quality(mut self, quality: u8) -> Self {
+        self.config.jpeg_quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Writes decoded bytes into an arbitraty `std::io::Write`, with the specified image format.
+    ///
+    /// Writing into a `std::io::Cursor` keeps the image in memory:
+    ///
+    /// ```
+    /// # use seagul_core::{encoder::{ImageEncoder, ImageWriter}, prelude::ImageFormat};
+    /// let encoded = ImageEncoder::default().encode_bytes(b"seagul").unwrap();
+    /// let image_writer = ImageWriter::new(&encoded);
+    ///
+    /// let mut buf = std::io::Cursor::new(Vec::new());
+    /// image_writer.write(&mut buf, ImageFormat::Png).unwrap();
+    /// let bytes = buf.into_inner();
+    /// assert!(bytes.starts_with(b"\x89PNG"));
+    /// ```
+    ///
+    /// `to_vec` does exactly this
+    pub fn write<W>(&self, writable: &mut W, format: ImageFormat) -> Result<(), SteganographyError>
     where
         W: std::io::Write,
     {
@@ -93,40 +354,63 @@ impl<'a> ImageWriter<'a> {
         let bytes = self.image.altered_image.as_bytes();
 
         match format {
-            ImageFormat::Jpeg | ImageFormat::Png => {
-                match image::ImageEncoder::write_image(
+            ImageFormat::Jpeg => {
+                // JPEG has no alpha channel
+                let rgb_image = self.image.altered_image.to_rgb8();
+                image::ImageEncoder::write_image(
+                    image::jpeg::JpegEncoder::new_with_quality(writable, self.config.jpeg_quality.clamp(1, 100)),
+                    rgb_image.as_raw(),
+                    target_dimensions.0,
+                    target_dimensions.1,
+                    image::ColorType::Rgb8,
+                )?;
+            }
+            ImageFormat::Png => {
+                image::ImageEncoder::write_image(
                     image::png::PngEncoder::new_with_quality(
                         writable,
-                        self.compression_type.clone().into(),
-                        self.filter_type.clone().into(),
+                        self.config.compression_type.clone().into(),
+                        self.config.filter_type.clone().into(),
                     ),
                     bytes,
                     target_dimensions.0,
                     target_dimensions.1,
-                    image::ColorType::Rgb8,
-                ) {
-                    Ok(_) => Ok(()),
-                    Err(e) => Err(std::io::Error::new(std::io::ErrorKind::Interrupted, e)),
-                }
+                    self.image.altered_image.color(),
+                )?;
             }
             ImageFormat::Bmp => {
                 // Box::new(image::bmp::BmpEncoder::new(&mut output_file))
-                match image::ImageEncoder::write_image(
+                image::ImageEncoder::write_image(
                     image::bmp::BmpEncoder::new(writable),
                     bytes,
                     target_dimensions.0,
                     target_dimensions.1,
-                    image::ColorType::Rgb8,
-                ) {
-                    Ok(_) => Ok(()),
-                    Err(e) => Err(std::io::Error::new(std::io::ErrorKind::Interrupted, e)),
-                }
+                    self.image.altered_image.color(),
+                )?;
             }
         }
+
+        Ok(())
+    }
+
+    /// The image encoded with the specified format, in memory
+    pub fn to_vec(&self, format: ImageFormat) -> Result<Vec<u8>, std::io::Error> {
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        match self.write(&mut cursor, format) {
+            Ok(()) => Ok(cursor.into_inner()),
+            Err(SteganographyError::IoError(e))
+            | Err(SteganographyError::ImageLoadError(image::ImageError::IoError(e))) => Err(e),
+            Err(e) => Err(std::io::Error::other(e)),
+        }
     }
 }
 
-/// An image decoder takes an image and alters its pixels to encode arbitrary data
+/// An image decoder takes an image and alters its pixels to encode arbitrary data.
+///
+/// Encoding is deterministic: the same source image, settings and payload always produce
+/// the same pixels, on every platform and whether the `rayon` feature is enabled or not.
+/// The only exception is encryption, which uses a random nonce for each encoding
+#[derive(Clone)]
 pub struct ImageEncoder {
     // Number of least significant bits to modify on each byte
     lsb_c: usize,
@@ -140,17 +424,55 @@ pub struct ImageEncoder {
     // Wheter to spread the message across all image
     spread: bool,
 
+    // The number of selected pixels between two pixels holding data when spreading,
+    // computed from the message length if 0
+    spread_stride: usize,
+
     // Fill all non-modified bytes with a fixed chunk of data
     padding: Option<String>,
 
-    // The color channel to use for encoding
-    encoding_channel: RgbChannel,
+    // The color channels to use for encoding, in order
+    channel_order: Vec<RgbChannel>,
 
     // The position on the image to start encoding from
     encoding_position: ImagePosition,
 
+    // The seed of the pseudo random order pixels are visited in, if any
+    permutation_seed: Option<u64>,
+
+    // The strategy choosing which pixels hold data
+    pixel_selector: Box<dyn PixelSelector>,
+
+    // The only part of the image to modify, if any
+    region: Option<Region>,
+
+    // The order in which the bits of each byte are stored
+    bit_order: BitOrder,
+
+    // Wheter to protect the data with an error correction code
+    ecc: bool,
+
+    // The key encrypting the payload, if any
+    #[cfg(feature = "crypto")]
+    key: Option<SecretKey>,
+
+    // The key the payload bytes are XORed with, if any
+    xor_key: Option<Vec<u8>>,
+
     // The source image to be modified
     source_image: DynamicImage,
+
+    // The compression to use when saving the encoded image
+    compression_type: CompressionType,
+
+    // The filter to use when saving the encoded image
+    filter_type: FilterType,
+
+    // The number of bytes read at once when encoding from a reader
+    chunk_size: usize,
+
+    // Wheter to encode into the low byte of 16 bit channels
+    rgb16_mode: bool,
 }
 
 impl Default for ImageEncoder {
@@ -160,324 +482,2250 @@ impl Default for ImageEncoder {
             skip_c: 1,
             offset: 0,
             spread: false,
+            spread_stride: 0,
             padding: None,
-            encoding_channel: RgbChannel::Blue,
+            channel_order: vec![RgbChannel::Blue],
             encoding_position: ImagePosition::TopLeft,
+            permutation_seed: None,
+            pixel_selector: Box::new(LinearPixelSelector),
+            region: None,
+            bit_order: BitOrder::LsbFirst,
+            ecc: false,
+            #[cfg(feature = "crypto")]
+            key: None,
+            xor_key: None,
             source_image: DynamicImage::new_rgb8(16, 16),
+            compression_type: CompressionType::Default,
+            filter_type: FilterType::NoFilter,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            rgb16_mode: false,
         }
     }
 }
 
-impl From<&str> for ImageEncoder {
-    fn from(path: &str) -> Self {
-        let mut file = File::open(path).expect("Test image not found");
-        Self::from(&mut file as &mut dyn std::io::Read)
+impl TryFrom<&str> for ImageEncoder {
+    type Error = SteganographyError;
+
+    /// Loads the image at `path`, failing if it can not be read or is not a supported image
+    fn try_from(path: &str) -> Result<Self, Self::Error> {
+        Self::try_from(Path::new(path))
     }
 }
 
-impl<R: std::io::Read + ?Sized> From<&mut R> for ImageEncoder {
-    fn from(readable: &mut R) -> Self {
-        let mut source_data: Vec<u8> = Vec::new();
-        readable
-            .read_to_end(&mut source_data)
-            .expect("Cannot load image from this path");
+impl TryFrom<&Path> for ImageEncoder {
+    type Error = SteganographyError;
 
-        let img = image::load_from_memory(source_data.as_bytes()).unwrap();
+    /// Loads the image at `path`, failing if it can not be read or is not a supported image
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let mut file = File::open(path).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("Cannot open {}: {}", path.display(), e))
+        })?;
+        Self::from_reader(&mut file)
+    }
+}
 
-        let mut encoder = Self::default();
-        encoder.source_image = img;
+impl TryFrom<PathBuf> for ImageEncoder {
+    type Error = SteganographyError;
 
-        encoder
+    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        Self::try_from(path.as_path())
     }
 }
 
-impl ImageEncoder {
-    /// Encodes a string into the source image for this decoder
-    pub fn encode_string(&self, data: String) -> Result<EncodedImage, String> {
-        self.encode_data(data.as_bytes())
+impl TryFrom<&mut dyn std::io::Read> for ImageEncoder {
+    type Error = SteganographyError;
+
+    fn try_from(readable: &mut dyn std::io::Read) -> Result<Self, Self::Error> {
+        Self::from_reader(readable)
     }
+}
 
-    /// Encodes arbitrary bytes into the source image for this decoder
-    pub fn encode_bytes<'a>(&self, data: &'a [u8]) -> Result<EncodedImage, String> {
-        self.encode_data(data.as_bytes())
+impl TryFrom<&[u8]> for ImageEncoder {
+    type Error = SteganographyError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(data)
     }
+}
 
-    fn encode_data<'a>(&self, data: &'a [u8]) -> Result<EncodedImage, String> {
-        let img = &self.source_image;
-        let mut encode_maps: Vec<ByteEncodeMap> = vec![];
-        let encoding_channel = self.get_use_channel().into();
-        let bytes_per_round = bytes_needed_for_data(data, self);
-
-        // Determine padding bits option
-        let mut padding_bits = None;
-        let unwrapped_padding_str;
-        if self.padding.is_some() {
-            unwrapped_padding_str = self.padding.as_ref().unwrap();
-            padding_bits = Some((*unwrapped_padding_str).as_bits::<Lsb0>());
-        }
-
-        if bytes_per_round <= img.as_bytes().len() {
-            let mut rgb_img = img.to_rgb8();
-            let image_dimensions = rgb_img.dimensions();
-            let mut real_offset: usize = 0;
-            match self.encoding_position {
-                ImagePosition::TopLeft => (),
-                ImagePosition::TopRight => {
-                    real_offset = image_dimensions.0 as usize;
-                }
-                ImagePosition::BottomLeft => {
-                    real_offset = image_dimensions.1 as usize;
-                }
-                ImagePosition::BottomRight => {
-                    real_offset = image_dimensions.0 as usize + image_dimensions.1 as usize
-                }
-                ImagePosition::Center => {
-                    real_offset = (image_dimensions.0 as usize + image_dimensions.1 as usize) / 2
-                }
-                ImagePosition::At(w, h) => {
-                    real_offset = (w * h) as usize;
-                }
-            }
+impl From<DynamicImage> for ImageEncoder {
+    fn from(img: DynamicImage) -> Self {
+        Self::from_image(img)
+    }
+}
 
-            real_offset += self.offset;
-            
-            let mut pixel_iter = rgb_img
-                .enumerate_pixels_mut()
-                .skip(real_offset)
-                .step_by(self.skip_c);
-
-            // while real_offset > 0 {
-            //     pixel_iter.next();
-            //     if let Some(_padding_bits_value) = padding_bits {
-            //         // TODO: put leading padding bits
-            //     }
-            //     real_offset -= 1;
-            // }
-
-            let mut pixel_iter_counter = img.pixels().count();
-
-            'encode_rounds: loop {
-                let data_iterator = data.iter();
-                'data_iter: for byte_to_encode in data_iterator {
-                    let mut current_byte_iter_count = 0;
-                    let mut current_byte_map = ByteEncodeMap::new();
-                    current_byte_map.encoded_byte = byte_to_encode.clone();
-
-                    let bits_to_encode = byte_to_bits(byte_to_encode);
-
-                    if let Some(bits_ptr) = bits_to_encode {
-                        while current_byte_iter_count < std::mem::size_of::<u8>() * 8 {
-
-                            // Get the chunk of bits of lsb_c length at current_byte_iter_count offset
-                            let bits_to_encode_slice: &BitSlice<Lsb0, u8> = &bits_ptr
-                                [current_byte_iter_count..current_byte_iter_count + self.lsb_c];
-
-                            if let Some(pixel_to_modify) = pixel_iter.next() {
-                                pixel_iter_counter = pixel_iter_counter - 1;
-                                let mut color_change = ColorChange(
-                                    pixel_to_modify.0,
-                                    pixel_to_modify.1,
-                                    pixel_to_modify.2.clone().into(),
-                                    Rgb::from([0, 0, 0]),
-                                );
-                                let bits_to_modify = pixel_to_modify
-                                    .2
-                                    .channels_mut()
-                                    .get_mut::<usize>(encoding_channel)
-                                    .unwrap()
-                                    .view_bits_mut::<Lsb0>();
-
-                                put_bits(bits_to_encode_slice, bits_to_modify, &self.lsb_c);
-    
-                                color_change.3 = pixel_to_modify.2.clone().into();
-                                current_byte_map.affected_points.push(color_change);
-                                current_byte_iter_count += self.lsb_c;
-                            } else {
-                                break 'data_iter;
-                            }
-                        }
-                    }
+impl ImageEncoder {
+    /// Creates an encoder for an image already loaded in memory, taking ownership of it.
+    /// Grayscale images use `RgbChannel::Luma` by default, so that they are encoded
+    /// without being converted to RGB, and 16 bit color images use the 16 bit mode
+    pub fn from_image(img: DynamicImage) -> Self {
+        let mut encoder = Self {
+            source_image: img,
+            ..Self::default()
+        };
+        if is_grayscale(&encoder.source_image) {
+            encoder.channel_order = vec![RgbChannel::Luma];
+        }
+        encoder.rgb16_mode = matches!(
+            encoder.source_image,
+            DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_)
+        );
 
-                    encode_maps.push(current_byte_map);
-                }
+        encoder
+    }
 
-                if self.spread {
-                    if pixel_iter_counter == 0 {
-                        break 'encode_rounds;
-                    } else {
-                        continue;
-                    }
-                } else {
-                    if let Some(_padding_bits_value) = padding_bits {
-                        // TODO: put trailing padding bytes
-                        break 'encode_rounds;
-                    } else {
-                        break 'encode_rounds;
-                    }
-                }
-            }
+    /// Creates an encoder for an encoded image (PNG, JPEG, BMP...) read from `reader`, failing
+    /// if it can not be read or is not a supported image
+    pub fn from_reader<R>(reader: &mut R) -> Result<Self, SteganographyError>
+    where
+        R: std::io::Read + ?Sized,
+    {
+        let mut source_data: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut source_data)?;
 
-            Ok(EncodedImage {
-                original_image: img.clone(),
-                altered_image: DynamicImage::ImageRgb8(rgb_img),
-                map: encode_maps,
-            })
-        } else {
-            Err(String::from(
-                "Not enough space in image to fit specified data",
-            ))
-        }
+        Self::from_bytes(&source_data)
     }
-}
 
-// fn encode_bytes<'a>(bytes: &[u8], into_iter: impl Iterator<Item = (u32, u32, &'a mut Rgb<u8>)>) {}
+    /// Creates an encoder for an encoded image (PNG, JPEG, BMP...) held in memory, failing
+    /// if it is not a supported image
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        let img = image::load_from_memory(data)?;
 
-fn put_bits(bits: &BitSlice<Lsb0, u8>, into: &mut BitSlice<Lsb0, u8>, lsb_c: &usize) {
-    for i in 0..*lsb_c {
-        into.set(i, bits[i]);
+        Ok(Self::from_image(img))
     }
-}
 
-impl ImageRules for ImageEncoder {
-    /// Skip the first `offset` bytes in the source buffer
-    fn set_offset(&mut self, offset: usize) -> &mut Self {
-        self.offset = offset;
+    /// Sets the key used to encrypt the payload with ChaCha20-Poly1305. A random nonce is
+    /// generated for each encoding and stored before the ciphertext, so the encoded data
+    /// is `crypto::OVERHEAD` bytes longer than the payload
+    #[cfg(feature = "crypto")]
+    pub fn set_key(&mut self, key: &[u8; KEY_SIZE]) -> &mut Self {
+        self.key = Some(SecretKey(*key));
         self
     }
 
-    /// Sets the number of least significative bits to edit for each
-    /// byte in the source buffer. The default is 1. The higher the value gets
-    /// the least space is required to encode data into the source, but the resulting
-    /// image will get noticeably different from the original
-    fn set_use_n_lsb(&mut self, n: usize) -> &mut Self {
-        self.lsb_c = n;
+    /// Consuming version of `set_key`
+    #[cfg(feature = "crypto")]
+    pub fn with_key(mut self, key: &[u8; KEY_SIZE]) -> Self {
+        self.set_key(key);
         self
     }
 
-    /// Specifies wich color channel will be the one used to store information bits.
-    fn set_use_channel(&mut self, channel: RgbChannel) -> &mut Self {
-        self.encoding_channel = channel;
+    /// Sets the key used to encrypt the payload to the one derived from `password` and `salt`
+    /// by `key_derivation::derive_key`, with `key_derivation::DEFAULT_ITERATIONS` rounds.
+    /// Unlike `set_password`, this does not change the order pixels are visited in
+    #[cfg(feature = "crypto")]
+    pub fn set_password_with_salt(&mut self, password: &str, salt: &[u8; SALT_SIZE]) -> &mut Self {
+        self.set_key(&derive_key(password, salt, DEFAULT_ITERATIONS))
+    }
+
+    /// Sets a key to XOR the encoded data with, the byte at index `i` with `key[i % key.len()]`.
+    /// An empty key disables it. Headers and checksums encoded along with a payload are
+    /// XORed too, and `ImageDecoder::set_xor_key` with the same key gives the data back.
+    ///
+    /// This is not encryption: the key is easily recovered from any known part of the
+    /// data, and it only prevents casual reading of the payload. Use `set_key`, from the
+    /// `crypto` feature, to keep it secret
+    pub fn set_xor_key(&mut self, key: &[u8]) -> &mut Self {
+        self.xor_key = Some(key.to_vec()).filter(|key| !key.is_empty());
         self
     }
 
-    /// When encoding data, `n` pixels will be skipped after each edited pixel
-    fn set_step_by_n_pixels(&mut self, n: usize) -> &mut Self {
-        if n < 1 {
-            self.skip_c = 1;
-        } else {
-            self.skip_c = n;
-        }
+    /// Consuming version of `set_xor_key`
+    pub fn with_xor_key(mut self, key: &[u8]) -> Self {
+        self.set_xor_key(key);
         self
     }
 
-    fn set_spread(&mut self, value: bool) -> &mut Self {
-        self.spread = value;
+    /// Sets whether data is encoded into the low byte of 16 bit color channels. The source
+    /// image is converted to 16 bits per channel if needed, and the encoded image must be
+    /// saved as PNG to keep them. Even using all 8 least significant bits, each channel
+    /// changes by less than 1/256 of its range, so more bits can be used than on 8 bit
+    /// images for the same visual change. `ImageDecoder` reads 16 bit images this way
+    pub fn set_rgb16_mode(&mut self, value: bool) -> &mut Self {
+        self.rgb16_mode = value;
         self
     }
 
-    fn set_position(&mut self, value: ImagePosition) -> &mut Self {
-        self.encoding_position = value;
+    /// Consuming version of `set_rgb16_mode`
+    pub fn with_rgb16_mode(mut self, value: bool) -> Self {
+        self.set_rgb16_mode(value);
         self
     }
 
-    fn get_use_n_lsb(&self) -> usize {
-        self.lsb_c
+    /// Sets the compression used when saving or writing the encoded image as PNG
+    pub fn set_compression_type(&mut self, compression_type: CompressionType) -> &mut Self {
+        self.compression_type = compression_type;
+        self
     }
 
-    fn get_offset(&self) -> usize {
-        self.offset
+    /// Sets the filter applied before compression when saving or writing the encoded image as PNG
+    pub fn set_filter_type(&mut self, filter_type: FilterType) -> &mut Self {
+        self.filter_type = filter_type;
+        self
     }
 
-    fn get_step_by_n_pixels(&self) -> usize {
-        self.skip_c
+    /// Sets the number of bytes read at once by `encode_reader`. The default is 4096.
+    /// If `0` is passed, it defaults to `1`
+    pub fn set_chunk_size(&mut self, size: usize) -> &mut Self {
+        self.chunk_size = size.max(1);
+        self
     }
 
-    fn get_use_channel(&self) -> &RgbChannel {
-        &self.encoding_channel
+    /// The options of this encoder, detached from its source image
+    pub fn config(&self) -> EncoderConfig {
+        EncoderConfig {
+            lsb_c: self.lsb_c,
+            skip_c: self.skip_c,
+            offset: self.offset,
+            spread: self.spread,
+            spread_stride: self.spread_stride,
+            channel_order: self.channel_order.clone(),
+            encoding_position: self.encoding_position.clone(),
+            padding: self.padding.clone(),
+            region: self.region,
+            bit_order: self.bit_order,
+            ecc: self.ecc,
+            compression_type: self.compression_type.clone(),
+            filter_type: self.filter_type.clone(),
+        }
     }
 
-    fn get_spread(&self) -> bool {
-        self.spread
+    /// Applies the options of `config` to this encoder, keeping its source image,
+    /// pixel selector and password
+    pub fn apply_config(&mut self, config: EncoderConfig) -> &mut Self {
+        self.lsb_c = config.lsb_c;
+        self.skip_c = config.skip_c;
+        self.offset = config.offset;
+        self.spread = config.spread;
+        self.spread_stride = config.spread_stride;
+        self.channel_order = config.channel_order;
+        self.encoding_position = config.encoding_position;
+        self.padding = config.padding;
+        self.region = config.region;
+        self.bit_order = config.bit_order;
+        self.ecc = config.ecc;
+        self.compression_type = config.compression_type;
+        self.filter_type = config.filter_type;
+        self
     }
 
-    fn get_position(&self) -> &ImagePosition {
-        &self.encoding_position
+    /// Estimates how many payload bytes can be encoded into the source image
+    /// with the current configuration
+    pub fn estimate_capacity(&self) -> usize {
+        capacity::bytes_available(self.source_image.dimensions(), self)
     }
 
-    fn set_padding(&mut self, value: &str) -> &mut Self {
-        self.padding = Some(String::from(value));
-        self
+    /// Describes how a payload of `payload_len` bytes fits into the source image
+    pub fn capacity_report(&self, payload_len: usize) -> CapacityReport {
+        capacity::analyze_capacity(payload_len, self.source_image.dimensions(), self)
     }
-}
 
-fn bytes_needed_for_data<R>(data: &[u8], rules: &R) -> usize
-where
-    R: ImageRules,
-{
-    (((data.len() * 8) - (rules.get_offset() * 3 * 8)) * rules.get_step_by_n_pixels())
-        / rules.get_use_n_lsb()
-    // total data bits   skipped pixels size in bits     iterator step size               bits used per pixel
-}
+    /// Encodes a string, such as a `&str` or a `String`, into the source image for this decoder
+    pub fn encode_string(&self, data: impl AsRef<str>) -> Result<EncodedImage, SteganographyError> {
+        self.encode_data(&self.seal(data.as_ref().as_bytes()))
+    }
 
-#[allow(dead_code)]
-fn eprint_color_changes(byte_map: &ByteEncodeMap, steps: usize) {
-    eprint!(
-        "Encoded in {} steps, {} pixel(s) modified -> ",
-        steps,
-        byte_map.affected_points.len()
-    );
-    for item in &byte_map.affected_points {
-        eprint!(" | {}", item);
+    /// Encodes arbitrary bytes into the source image for this decoder
+    pub fn encode_bytes(&self, data: impl AsRef<[u8]>) -> Result<EncodedImage, SteganographyError> {
+        self.encode_data(&self.seal(data.as_ref()))
     }
-    eprintln!("\n\n");
-}
 
-#[cfg(test)]
-mod tests {
-    fn ensure_out_dir() -> std::io::Result<()> {
-        std::fs::create_dir_all("tests/out")
+    /// Encodes the bytes read from `reader` into the source image. Data is read in chunks
+    /// of `chunk_size` bytes and written as soon as it is read, so the payload never needs
+    /// to be entirely in memory.
+    ///
+    /// Since the payload length is not known in advance, encoding fails with
+    /// `SteganographyError::InsufficientCapacity` only once the image is full, reporting
+    /// the payload bytes read so far. Spreading and encryption are not supported, as they
+    /// require the whole payload. The `changes` of the returned image are not recorded
+    pub fn encode_reader<R>(&self, reader: &mut R) -> Result<EncodedImage, SteganographyError>
+    where
+        R: Read + ?Sized,
+    {
+        self.validate()?;
+
+        if self.spread {
+            return Err(SteganographyError::InvalidConfiguration(String::from(
+                "Spreading requires the whole payload, use `encode_bytes` instead",
+            )));
+        }
+
+        if self.is_encrypted() {
+            return Err(SteganographyError::InvalidConfiguration(String::from(
+                "Encryption requires the whole payload, use `encode_bytes` instead",
+            )));
+        }
+
+        let wide_image = self.wide_image();
+        let low_image = wide_image.as_ref().map(low_bytes);
+        let img = low_image.as_ref().unwrap_or(&self.source_image);
+        let channel_order = channel_indices(self.get_channel_order());
+        let padding_bits = self.padding_bits();
+
+        let altered_image = if self.channel_order.contains(&RgbChannel::Luma) {
+            let mut luma_img = luma_buffer(img);
+            self.encode_stream(&mut luma_img, reader, &channel_order, padding_bits.as_deref())?;
+            DynamicImage::ImageLuma8(luma_img)
+        } else if img.color().has_alpha() {
+            let mut rgba_img = rgba_buffer(img);
+            self.encode_stream(&mut rgba_img, reader, &channel_order, padding_bits.as_deref())?;
+            DynamicImage::ImageRgba8(rgba_img)
+        } else {
+            let mut rgb_img = rgb_buffer(img);
+            self.encode_stream(&mut rgb_img, reader, &channel_order, padding_bits.as_deref())?;
+            DynamicImage::ImageRgb8(rgb_img)
+        };
+        let altered_image = match wide_image {
+            Some(wide_image) => with_low_bytes(wide_image, &altered_image),
+            None => altered_image,
+        };
+
+        Ok(EncodedImage {
+            original_image: self.source_image.clone(),
+            altered_image,
+            map: vec![],
+            compression_type: self.compression_type.clone(),
+            filter_type: self.filter_type.clone(),
+        })
     }
 
-    use crate::{encoder::ImageEncoder, prelude::*};
+    /// Encodes the bytes yielded by `bytes` into the source image, like `encode_reader` does.
+    /// Bytes are pulled from the iterator only as they are encoded, up to `chunk_size` at a
+    /// time, so the payload is never entirely in memory and can be generated on the fly.
+    ///
+    /// Encoding fails with `SteganographyError::InsufficientCapacity` once the image is full,
+    /// and the iterator is not consumed any further. Use `encode_iter_exact` to check the
+    /// capacity before reading anything when the payload length is known
+    pub fn encode_iter<I>(&self, bytes: I) -> Result<EncodedImage, SteganographyError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        self.encode_reader(&mut IterReader { bytes })
+    }
 
-    #[test]
-    fn target_byte_size_calc() {
-        let mut encoder = ImageEncoder::default();
-        assert_eq!(super::bytes_needed_for_data(&[8, 1, 2, 3], &encoder), 32);
-        encoder.set_use_n_lsb(2);
-        assert_eq!(super::bytes_needed_for_data(&[8, 1, 2, 3], &encoder), 16);
-        encoder.set_step_by_n_pixels(2);
-        assert_eq!(super::bytes_needed_for_data(&[8, 1, 2, 3], &encoder), 32);
+    /// Like `encode_iter`, for iterators knowing their length. Fails with
+    /// `SteganographyError::InsufficientCapacity` before pulling any byte if the payload
+    /// does not fit into the source image
+    pub fn encode_iter_exact<I>(&self, bytes: I) -> Result<EncodedImage, SteganographyError>
+    where
+        I: ExactSizeIterator<Item = u8>,
+    {
+        self.validate()?;
+
+        let report = self.capacity_report(bytes.len());
+        if !report.fits {
+            return Err(SteganographyError::InsufficientCapacity {
+                required: bytes.len(),
+                available: (bytes.len() as i64 + report.headroom_bytes).max(0) as usize,
+            });
+        }
+
+        self.encode_iter(bytes)
     }
 
-    #[test]
-    fn simple_encoding() {
-        ensure_out_dir().unwrap();
+    /// Encodes the contents of the file at `path` into the source image, preceded by a
+    /// `FileHeader` holding its name and length. Files encoded this way can be written
+    /// back with `ImageDecoder::decode_file`.
+    ///
+    /// Encryption is not supported, as the decoder reads the `FileHeader` before the file
+    pub fn encode_file(&self, path: &Path) -> Result<EncodedImage, SteganographyError> {
+        if self.is_encrypted() {
+            return Err(SteganographyError::InvalidConfiguration(String::from(
+                "Encryption is not supported when encoding files, use `encode_with_header` instead",
+            )));
+        }
 
-        let encode_result = super::ImageEncoder::from("tests/images/red_panda.jpg")
-            .set_use_n_lsb(2)
-            .set_use_channel(RgbChannel::Blue)
-            .encode_data(
-                b"
-                Midway upon the journey of our life
-                I found myself within a forest dark,
-                For the straightforward pathway had been lost.
-                Ah me! how hard a thing it is to say
-                What was this forest savage, rough, and stern,
-                Which in the very thought renews the fear.
-                So bitter is it, death is little more;
-                But of the good to treat, which there I found,
-                Speak will I of the other things I saw there.
-                I cannot well repeat how there I entered,
-                So full was I of slumber at the moment
-                In which I had abandoned the true way.",
-            );
+        let filename = path.file_name().and_then(|name| name.to_str()).ok_or_else(|| {
+            SteganographyError::InvalidConfiguration(format!(
+                "{} has no valid UTF-8 file name",
+                path.display()
+            ))
+        })?;
+        let contents = std::fs::read(path)?;
+        let mut data = FileHeader::new(filename, contents.len())?.encode();
+        data.extend_from_slice(&contents);
 
-        assert!(encode_result.is_ok(), "Encoding failed");
+        self.encode_data(&data)
+    }
+
+    /// Encodes arbitrary bytes into the source image, preceded by a `Header` describing
+    /// them. Data encoded this way can be decoded with `ImageDecoder::decode_with_header`,
+    /// without any marker. When encrypting, the header is not encrypted and describes
+    /// the encrypted payload
+    pub fn encode_with_header(&self, data: impl AsRef<[u8]>) -> Result<EncodedImage, SteganographyError> {
+        self.encode_framed_after(data.as_ref(), 0)
+    }
+
+    /// Splits `data` into shards encoded with `encode_with_header` into each of `covers`, in
+    /// order, using the settings of this encoder. Each shard is as large as its cover allows,
+    /// and covers left once the whole payload is encoded are not used. The shards can be
+    /// decoded back into `data` with `ImageDecoder::decode_multi`.
+    ///
+    /// Fails with `SteganographyError::InsufficientCapacity` if `data` does not fit into all
+    /// the covers, before encoding any of them
+    pub fn encode_split(&self, data: &[u8], covers: Vec<DynamicImage>) -> Result<Vec<EncodedImage>, SteganographyError> {
+        #[cfg(feature = "crypto")]
+        let overhead = HEADER_SIZE + if self.is_encrypted() { crypto::OVERHEAD } else { 0 };
+        #[cfg(not(feature = "crypto"))]
+        let overhead = HEADER_SIZE;
+
+        let shards: Vec<ImageEncoder> = covers
+            .into_iter()
+            .map(|cover| ImageEncoder {
+                source_image: cover,
+                ..self.clone()
+            })
+            .collect();
+        let capacities: Vec<usize> = shards
+            .iter()
+            .map(|shard| shard.estimate_capacity().saturating_sub(overhead))
+            .collect();
+        let available: usize = capacities.iter().sum();
+        if data.len() > available {
+            return Err(SteganographyError::InsufficientCapacity {
+                required: data.len(),
+                available,
+            });
+        }
+
+        let mut encoded = vec![];
+        let mut rest = data;
+        for (shard, capacity) in shards.iter().zip(capacities) {
+            if rest.is_empty() && !encoded.is_empty() {
+                break;
+            }
+            let (chunk, remaining) = rest.split_at(capacity.min(rest.len()));
+            encoded.push(shard.encode_with_header(chunk)?);
+            rest = remaining;
+        }
+
+        Ok(encoded)
+    }
+
+    /// Encodes arbitrary bytes like `encode_with_header`, preceded by `Metadata` describing
+    /// the settings of this encoder. Data encoded this way can be decoded with
+    /// `ImageDecoder::decode_self_describing`, without configuring the decoder.
+    ///
+    /// The metadata takes the first `METADATA_PIXELS` pixels of the image, and the payload
+    /// is encoded after them. Fails if the settings can not be described by `Metadata`
+    pub fn encode_self_describing(&self, data: impl AsRef<[u8]>) -> Result<EncodedImage, SteganographyError> {
+        let metadata = Metadata::encode_into(self)?;
+        let payload = self.encode_framed_after(data.as_ref(), METADATA_PIXELS)?;
+        let described = ImageEncoder::from_image(payload.altered_image).encode_bytes(metadata)?;
+
+        Ok(EncodedImage {
+            original_image: payload.original_image,
+            altered_image: described.altered_image,
+            map: described.map.into_iter().chain(payload.map).collect(),
+            compression_type: self.compression_type.clone(),
+            filter_type: self.filter_type.clone(),
+        })
+    }
+
+    /// Encodes `data`, encrypted if a key is set, preceded by a `Header` describing it and
+    /// skipping `reserved_pixels` more pixels than the configured ones. When spreading, the
+    /// header is not spread so that it can be read before knowing the payload length
+    fn encode_framed_after(&self, data: &[u8], reserved_pixels: usize) -> Result<EncodedImage, SteganographyError> {
+        let data = self.seal(data);
+        let header = self.header_for(&data)?.encode();
+        let real_offset = self.real_offset() + reserved_pixels;
+
+        if !self.spread {
+            let mut framed_data = Vec::with_capacity(HEADER_SIZE + data.len());
+            framed_data.extend_from_slice(&header);
+            framed_data.extend_from_slice(&data);
+
+            return self.encode_segments(&[Segment {
+                data: &framed_data,
+                start: real_offset,
+                step: self.skip_c,
+            }]);
+        }
+
+        let header_pixels = capacity::pixels_spanned(HEADER_SIZE, self);
+        let stride = capacity::spread_stride_after(
+            self.source_image.dimensions(),
+            data.len(),
+            self,
+            reserved_pixels + header_pixels,
+        );
+        self.encode_segments(&[
+            Segment {
+                data: &header,
+                start: real_offset,
+                step: self.skip_c,
+            },
+            Segment {
+                data: &data,
+                start: real_offset + header_pixels,
+                step: self.skip_c * stride,
+            },
+        ])
+    }
+
+    /// The `Header` describing `data`, once encrypted if a key is set
+    fn header_for(&self, data: &[u8]) -> Result<Header, SteganographyError> {
+        let mut flags = if self.spread { FLAG_SPREAD } else { 0 };
+        if self.ecc {
+            flags |= FLAG_ECC;
+        }
+        if self.is_encrypted() {
+            flags |= FLAG_ENCRYPTED;
+        }
+        Header::new(
+            data,
+            self.lsb_c as u8,
+            u8::from(self.get_use_channel().clone()),
+            flags,
+        )
+    }
+
+    /// Encodes arbitrary bytes into the source image, preceded by their length as framed by
+    /// `header::frame_with_length` and followed by their CRC32 as a `CRC_SIZE` bytes little
+    /// endian integer. Data encoded this way can be decoded and checked with
+    /// `ImageDecoder::decode_with_crc`. When encrypting, the length and the CRC32 are
+    /// those of the encrypted payload
+    pub fn encode_with_crc(&self, data: impl AsRef<[u8]>) -> Result<EncodedImage, SteganographyError> {
+        let data = self.seal(data.as_ref());
+        let mut checked_data = frame_with_length(&data)?;
+        checked_data.reserve(CRC_SIZE);
+        checked_data.extend_from_slice(&crc32(&data).to_le_bytes());
+
+        self.encode_data(&checked_data)
+    }
+
+    /// Checks that the configuration of this encoder can be used on its source image.
+    /// Called before encoding, fails with `SteganographyError::InvalidConfiguration`
+    /// describing the first problem found
+    pub fn validate(&self) -> Result<(), SteganographyError> {
+        let invalid = |reason: String| Err(SteganographyError::InvalidConfiguration(reason));
+
+        if !(1..=8).contains(&self.lsb_c) {
+            return invalid(format!(
+                "The number of least significant bits must be between 1 and 8, got {}",
+                self.lsb_c
+            ));
+        }
+
+        if self.skip_c == 0 {
+            return invalid(String::from("The pixel step must be at least 1"));
+        }
+
+        if self.channel_order.is_empty() {
+            return invalid(String::from(
+                "At least one color channel must be used for encoding",
+            ));
+        }
+
+        if self.channel_order.contains(&RgbChannel::Luma)
+            && !(is_grayscale(&self.source_image)
+                && self.channel_order.iter().all(|channel| *channel == RgbChannel::Luma))
+        {
+            return invalid(String::from(
+                "The luma channel can only be used alone, on grayscale images",
+            ));
+        }
+
+        if self.rgb16_mode && self.channel_order.contains(&RgbChannel::Luma) {
+            return invalid(String::from(
+                "The luma channel can not be used in 16 bit mode",
+            ));
+        }
+
+        let (width, height) = self.source_image.dimensions();
+        if let Some((x1, y1, x2, y2)) = self.region {
+            if x1 >= x2 || y1 >= y2 || x2 > width || y2 > height {
+                return invalid(format!(
+                    "The region ({}, {}, {}, {}) is empty or exceeds the {}x{} image",
+                    x1, y1, x2, y2, width, height
+                ));
+            }
+        }
+
+        let (area_width, area_height) = region_dimensions(self.encoding_area());
+        let pixel_count = area_width as usize * area_height as usize;
+        if self.offset >= pixel_count {
+            return invalid(format!(
+                "The offset {} is beyond the {} pixels available for encoding",
+                self.offset, pixel_count
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The source image widened to 16 bits per channel in 16 bit mode, keeping its alpha
+    /// channel if any
+    fn wide_image(&self) -> Option<DynamicImage> {
+        if !self.rgb16_mode {
+            return None;
+        }
+
+        let img = &self.source_image;
+        Some(if img.color().has_alpha() {
+            DynamicImage::ImageRgba16(img.to_rgba16())
+        } else {
+            DynamicImage::ImageRgb16(img.to_rgb16())
+        })
+    }
+
+    /// The part of the source image that can be modified
+    fn encoding_area(&self) -> Region {
+        clamp_region(self.source_image.dimensions(), self.region)
+    }
+
+    /// The index of the first pixel holding data, among the ones selected in the encoding area
+    fn real_offset(&self) -> usize {
+        self.encoding_position
+            .pixel_offset(region_dimensions(self.encoding_area()))
+            + self.offset
+    }
+
+    /// The pixels to encode into, in order, with the encoding starting from `real_offset`
+    /// and using one pixel every `step`
+    fn selected_pixels(&self, real_offset: usize, step: usize) -> Box<dyn Iterator<Item = (u32, u32)>> {
+        let config = SelectorConfig {
+            start: real_offset,
+            step,
+        };
+        pixel_order(
+            self.pixel_selector.as_ref(),
+            self.encoding_area(),
+            &config,
+            self.permutation_seed,
+        )
+    }
+
+    /// The bits of the padding string, if any
+    fn padding_bits(&self) -> Option<Vec<bool>> {
+        self.get_padding().map(|padding_str| {
+            padding_str
+                .as_bytes()
+                .view_bits::<Lsb0>()
+                .iter()
+                .map(|bit| *bit)
+                .collect()
+        })
+    }
+
+    /// Wheter payloads are encrypted before being encoded
+    fn is_encrypted(&self) -> bool {
+        #[cfg(feature = "crypto")]
+        if self.key.is_some() {
+            return true;
+        }
+
+        false
+    }
+
+    /// Encrypts `data` if a key is set, leaving it untouched otherwise
+    fn seal<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        #[cfg(feature = "crypto")]
+        if let Some(key) = &self.key {
+            return Cow::Owned(crypto::seal(data, &key.0));
+        }
+
+        Cow::Borrowed(data)
+    }
+
+    fn encode_data(&self, data: &[u8]) -> Result<EncodedImage, SteganographyError> {
+        self.validate()?;
+
+        let report = self.capacity_report(data.len());
+        if !report.fits {
+            return Err(SteganographyError::InsufficientCapacity {
+                required: data.len(),
+                available: (data.len() as i64 + report.headroom_bytes).max(0) as usize,
+            });
+        }
+
+        self.encode_data_after(data, 0)
+    }
+
+    /// Encodes `data` skipping `reserved_pixels` more pixels than the configured ones
+    fn encode_data_after(&self, data: &[u8], reserved_pixels: usize) -> Result<EncodedImage, SteganographyError> {
+        let stride = capacity::spread_stride_after(
+            self.source_image.dimensions(),
+            data.len(),
+            self,
+            reserved_pixels,
+        );
+
+        self.encode_segments(&[Segment {
+            data,
+            start: self.real_offset() + reserved_pixels,
+            step: self.skip_c * stride,
+        }])
+    }
+
+    /// Encodes each one of `segments` into the source image, in order. Padding, if any, is
+    /// put before the first segment and after the last one
+    fn encode_segments(&self, segments: &[Segment]) -> Result<EncodedImage, SteganographyError> {
+        self.validate()?;
+
+        let (area_width, area_height) = region_dimensions(self.encoding_area());
+        let area_pixels = area_width as usize * area_height as usize;
+        for segment in segments {
+            let pixels_needed = capacity::pixels_consumed_by_step(segment.data.len(), self, segment.step);
+            if pixels_needed > area_pixels.saturating_sub(segment.start) {
+                return Err(SteganographyError::InsufficientCapacity {
+                    required: segments.iter().map(|segment| segment.data.len()).sum(),
+                    available: self.estimate_capacity(),
+                });
+            }
+        }
+
+        // Data is XORed before being protected, so that decoders correct it first
+        let masked_data: Vec<Vec<u8>>;
+        let segments: Cow<[Segment]> = match self.xor_key.as_deref() {
+            Some(key) => {
+                masked_data = segments
+                    .iter()
+                    .map(|segment| {
+                        let mut data = segment.data.to_vec();
+                        apply_xor_key(&mut data, key, 0);
+                        data
+                    })
+                    .collect();
+                Cow::Owned(
+                    segments
+                        .iter()
+                        .zip(&masked_data)
+                        .map(|(segment, data)| Segment { data, ..*segment })
+                        .collect(),
+                )
+            }
+            None => Cow::Borrowed(segments),
+        };
+
+        let protected_data: Vec<Vec<u8>>;
+        let segments: Cow<[Segment]> = if self.ecc {
+            protected_data = segments
+                .iter()
+                .map(|segment| hamming_encode(segment.data))
+                .collect();
+            Cow::Owned(
+                segments
+                    .iter()
+                    .zip(&protected_data)
+                    .map(|(segment, data)| Segment { data, ..*segment })
+                    .collect(),
+            )
+        } else {
+            segments
+        };
+
+        // In 16 bit mode, data is encoded into an 8 bit image made of the low byte of each
+        // channel, which is then merged back into the 16 bit image
+        let wide_image = self.wide_image();
+        let low_image = wide_image.as_ref().map(low_bytes);
+        let img = low_image.as_ref().unwrap_or(&self.source_image);
+        let channel_order = channel_indices(self.get_channel_order());
+        let padding_bits = self.padding_bits();
+
+        // Grayscale images encoded on their luma channel stay grayscale, and images with
+        // transparency keep their alpha channel, which can also hold data
+        let (altered_image, encode_maps) = if self.channel_order.contains(&RgbChannel::Luma) {
+            let mut luma_img = luma_buffer(img);
+            let encode_maps = self.encode_pixels(
+                &mut luma_img,
+                &segments,
+                &channel_order,
+                padding_bits.as_deref(),
+            )?;
+            (DynamicImage::ImageLuma8(luma_img), encode_maps)
+        } else if img.color().has_alpha() {
+            let mut rgba_img = rgba_buffer(img);
+            let encode_maps = self.encode_pixels(
+                &mut rgba_img,
+                &segments,
+                &channel_order,
+                padding_bits.as_deref(),
+            )?;
+            (DynamicImage::ImageRgba8(rgba_img), encode_maps)
+        } else {
+            let mut rgb_img = rgb_buffer(img);
+            let encode_maps = self.encode_pixels(
+                &mut rgb_img,
+                &segments,
+                &channel_order,
+                padding_bits.as_deref(),
+            )?;
+            (DynamicImage::ImageRgb8(rgb_img), encode_maps)
+        };
+        let altered_image = match wide_image {
+            Some(wide_image) => with_low_bytes(wide_image, &altered_image),
+            None => altered_image,
+        };
+
+        Ok(EncodedImage {
+            original_image: self.source_image.clone(),
+            altered_image,
+            map: encode_maps,
+            compression_type: self.compression_type.clone(),
+            filter_type: self.filter_type.clone(),
+        })
+    }
+
+    /// Encodes each one of `segments` into the pixels of `pixel_buffer`
+    fn encode_pixels<P>(
+        &self,
+        pixel_buffer: &mut ImageBuffer<P, Vec<u8>>,
+        segments: &[Segment],
+        channel_order: &[usize],
+        padding_bits: Option<&[bool]>,
+    ) -> Result<Vec<ByteEncodeMap>, SteganographyError>
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        let first_pixel = segments.first().map_or(0, |segment| segment.start);
+        self.prepare_pixels(pixel_buffer, first_pixel, channel_order, padding_bits)?;
+
+        let mut encode_maps = vec![];
+        for (index, segment) in segments.iter().enumerate() {
+            let padding_bits = padding_bits.filter(|_| index == segments.len() - 1);
+
+            #[cfg(not(feature = "rayon"))]
+            let segment_maps = self.encode_pixels_sequential(
+                pixel_buffer,
+                segment.start,
+                segment.step,
+                segment.data,
+                channel_order,
+                padding_bits,
+            )?;
+
+            #[cfg(feature = "rayon")]
+            let segment_maps = self.encode_pixels_parallel(
+                pixel_buffer,
+                segment.start,
+                segment.step,
+                segment.data,
+                channel_order,
+                padding_bits,
+            )?;
+
+            encode_maps.extend(segment_maps);
+        }
+
+        Ok(encode_maps)
+    }
+
+    /// Checks that `channel_order` fits the pixels of `pixel_buffer` and puts the leading
+    /// padding bits, if any, in every pixel before `real_offset`
+    fn prepare_pixels<P>(
+        &self,
+        pixel_buffer: &mut ImageBuffer<P, Vec<u8>>,
+        real_offset: usize,
+        channel_order: &[usize],
+        padding_bits: Option<&[bool]>,
+    ) -> Result<(), SteganographyError>
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        if channel_order
+            .iter()
+            .any(|channel| *channel >= P::CHANNEL_COUNT as usize)
+        {
+            return Err(SteganographyError::InvalidConfiguration(String::from(
+                "The alpha channel can only be used on images with transparency",
+            )));
+        }
+
+        // Put leading padding bits in every pixel before the starting one
+        if let Some(padding_bits_value) = padding_bits {
+            let mut padding_iter = padding_bits_value.iter().copied().cycle();
+            let leading_pixels = pixel_order(
+                self.pixel_selector.as_ref(),
+                self.encoding_area(),
+                &SelectorConfig::default(),
+                None,
+            );
+            for (x, y) in leading_pixels.take(real_offset) {
+                put_padding_bits(
+                    pixel_buffer.get_pixel_mut(x, y),
+                    channel_order,
+                    self.lsb_c,
+                    &mut padding_iter,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the bytes read from `reader` into the pixels of `pixel_buffer`, one chunk
+    /// at a time, then puts the trailing padding bits
+    fn encode_stream<P, R>(
+        &self,
+        pixel_buffer: &mut ImageBuffer<P, Vec<u8>>,
+        reader: &mut R,
+        channel_order: &[usize],
+        padding_bits: Option<&[bool]>,
+    ) -> Result<(), SteganographyError>
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+        R: Read + ?Sized,
+    {
+        let real_offset = self.real_offset();
+        self.prepare_pixels(pixel_buffer, real_offset, channel_order, padding_bits)?;
+
+        let mut writer = PayloadWriter::new(self.selected_pixels(real_offset, self.skip_c), channel_order, self);
+        let mut chunk = vec![0u8; self.chunk_size];
+        let mut bytes_read = 0;
+
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(key) = self.xor_key.as_deref() {
+                apply_xor_key(&mut chunk[..read], key, bytes_read);
+            }
+            bytes_read += read;
+
+            let protected_chunk;
+            let bytes = if self.ecc {
+                protected_chunk = hamming_encode(&chunk[..read]);
+                &protected_chunk[..]
+            } else {
+                &chunk[..read]
+            };
+
+            for byte_to_encode in bytes {
+                if writer.write_byte(pixel_buffer, *byte_to_encode)?.is_none() {
+                    return Err(SteganographyError::InsufficientCapacity {
+                        required: bytes_read,
+                        available: self.estimate_capacity(),
+                    });
+                }
+            }
+        }
+
+        if let Some(padding_bits_value) = padding_bits {
+            writer.pad(pixel_buffer, padding_bits_value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encodes `data` walking the pixels of `pixel_buffer` one at a time, starting from `real_offset`
+    /// and using one pixel every `step`
+    #[cfg_attr(feature = "rayon", allow(dead_code))]
+    fn encode_pixels_sequential<P>(
+        &self,
+        pixel_buffer: &mut ImageBuffer<P, Vec<u8>>,
+        real_offset: usize,
+        step: usize,
+        data: &[u8],
+        channel_order: &[usize],
+        padding_bits: Option<&[bool]>,
+    ) -> Result<Vec<ByteEncodeMap>, SteganographyError>
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        let mut writer = PayloadWriter::new(self.selected_pixels(real_offset, step), channel_order, self);
+        let mut encode_maps: Vec<ByteEncodeMap> = vec![];
+
+        for byte_to_encode in data.iter() {
+            match writer.write_byte(pixel_buffer, *byte_to_encode)? {
+                Some(byte_map) => encode_maps.push(byte_map),
+                None => break,
+            }
+        }
+
+        // Put trailing padding bits in the rest of the current pixel and in
+        // every pixel that would have been used for more data
+        if let Some(padding_bits_value) = padding_bits {
+            writer.pad(pixel_buffer, padding_bits_value)?;
+        }
+
+        Ok(encode_maps)
+    }
+
+    /// Encodes `data` into `pixel_buffer` processing pixels in parallel, starting from `real_offset`
+    /// and using one pixel every `step`. Produces the same output as `encode_pixels_sequential`.
+    #[cfg(feature = "rayon")]
+    fn encode_pixels_parallel<P>(
+        &self,
+        pixel_buffer: &mut ImageBuffer<P, Vec<u8>>,
+        real_offset: usize,
+        step: usize,
+        data: &[u8],
+        channel_order: &[usize],
+        padding_bits: Option<&[bool]>,
+    ) -> Result<Vec<ByteEncodeMap>, SteganographyError>
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        use rayon::prelude::*;
+
+        let byte_step = std::mem::size_of::<u8>() * 8;
+        let image_width = pixel_buffer.width() as usize;
+        let pixel_size = P::CHANNEL_COUNT as usize;
+        let data_bits = data.len() * byte_step;
+        let lsb_c = self.lsb_c;
+
+        // Unless pixels are selected in their natural order, the position of each pixel
+        // in the selection determines which bits it holds
+        let positions: Option<Vec<usize>> =
+            if self.pixel_selector.is_linear()
+                && self.permutation_seed.is_none()
+                && self.region.is_none()
+            {
+                None
+            } else {
+                let mut positions = vec![UNSELECTED; pixel_buffer.pixels().len()];
+                let selection = self.selected_pixels(real_offset, step);
+                for (position, (x, y)) in selection.enumerate() {
+                    positions[y as usize * image_width + x as usize] = position;
+                }
+                Some(positions)
+            };
+
+        let raw_pixels: &mut [u8] = pixel_buffer;
+        let (start, end, chunk_size) = if positions.is_some() {
+            (0, raw_pixels.len(), pixel_size)
+        } else {
+            let start = (real_offset * pixel_size).min(raw_pixels.len());
+
+            // Unless the whole image is being filled, only the pixels holding data are visited
+            let end = if padding_bits.is_some() {
+                raw_pixels.len()
+            } else {
+                let bits_per_pixel = lsb_c * channel_order.len();
+                let pixels_needed = data_bits.div_ceil(bits_per_pixel);
+                (start + pixels_needed * step * pixel_size).min(raw_pixels.len())
+            };
+
+            (start, end, step * pixel_size)
+        };
+
+        // Each chunk begins with the pixel to modify, followed by the pixels skipped after it.
+        // When positions are known, each chunk is a single pixel
+        let mut changes: Vec<(usize, Vec<(usize, ColorChange)>)> = raw_pixels[start..end]
+            .par_chunks_mut(chunk_size)
+            .enumerate()
+            .map(|(chunk_index, chunk)| {
+                let (absolute_index, position) = match &positions {
+                    Some(positions) => (chunk_index, positions[chunk_index]),
+                    None => (real_offset + chunk_index * step, chunk_index),
+                };
+                if position == UNSELECTED {
+                    return Ok((position, vec![]));
+                }
+
+                let x = (absolute_index % image_width) as u32;
+                let y = (absolute_index / image_width) as u32;
+                let pixel = P::from_slice_mut(&mut chunk[..pixel_size]);
+                let mut pixel_changes = vec![];
+
+                for (channel_index, channel) in channel_order.iter().enumerate() {
+                    let channel_bit_index = (position * channel_order.len() + channel_index) * lsb_c;
+
+                    // A channel can hold the end of a byte and the start of the next one
+                    let mut channel_bits = 0;
+                    while channel_bits < lsb_c {
+                        let bit_index = channel_bit_index + channel_bits;
+
+                        if bit_index < data_bits {
+                            let occurrence = bit_index / byte_step;
+                            let bits_offset = bit_index % byte_step;
+                            let bits_count = (lsb_c - channel_bits).min(byte_step - bits_offset);
+                            let arranged_byte = self.bit_order.arrange(data[occurrence]);
+                            let bits_to_encode = byte_to_bits(&arranged_byte);
+                            let original_color: Rgb<u8> = pixel.to_rgb().into();
+
+                            put_bits(
+                                &bits_to_encode[bits_offset..bits_offset + bits_count],
+                                pixel.channels_mut()[*channel].view_bits_mut::<Lsb0>(),
+                                channel_bits,
+                            )?;
+
+                            pixel_changes.push((
+                                occurrence,
+                                ColorChange(x, y, original_color, pixel.to_rgb().into()),
+                            ));
+                            channel_bits += bits_count;
+                        } else {
+                            if let Some(padding) = padding_bits.filter(|p| !p.is_empty()) {
+                                let padding_offset = (bit_index - data_bits) % padding.len();
+                                let padding_to_encode: BitVec<Lsb0, u8> = padding
+                                    .iter()
+                                    .copied()
+                                    .cycle()
+                                    .skip(padding_offset)
+                                    .take(lsb_c - channel_bits)
+                                    .collect();
+                                put_bits(
+                                    &padding_to_encode,
+                                    pixel.channels_mut()[*channel].view_bits_mut::<Lsb0>(),
+                                    channel_bits,
+                                )?;
+                            }
+                            break;
+                        }
+                    }
+                }
+
+                Ok((position, pixel_changes))
+            })
+            .collect::<Result<_, SteganographyError>>()?;
+
+        if positions.is_some() {
+            changes.sort_unstable_by_key(|(position, _)| *position);
+        }
+        let selected_pixels = changes
+            .iter()
+            .filter(|(position, _)| *position != UNSELECTED)
+            .count();
+
+        let mut encode_maps: Vec<ByteEncodeMap> = vec![];
+        let ordered_changes = changes.into_iter().flat_map(|(_, pixel_changes)| pixel_changes);
+        for (occurrence, color_change) in ordered_changes {
+            if encode_maps.len() <= occurrence {
+                let mut byte_map = ByteEncodeMap::new();
+                byte_map.encoded_byte = Some(data[occurrence]);
+                encode_maps.push(byte_map);
+            }
+            encode_maps[occurrence].affected_points.push(color_change);
+        }
+
+        // A byte that did not fit entirely in the image is not reported
+        encode_maps.truncate(selected_pixels * lsb_c * channel_order.len() / byte_step);
+
+        Ok(encode_maps)
+    }
+}
+
+// fn encode_bytes<'a>(bytes: &[u8], into_iter: impl Iterator<Item = (u32, u32, &'a mut Rgb<u8>)>) {}
+
+/// A part of a payload encoded from its own starting pixel, using one pixel every `step`
+#[derive(Clone, Copy)]
+struct Segment<'a> {
+    data: &'a [u8],
+    start: usize,
+    step: usize,
+}
+
+/// Writes payload bytes one after the other into the selected pixels of an image.
+/// Consecutive bytes can share a pixel, each channel holding `lsb_c` bits
+struct PayloadWriter<'a> {
+    pixels: Box<dyn Iterator<Item = (u32, u32)>>,
+    channel_order: &'a [usize],
+    lsb_c: usize,
+    bit_order: BitOrder,
+
+    // The pixel currently being written and the index of its next channel to use,
+    // according to the channel order
+    current_pixel: Option<(u32, u32)>,
+    channel_cursor: usize,
+
+    // The number of bits already written in the channel at `channel_cursor`, when
+    // `lsb_c` does not divide a byte
+    channel_bits: usize,
+}
+
+impl<'a> PayloadWriter<'a> {
+    fn new(
+        pixels: Box<dyn Iterator<Item = (u32, u32)>>,
+        channel_order: &'a [usize],
+        encoder: &ImageEncoder,
+    ) -> Self {
+        Self {
+            pixels,
+            channel_order,
+            lsb_c: encoder.lsb_c,
+            bit_order: encoder.bit_order,
+            current_pixel: None,
+            channel_cursor: 0,
+            channel_bits: 0,
+        }
+    }
+
+    /// Writes `byte` into the next pixels. Returns `None` if the pixels run out
+    /// before the whole byte is written
+    fn write_byte<P>(
+        &mut self,
+        pixel_buffer: &mut ImageBuffer<P, Vec<u8>>,
+        byte: u8,
+    ) -> Result<Option<ByteEncodeMap>, SteganographyError>
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        let mut byte_map = ByteEncodeMap::new();
+        byte_map.encoded_byte = Some(byte);
+
+        let arranged_byte = self.bit_order.arrange(byte);
+        let bits_to_encode = byte_to_bits(&arranged_byte);
+
+        let byte_step = std::mem::size_of::<u8>() * 8;
+        let mut bits_offset = 0;
+        while bits_offset < byte_step {
+            // Get the chunk of bits at bits_offset fitting in the current channel
+            let bits_count = (self.lsb_c - self.channel_bits).min(byte_step - bits_offset);
+            let bits_to_encode_slice: &BitSlice<Lsb0, u8> =
+                &bits_to_encode[bits_offset..bits_offset + bits_count];
+
+            if self.channel_cursor == 0 && self.channel_bits == 0 {
+                self.current_pixel = self.pixels.next();
+            }
+
+            let (x, y) = match self.current_pixel {
+                Some(pixel) => pixel,
+                None => return Ok(None),
+            };
+            let pixel_to_modify = pixel_buffer.get_pixel_mut(x, y);
+            let mut color_change = ColorChange(x, y, pixel_to_modify.to_rgb().into(), Rgb::from([0, 0, 0]));
+            let bits_to_modify = pixel_to_modify
+                .channels_mut()
+                .get_mut::<usize>(self.channel_order[self.channel_cursor])
+                .unwrap()
+                .view_bits_mut::<Lsb0>();
+
+            put_bits(bits_to_encode_slice, bits_to_modify, self.channel_bits)?;
+
+            color_change.3 = pixel_to_modify.to_rgb().into();
+            byte_map.affected_points.push(color_change);
+            bits_offset += bits_count;
+            self.channel_bits += bits_count;
+            if self.channel_bits == self.lsb_c {
+                self.channel_bits = 0;
+                self.channel_cursor = (self.channel_cursor + 1) % self.channel_order.len();
+            }
+        }
+
+        Ok(Some(byte_map))
+    }
+
+    /// Puts `padding_bits`, repeated, in the rest of the current pixel and in every
+    /// pixel left
+    fn pad<P>(&mut self, pixel_buffer: &mut ImageBuffer<P, Vec<u8>>, padding_bits: &[bool]) -> Result<(), SteganographyError>
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        let mut padding_iter = padding_bits.iter().copied().cycle();
+        if self.channel_bits != 0 {
+            if let Some((x, y)) = self.current_pixel {
+                let padding_to_encode: BitVec<Lsb0, u8> =
+                    padding_iter.by_ref().take(self.lsb_c - self.channel_bits).collect();
+                let bits_to_modify = pixel_buffer.get_pixel_mut(x, y).channels_mut()
+                    [self.channel_order[self.channel_cursor]]
+                    .view_bits_mut::<Lsb0>();
+                put_bits(&padding_to_encode, bits_to_modify, self.channel_bits)?;
+            }
+            self.channel_bits = 0;
+            self.channel_cursor = (self.channel_cursor + 1) % self.channel_order.len();
+        }
+        if self.channel_cursor != 0 {
+            if let Some((x, y)) = self.current_pixel {
+                put_padding_bits(
+                    pixel_buffer.get_pixel_mut(x, y),
+                    &self.channel_order[self.channel_cursor..],
+                    self.lsb_c,
+                    &mut padding_iter,
+                )?;
+            }
+        }
+        for (x, y) in self.pixels.by_ref() {
+            put_padding_bits(
+                pixel_buffer.get_pixel_mut(x, y),
+                self.channel_order,
+                self.lsb_c,
+                &mut padding_iter,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the bytes yielded by an iterator, pulling only as many as requested
+struct IterReader<I> {
+    bytes: I,
+}
+
+impl<I> Read for IterReader<I>
+where
+    I: Iterator<Item = u8>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut read = 0;
+        // The buffer comes first so that no byte is pulled once it is full
+        for (slot, byte) in buf.iter_mut().zip(&mut self.bytes) {
+            *slot = byte;
+            read += 1;
+        }
+        Ok(read)
+    }
+}
+
+/// Writes `bits` into `into`, starting from its bit at index `from`. Fails without
+/// writing anything if `bits` do not fit between `from` and the end of `into`
+fn put_bits(bits: &BitSlice<Lsb0, u8>, into: &mut BitSlice<Lsb0, u8>, from: usize) -> Result<(), SteganographyError> {
+    if from + bits.len() > into.len() {
+        return Err(SteganographyError::InvalidConfiguration(format!(
+            "Can not write {} bits from bit {} of a {} bit channel",
+            bits.len(),
+            from,
+            into.len()
+        )));
+    }
+
+    into[from..from + bits.len()].copy_from_bitslice(bits);
+    Ok(())
+}
+
+/// Writes the next `lsb_c` bits yielded by `padding` into each one of `channels` of `pixel`
+fn put_padding_bits<P, I>(pixel: &mut P, channels: &[usize], lsb_c: usize, padding: &mut I) -> Result<(), SteganographyError>
+where
+    P: Pixel<Subpixel = u8>,
+    I: Iterator<Item = bool>,
+{
+    for channel in channels {
+        let padding_to_encode: BitVec<Lsb0, u8> = padding.by_ref().take(lsb_c).collect();
+        put_bits(&padding_to_encode, pixel.channels_mut()[*channel].view_bits_mut::<Lsb0>(), 0)?;
+    }
+
+    Ok(())
+}
+
+/// Copies the pixels of `img` into an RGB buffer to encode into. Images that are already RGB
+/// are copied as they are, skipping the per pixel conversion of `DynamicImage::to_rgb8`. The
+/// copy itself is needed, as the source image is kept for later encodings and as
+/// `EncodedImage::original_image`
+fn rgb_buffer(img: &DynamicImage) -> RgbImage {
+    match img.as_rgb8() {
+        Some(buffer) => buffer.clone(),
+        None => img.to_rgb8(),
+    }
+}
+
+/// Copies the pixels of `img` into an RGBA buffer, like `rgb_buffer`
+fn rgba_buffer(img: &DynamicImage) -> RgbaImage {
+    match img.as_rgba8() {
+        Some(buffer) => buffer.clone(),
+        None => img.to_rgba8(),
+    }
+}
+
+/// An 8 bit image made of the low byte of each channel of `wide_image`, which must be a
+/// 16 bit RGB or RGBA image
+fn low_bytes(wide_image: &DynamicImage) -> DynamicImage {
+    let (width, height) = wide_image.dimensions();
+    match wide_image {
+        DynamicImage::ImageRgb16(buffer) => DynamicImage::ImageRgb8(
+            ImageBuffer::from_raw(width, height, buffer.iter().map(|value| *value as u8).collect())
+                .expect("The buffer has one byte per channel"),
+        ),
+        DynamicImage::ImageRgba16(buffer) => DynamicImage::ImageRgba8(
+            ImageBuffer::from_raw(width, height, buffer.iter().map(|value| *value as u8).collect())
+                .expect("The buffer has one byte per channel"),
+        ),
+        _ => unreachable!("16 bit mode only uses RGB and RGBA images"),
+    }
+}
+
+/// Replaces the low byte of each channel of `wide_image` with the matching channel of
+/// `low_image`, as produced by `low_bytes`
+fn with_low_bytes(wide_image: DynamicImage, low_image: &DynamicImage) -> DynamicImage {
+    let merge = |(value, low): (&mut u16, &u8)| *value = (*value & 0xff00) | *low as u16;
+    match wide_image {
+        DynamicImage::ImageRgb16(mut buffer) => {
+            buffer.iter_mut().zip(low_image.as_bytes()).for_each(merge);
+            DynamicImage::ImageRgb16(buffer)
+        }
+        DynamicImage::ImageRgba16(mut buffer) => {
+            buffer.iter_mut().zip(low_image.as_bytes()).for_each(merge);
+            DynamicImage::ImageRgba16(buffer)
+        }
+        _ => unreachable!("16 bit mode only uses RGB and RGBA images"),
+    }
+}
+
+/// Copies the pixels of `img` into an 8 bit grayscale buffer, like `rgb_buffer`
+fn luma_buffer(img: &DynamicImage) -> GrayImage {
+    match img.as_luma8() {
+        Some(buffer) => buffer.clone(),
+        None => img.to_luma8(),
+    }
+}
+
+impl ImageRules for ImageEncoder {
+    /// Skip the first `offset` pixels of the traversal
+    fn set_offset(&mut self, offset: usize) -> &mut Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the number of least significative bits to edit for each
+    /// byte in the source buffer. The default is 1. The higher the value gets
+    /// the least space is required to encode data into the source, but the resulting
+    /// image will get noticeably different from the original
+    fn set_use_n_lsb(&mut self, n: usize) -> &mut Self {
+        self.lsb_c = n;
+        self
+    }
+
+    /// Specifies wich color channel will be the one used to store information bits.
+    fn set_use_channel(&mut self, channel: RgbChannel) -> &mut Self {
+        self.channel_order = vec![channel];
+        self
+    }
+
+    fn set_channel_order(&mut self, order: Vec<RgbChannel>) -> &mut Self {
+        self.channel_order = order;
+        self
+    }
+
+    /// When encoding data, one pixel every `n` pixels will be edited, starting from the
+    /// offset pixel
+    fn set_step_by_n_pixels(&mut self, n: usize) -> &mut Self {
+        if n < 1 {
+            self.skip_c = 1;
+        } else {
+            self.skip_c = n;
+        }
+        self
+    }
+
+    fn set_spread(&mut self, value: bool) -> &mut Self {
+        self.spread = value;
+        self
+    }
+
+    fn set_position(&mut self, value: ImagePosition) -> &mut Self {
+        self.encoding_position = value;
+        self
+    }
+
+    fn get_use_n_lsb(&self) -> usize {
+        self.lsb_c
+    }
+
+    fn get_offset(&self) -> usize {
+        self.offset
+    }
+
+    fn get_step_by_n_pixels(&self) -> usize {
+        self.skip_c
+    }
+
+    fn get_use_channel(&self) -> &RgbChannel {
+        self.channel_order.first().unwrap_or(&RgbChannel::Blue)
+    }
+
+    fn get_channel_order(&self) -> &[RgbChannel] {
+        &self.channel_order
+    }
+
+    fn get_spread(&self) -> bool {
+        self.spread
+    }
+
+    fn set_spread_stride(&mut self, stride: usize) -> &mut Self {
+        self.spread_stride = stride;
+        self
+    }
+
+    fn get_spread_stride(&self) -> usize {
+        self.spread_stride
+    }
+
+    fn get_position(&self) -> &ImagePosition {
+        &self.encoding_position
+    }
+
+    fn set_padding(&mut self, value: impl AsRef<str>) -> &mut Self {
+        self.padding = Some(String::from(value.as_ref()));
+        self
+    }
+
+    fn get_padding(&self) -> Option<&str> {
+        self.padding.as_deref()
+    }
+
+    fn set_permutation_seed(&mut self, seed: Option<u64>) -> &mut Self {
+        self.permutation_seed = seed;
+        self
+    }
+
+    fn get_permutation_seed(&self) -> Option<u64> {
+        self.permutation_seed
+    }
+
+    fn set_pixel_selector(&mut self, selector: Box<dyn PixelSelector>) -> &mut Self {
+        self.pixel_selector = selector;
+        self
+    }
+
+    fn get_pixel_selector(&self) -> &dyn PixelSelector {
+        self.pixel_selector.as_ref()
+    }
+
+    fn set_region(&mut self, x1: u32, y1: u32, x2: u32, y2: u32) -> &mut Self {
+        self.region = Some((x1, y1, x2, y2));
+        self
+    }
+
+    fn get_region(&self) -> Option<Region> {
+        self.region
+    }
+
+    fn set_bit_order(&mut self, order: BitOrder) -> &mut Self {
+        self.bit_order = order;
+        self
+    }
+
+    fn get_bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    fn set_ecc(&mut self, enabled: bool) -> &mut Self {
+        self.ecc = enabled;
+        self
+    }
+
+    fn get_ecc(&self) -> bool {
+        self.ecc
+    }
+}
+
+#[allow(dead_code)]
+fn eprint_color_changes(byte_map: &ByteEncodeMap, steps: usize) {
+    eprint!(
+        "Encoded in {} steps, {} pixel(s) modified -> ",
+        steps,
+        byte_map.affected_points.len()
+    );
+    for item in &byte_map.affected_points {
+        eprint!(" | {}", item);
+    }
+    eprintln!("\n\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    fn ensure_out_dir() -> std::io::Result<()> {
+        std::fs::create_dir_all("tests/out")
+    }
+
+    use image::GenericImageView;
+
+    use crate::{decoder::ImageDecoder, encoder::ImageEncoder, error::SteganographyError, prelude::*};
+
+    #[test]
+    fn target_pixels_calc() {
+        use crate::capacity::pixels_consumed;
+
+        let mut encoder = ImageEncoder::default();
+        assert_eq!(pixels_consumed(4, &encoder), 32);
+        encoder.set_use_n_lsb(2);
+        assert_eq!(pixels_consumed(4, &encoder), 16);
+        encoder.set_step_by_n_pixels(2);
+        assert_eq!(pixels_consumed(4, &encoder), 31);
+        encoder.set_channel_order(vec![RgbChannel::Red, RgbChannel::Green]);
+        assert_eq!(pixels_consumed(4, &encoder), 15);
+    }
+
+    #[test]
+    fn insufficient_capacity() {
+        let encoder = ImageEncoder::default();
+        let result = encoder.encode_bytes([0u8; 1024]);
+
+        assert!(matches!(
+            result,
+            Err(SteganographyError::InsufficientCapacity { .. })
+        ));
+    }
+
+    #[test]
+    fn accepts_byte_containers() {
+        let encoder = ImageEncoder::default();
+        let expected = encoder.encode_bytes(b"seagul").unwrap().altered_image;
+
+        let results = vec![
+            encoder.encode_bytes(vec![b's', b'e', b'a', b'g', b'u', b'l']),
+            encoder.encode_bytes(String::from("seagul")),
+            encoder.encode_bytes(Box::<[u8]>::from(&b"seagul"[..])),
+            encoder.encode_string("seagul"),
+            encoder.encode_string(String::from("seagul")),
+        ];
+
+        for result in results {
+            assert_eq!(result.unwrap().altered_image, expected);
+        }
+    }
+
+    /// Reassembles `len` bytes from the blue channel LSB of the pixels starting at `from`
+    fn read_blue_lsb_bytes(image: &image::DynamicImage, from: usize, len: usize) -> Vec<u8> {
+        image
+            .to_rgb8()
+            .pixels()
+            .skip(from)
+            .take(len * 8)
+            .collect::<Vec<_>>()
+            .chunks(8)
+            .map(|pixels| {
+                pixels
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (i, pixel)| byte | ((pixel[2] & 1) << i))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn invalid_sources() {
+        assert!(matches!(
+            ImageEncoder::from_bytes(b"not an image"),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+        assert!(matches!(
+            ImageDecoder::try_from(&b"not an image"[..]),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+
+        match ImageEncoder::try_from("tests/images/missing.png") {
+            Err(SteganographyError::IoError(e)) => assert!(e.to_string().contains("tests/images/missing.png")),
+            _ => panic!("Loading a missing image should fail"),
+        }
+    }
+
+    #[test]
+    fn alpha_channel_without_transparency() {
+        let result = ImageEncoder::default()
+            .with_channel(RgbChannel::Alpha)
+            .encode_bytes(b"x");
+
+        assert!(matches!(
+            result,
+            Err(SteganographyError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_configurations() {
+        let encoders = vec![
+            ImageEncoder::default().with_lsb(0),
+            ImageEncoder::default().with_lsb(9),
+            ImageEncoder::default().with_channel_order(vec![]),
+            ImageEncoder::default().with_offset(256),
+            ImageEncoder::default().with_region(4, 4, 4, 8),
+            ImageEncoder::default().with_region(4, 4, 17, 8),
+            ImageEncoder::from_image(image::DynamicImage::new_rgb8(0, 0)),
+        ];
+
+        for encoder in encoders {
+            assert!(matches!(
+                encoder.validate(),
+                Err(SteganographyError::InvalidConfiguration(_))
+            ));
+            assert!(matches!(
+                encoder.encode_bytes(b"x"),
+                Err(SteganographyError::InvalidConfiguration(_))
+            ));
+        }
+
+        assert!(ImageEncoder::default().with_lsb(8).with_offset(255).validate().is_ok());
+    }
+
+    #[test]
+    fn leading_padding() {
+        let encoded = ImageEncoder::default()
+            .set_position(ImagePosition::TopRight)
+            .set_padding("ab")
+            .encode_bytes(b"x")
+            .expect("Encoding failed");
+
+        // The top right position skips the first row of 16 pixels
+        assert_eq!(read_blue_lsb_bytes(&encoded.altered_image, 0, 2), b"ab");
+        assert_eq!(read_blue_lsb_bytes(&encoded.altered_image, 16, 1), b"x");
+    }
+
+    #[test]
+    fn comparable_changes() {
+        let encoder = ImageEncoder::default().with_lsb(2);
+        let first = encoder.encode_bytes(b"seagul").unwrap();
+        let second = encoder.encode_bytes(b"seagul").unwrap();
+        assert_eq!(first.map, second.map);
+        assert_eq!(first.map[0].encoded_byte, Some(b's'));
+        assert!(first.map.iter().all(|byte_map| byte_map.encoded_byte.is_some()));
+        assert_eq!(super::ByteEncodeMap::new().encoded_byte, None);
+
+        let changed_points: std::collections::HashSet<_> =
+            first.map.iter().flat_map(|byte_map| byte_map.affected_points.clone()).collect();
+        assert_eq!(changed_points.len(), 24);
+        assert_ne!(first.map, encoder.encode_bytes(b"seagull").unwrap().map);
+    }
+
+    #[test]
+    fn encode_at_center() {
+        let encoded = ImageEncoder::from_image(image::DynamicImage::new_rgb8(100, 100))
+            .with_position(ImagePosition::Center)
+            .encode_bytes([0xffu8])
+            .expect("Encoding failed");
+
+        let changed = encoded.diff().changed_pixels();
+        assert_eq!((changed[0].0, changed[0].1), (50, 50));
+        assert_eq!(ImagePosition::Center.pixel_offset((5, 3)), 7);
+    }
+
+    #[test]
+    fn encode_at_position() {
+        let encoded = ImageEncoder::from_image(image::DynamicImage::new_rgb8(100, 100))
+            .with_position(ImagePosition::At(10, 5))
+            .encode_bytes([0xffu8])
+            .expect("Encoding failed");
+
+        let changed = encoded.diff().changed_pixels();
+        assert_eq!((changed[0].0, changed[0].1), (10, 5));
+        assert_eq!(read_blue_lsb_bytes(&encoded.altered_image, 510, 1), [0xff]);
+    }
+
+    #[test]
+    fn spread_payload() {
+        let encoder = ImageEncoder::from_image(image::DynamicImage::new_rgb8(100, 100)).with_spread(true);
+        let first_points = |encoded: &super::EncodedImage| -> Vec<(u32, u32)> {
+            encoded
+                .map
+                .iter()
+                .map(|byte_map| (byte_map.affected_points[0].0, byte_map.affected_points[0].1))
+                .collect()
+        };
+
+        // 16 pixels hold the payload, one every 10000 / 16 pixels
+        let encoded = encoder.encode_bytes([0xffu8, 0xff]).expect("Encoding failed");
+        assert_eq!(first_points(&encoded), [(0, 0), (0, 50)]);
+        assert_eq!(encoded.diff().changed_pixel_count(), 16);
+
+        let encoded = encoder
+            .with_spread_stride(5)
+            .encode_bytes([0xffu8, 0xff])
+            .expect("Encoding failed");
+        assert_eq!(first_points(&encoded), [(0, 0), (40, 0)]);
+    }
+
+    #[test]
+    fn msb_first_bit_order() {
+        let encoded = ImageEncoder::default()
+            .set_bit_order(BitOrder::MsbFirst)
+            .encode_bytes(b"x")
+            .expect("Encoding failed");
+
+        assert_eq!(read_blue_lsb_bytes(&encoded.altered_image, 0, 1), [b'x'.reverse_bits()]);
+    }
+
+    #[test]
+    fn in_memory_export() {
+        let encoded = ImageEncoder::default().encode_bytes(b"seagul").expect("Encoding failed");
+
+        let png = encoded.to_png_bytes().expect("PNG export failed");
+        assert_eq!(&png[1..4], b"PNG");
+        assert!(image::load_from_memory(&png).unwrap() == encoded.altered_image);
+
+        let bmp = encoded.to_bmp_bytes().expect("BMP export failed");
+        assert_eq!(&bmp[..2], b"BM");
+        assert!(image::load_from_memory(&bmp).unwrap().to_rgb8() == encoded.altered_image.to_rgb8());
+
+        let writer = super::ImageWriter::new(&encoded).with_compression(CompressionType::Best);
+        let png = writer.to_vec(ImageFormat::Png).expect("PNG export failed");
+        assert!(image::load_from_memory(&png).unwrap() == encoded.altered_image);
+    }
+
+    #[test]
+    fn clone_encoded_image() {
+        let encoded = ImageEncoder::from_image(image::DynamicImage::new_rgb8(16, 16))
+            .encode_bytes(b"Midway")
+            .expect("Encoding failed");
+        let copy = encoded.clone();
+
+        assert_eq!(
+            copy.to_png_bytes().expect("Could not write copy"),
+            encoded.to_png_bytes().expect("Could not write original")
+        );
+        assert_eq!(copy.color_channel_writes(), encoded.color_channel_writes());
+    }
+
+    #[test]
+    fn all_bits_of_single_pixel() {
+        let image = image::DynamicImage::new_rgb8(1, 1);
+        let encoded = ImageEncoder::from_image(image.clone())
+            .with_lsb(8)
+            .with_channel(RgbChannel::All)
+            .with_padding("pad")
+            .encode_bytes(b"sea")
+            .expect("Encoding failed");
+        assert_eq!(encoded.altered_image().to_rgb8().get_pixel(0, 0).0, *b"sea");
+
+        let decoded = ImageDecoder::from(encoded.altered_image().clone())
+            .with_lsb(8)
+            .with_channel(RgbChannel::All)
+            .decode()
+            .expect("Decoding failed");
+        assert_eq!(decoded.as_raw(), "sea");
+
+        assert!(matches!(
+            ImageEncoder::from_image(image).with_lsb(8).encode_bytes(b"se"),
+            Err(SteganographyError::InsufficientCapacity { .. })
+        ));
+    }
+
+    #[test]
+    fn put_bits_bounds() {
+        use super::put_bits;
+        use bitvec::prelude::*;
+
+        let bits = bitvec![Lsb0, u8; 1; 3];
+        let mut channel = 0u8;
+        put_bits(&bits, channel.view_bits_mut::<Lsb0>(), 5).expect("Bits should fit");
+        assert_eq!(channel, 0b1110_0000);
+
+        assert!(matches!(
+            put_bits(&bits, channel.view_bits_mut::<Lsb0>(), 6),
+            Err(SteganographyError::InvalidConfiguration(_))
+        ));
+        assert_eq!(channel, 0b1110_0000);
+    }
+
+    #[test]
+    fn deterministic_output() {
+        let source = image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| {
+            image::Rgb([(x * 4) as u8, (y * 4) as u8, (x * y) as u8])
+        }));
+        let encoders = vec![
+            ImageEncoder::from_image(source.clone()),
+            ImageEncoder::from_image(source.clone())
+                .with_lsb(3)
+                .with_channel(RgbChannel::All)
+                .with_spread(true)
+                .with_ecc(),
+            ImageEncoder::from_image(source.clone())
+                .with_padding("pad")
+                .with_password("seagul"),
+        ];
+
+        for encoder in encoders {
+            let first = encoder.encode_bytes(b"Midway upon the journey").unwrap();
+            let second = encoder.encode_bytes(b"Midway upon the journey").unwrap();
+            assert_eq!(first.altered_image.as_bytes(), second.altered_image.as_bytes());
+            assert_eq!(first.to_png_bytes().unwrap(), second.to_png_bytes().unwrap());
+        }
+    }
+
+    #[test]
+    fn rgb16_mode() {
+        let source = image::DynamicImage::ImageRgb16(image::ImageBuffer::from_fn(16, 16, |x, y| {
+            image::Rgb([(x * 4000) as u16, (y * 4000) as u16, 0x8080])
+        }));
+        let encoded = ImageEncoder::from_image(source.clone())
+            .with_lsb(8)
+            .encode_bytes(b"Midway upon the journey")
+            .expect("Encoding failed");
+        let altered = encoded.altered_image.as_rgb16().unwrap();
+        for (before, after) in source.as_rgb16().unwrap().iter().zip(altered.iter()) {
+            assert_eq!(before >> 8, after >> 8);
+        }
+
+        let png = encoded.to_png_bytes().expect("PNG export failed");
+        let reloaded = image::load_from_memory(&png).unwrap();
+        assert_eq!(reloaded.color(), image::ColorType::Rgb16);
+        assert_eq!(
+            ImageDecoder::from_image(reloaded).with_lsb(8).decode_exact(23).unwrap().embedded_data(),
+            b"Midway upon the journey"
+        );
+
+        let widened = ImageEncoder::default()
+            .with_rgb16_mode(true)
+            .encode_bytes(b"seagul")
+            .expect("Encoding failed");
+        assert!(matches!(widened.altered_image, image::DynamicImage::ImageRgb16(_)));
+        assert_eq!(
+            ImageDecoder::from_image(widened.altered_image).decode_exact(6).unwrap().embedded_data(),
+            b"seagul"
+        );
+
+        let luma = image::DynamicImage::new_luma8(16, 16);
+        assert!(matches!(
+            ImageEncoder::from_image(luma).with_rgb16_mode(true).validate(),
+            Err(SteganographyError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn grayscale_source() {
+        let source = image::GrayImage::from_pixel(16, 16, image::Luma([128]));
+        let encoded = ImageEncoder::from_image(image::DynamicImage::ImageLuma8(source))
+            .encode_bytes(b"seagul")
+            .expect("Encoding failed");
+        assert!(matches!(encoded.altered_image, image::DynamicImage::ImageLuma8(_)));
+
+        let png = encoded.to_png_bytes().expect("PNG export failed");
+        let reloaded = image::load_from_memory(&png).unwrap();
+        assert_eq!(reloaded.color(), image::ColorType::L8);
+        assert_eq!(
+            ImageDecoder::from_image(reloaded).decode_exact(6).unwrap().embedded_data(),
+            b"seagul"
+        );
+
+        let described = ImageEncoder::from_image(encoded.altered_image)
+            .encode_self_describing(b"seagul")
+            .expect("Encoding failed");
+        assert_eq!(
+            ImageDecoder::from_image(described.altered_image)
+                .decode_self_describing()
+                .unwrap()
+                .embedded_data(),
+            b"seagul"
+        );
+
+        assert!(matches!(
+            ImageEncoder::default().with_channel(RgbChannel::Luma).validate(),
+            Err(SteganographyError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn encode_reader() {
+        let data = b"Midway upon the journey of our life";
+        let mut encoder = ImageEncoder::default();
+        encoder.set_padding("pad").set_use_n_lsb(4).set_chunk_size(3);
+
+        let from_bytes = encoder.encode_bytes(data).expect("Encoding failed");
+        let from_reader = encoder
+            .encode_reader(&mut &data[..])
+            .expect("Encoding failed");
+        assert!(from_reader.altered_image == from_bytes.altered_image);
+
+        encoder.set_use_n_lsb(1);
+        assert!(matches!(
+            encoder.encode_reader(&mut &data[..]),
+            Err(SteganographyError::InsufficientCapacity { required: 33, available: 32 })
+        ));
+        assert!(matches!(
+            encoder.set_spread(true).encode_reader(&mut &data[..]),
+            Err(SteganographyError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn encode_iter() {
+        let data = b"Midway upon the journey of our life";
+        let mut encoder = ImageEncoder::default();
+        encoder.set_use_n_lsb(4).set_chunk_size(4);
+
+        let from_bytes = encoder.encode_bytes(data).expect("Encoding failed");
+        let from_iter = encoder.encode_iter(data.iter().copied()).expect("Encoding failed");
+        assert!(from_iter.altered_image == from_bytes.altered_image);
+        let from_exact = encoder.encode_iter_exact(data.iter().copied()).expect("Encoding failed");
+        assert!(from_exact.altered_image == from_bytes.altered_image);
+
+        // An endless generator is read until the image is full
+        let mut pulled = 0;
+        let generator = std::iter::repeat(b'x').inspect(|_| pulled += 1);
+        assert!(matches!(
+            encoder.encode_iter(generator),
+            Err(SteganographyError::InsufficientCapacity { required: 132, available: 128 })
+        ));
+        assert_eq!(pulled, 132);
+
+        let mut pulled = 0;
+        let too_long = [0u8; 129].iter().copied().inspect(|_| pulled += 1);
+        assert!(matches!(
+            encoder.encode_iter_exact(too_long),
+            Err(SteganographyError::InsufficientCapacity { required: 129, available: 128 })
+        ));
+        assert_eq!(pulled, 0);
+    }
+
+    #[test]
+    fn trailing_padding() {
+        let encoded = ImageEncoder::default()
+            .set_padding("ab")
+            .encode_bytes(b"x")
+            .expect("Encoding failed");
+
+        assert_eq!(read_blue_lsb_bytes(&encoded.altered_image, 0, 1), b"x");
+        assert_eq!(
+            read_blue_lsb_bytes(&encoded.altered_image, 8, 31),
+            b"abababababababababababababababa"
+        );
+    }
+
+    #[test]
+    fn encoding_statistics() {
+        let encoded = ImageEncoder::default()
+            .with_channel_order(vec![RgbChannel::Red, RgbChannel::Green, RgbChannel::Blue])
+            .encode_bytes(b"ab")
+            .expect("Encoding failed");
+
+        assert_eq!(encoded.bytes_encoded(), 2);
+        assert_eq!(encoded.color_channel_writes(), 16);
+        // The third pixel holds bits of both bytes
+        assert_eq!(encoded.pixels_modified(), 6);
+    }
+
+    #[test]
+    fn fractional_lsb() {
+        for lsb in [3, 5, 6, 7] {
+            let encoded = ImageEncoder::default()
+                .with_lsb(lsb)
+                .with_padding("ab")
+                .encode_bytes(b"xyz")
+                .expect("Encoding failed");
+
+            let decoder = ImageDecoder::from_image(encoded.altered_image.clone())
+                .with_lsb(lsb)
+                .with_padding("ab");
+            assert_eq!(decoder.decode_exact(3).unwrap().embedded_data(), b"xyz");
+
+            // The padding starts right after the last bit of data, in the same channel
+            let decoded = decoder.decode_exact(8).unwrap();
+            assert_eq!(&decoded.embedded_data()[3..], b"ababa");
+        }
+    }
+
+    #[test]
+    fn signal_to_noise() {
+        let encoder = ImageEncoder::default();
+
+        let unchanged = encoder.encode_bytes(b"").expect("Encoding failed");
+        assert_eq!(unchanged.mse(), 0.0);
+        assert_eq!(unchanged.psnr(), f64::INFINITY);
+
+        // 'x' is 0b0111_1000, which sets the LSB of 4 out of 256 * 3 samples
+        let encoded = encoder.encode_bytes(b"x").expect("Encoding failed");
+        assert!((encoded.mse() - 4.0 / 768.0).abs() < f64::EPSILON);
+        assert!((encoded.psnr() - 10.0 * (255.0_f64.powi(2) * 192.0).log10()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn diff_image_and_heatmap() {
+        use image::GenericImageView;
+
+        let encoded = ImageEncoder::from_image(image::DynamicImage::new_rgb8(16, 16))
+            .with_lsb(2)
+            .encode_bytes([0b1110_0100])
+            .expect("Encoding failed");
+
+        let diff = encoded.generate_diff_image().to_rgb8();
+        let heatmap = encoded.generate_heatmap().to_rgb8();
+        assert_eq!(diff.dimensions(), encoded.original_image.dimensions());
+        assert_eq!(heatmap.dimensions(), encoded.original_image.dimensions());
+
+        // Blue channel values are 0, 1, 2, 3 in the first pixels
+        assert_eq!(diff.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(diff.get_pixel(1, 0).0, [0, 0, 128]);
+        assert_eq!(diff.get_pixel(3, 0).0, [0, 0, 255]);
+        assert_eq!(diff.get_pixel(4, 0).0, [0, 0, 0]);
+
+        assert_eq!(heatmap.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(heatmap.get_pixel(1, 0).0, [127, 0, 0]);
+        assert_eq!(heatmap.get_pixel(3, 0).0, [255, 0, 0]);
+        assert_eq!(heatmap.get_pixel(4, 0).0, [0, 0, 0]);
+
+        let diff = encoded.diff();
+        assert_eq!(diff.changed_pixel_count(), 3);
+        assert_eq!(diff.total_bits_changed(), 4);
+    }
+
+    #[test]
+    fn roundtrip_verification() {
+        let mut encoder = ImageEncoder::default();
+        encoder
+            .set_use_n_lsb(2)
+            .set_channel_order(vec![RgbChannel::Red, RgbChannel::Green]);
+        let encoded = encoder.encode_bytes(b"seagul").expect("Encoding failed");
+
+        assert!(encoded.verify_roundtrip(b"seagul", &encoder).unwrap());
+        assert!(!encoded.verify_roundtrip(b"seagull", &encoder).unwrap());
+
+        encoder.set_use_n_lsb(1);
+        assert!(!encoded.verify_roundtrip(b"seagul", &encoder).unwrap());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_matches_sequential() {
+        use bitvec::prelude::*;
+
+        use crate::pixel_selectors::{SpiralPixelSelector, ZigZagPixelSelector};
+
+        let source = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap().source_image;
+        let configurations = vec![
+            ImageEncoder::from_image(source.clone()),
+            ImageEncoder::from_image(source.clone())
+                .with_lsb(2)
+                .with_step_by_n_pixels(3)
+                .with_position(ImagePosition::TopRight)
+                .with_padding("pad"),
+            ImageEncoder::from_image(source.clone())
+                .with_lsb(4)
+                .with_channel_order(vec![RgbChannel::Red, RgbChannel::Green, RgbChannel::Blue]),
+            ImageEncoder::from_image(source.clone())
+                .with_step_by_n_pixels(2)
+                .with_padding("pad")
+                .with_password("seagul"),
+            ImageEncoder::from_image(source.clone()).with_lsb(2).with_spread(true),
+            ImageEncoder::from_image(source.clone()).with_spread(true).with_password("seagul"),
+            ImageEncoder::from_image(source.clone())
+                .with_spread(true)
+                .with_spread_stride(7)
+                .with_step_by_n_pixels(2)
+                .with_padding("pad"),
+            ImageEncoder::from_image(source.clone())
+                .with_lsb(2)
+                .with_offset(100)
+                .with_padding("pad")
+                .with_pixel_selector(Box::new(SpiralPixelSelector)),
+            ImageEncoder::from_image(source.clone())
+                .with_step_by_n_pixels(3)
+                .with_pixel_selector(Box::new(ZigZagPixelSelector))
+                .with_password("seagul"),
+            ImageEncoder::from_image(source.clone())
+                .with_lsb(2)
+                .with_padding("pad")
+                .with_bit_order(crate::prelude::BitOrder::MsbFirst),
+            ImageEncoder::from_image(source.clone())
+                .with_lsb(3)
+                .with_channel_order(vec![RgbChannel::Red, RgbChannel::Blue])
+                .with_padding("pad"),
+            ImageEncoder::from_image(source.clone())
+                .with_lsb(5)
+                .with_step_by_n_pixels(2)
+                .with_pixel_selector(Box::new(SpiralPixelSelector)),
+            ImageEncoder::from_image(source.clone())
+                .with_channel(RgbChannel::All)
+                .with_step_by_n_pixels(2)
+                .with_padding("pad"),
+            ImageEncoder::from_image(source)
+                .with_lsb(2)
+                .with_region(100, 50, 200, 150)
+                .with_position(ImagePosition::Center)
+                .with_padding("pad"),
+        ];
+
+        for encoder in configurations {
+            let data = b"Midway upon the journey of our life";
+            let channel_order = crate::prelude::channel_indices(encoder.get_channel_order());
+            let real_offset = encoder.real_offset();
+            let step = encoder.skip_c
+                * crate::capacity::spread_stride(encoder.source_image.dimensions(), data.len(), &encoder);
+            let padding_bits: Option<Vec<bool>> = encoder.get_padding().map(|padding_str| {
+                padding_str.as_bytes().view_bits::<Lsb0>().iter().map(|bit| *bit).collect()
+            });
+
+            let mut sequential_img = encoder.source_image.to_rgb8();
+            let sequential_maps = encoder.encode_pixels_sequential(
+                &mut sequential_img,
+                real_offset,
+                step,
+                data,
+                &channel_order,
+                padding_bits.as_deref(),
+            )
+            .expect("Encoding failed");
+
+            let mut parallel_img = encoder.source_image.to_rgb8();
+            let parallel_maps = encoder.encode_pixels_parallel(
+                &mut parallel_img,
+                real_offset,
+                step,
+                data,
+                &channel_order,
+                padding_bits.as_deref(),
+            )
+            .expect("Encoding failed");
+
+            assert!(sequential_img == parallel_img);
+            assert_eq!(sequential_maps.len(), parallel_maps.len());
+            for (sequential, parallel) in sequential_maps.iter().zip(parallel_maps.iter()) {
+                assert_eq!(sequential.encoded_byte, parallel.encoded_byte);
+                assert_eq!(sequential.len(), parallel.len());
+            }
+        }
+    }
+
+    #[test]
+    fn writer_compression() {
+        let mut encoder = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap();
+        encoder.set_compression_type(CompressionType::Fast);
+        let encoded = encoder.encode_bytes(b"seagul").expect("Encoding failed");
+
+        let mut fast = vec![];
+        encoded.write(&mut fast, ImageFormat::Png).expect("Write failed");
+
+        let mut best = vec![];
+        super::ImageWriter::new(&encoded)
+            .with_compression(CompressionType::Best)
+            .with_filter(FilterType::Paeth)
+            .write(&mut best, ImageFormat::Png)
+            .expect("Write failed");
+
+        assert!(best.len() < fast.len());
+        assert!(image::load_from_memory(&best).unwrap() == encoded.altered_image);
+    }
+
+    #[test]
+    fn writer_jpeg_quality() {
+        let encoded = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+            .encode_bytes(b"seagul")
+            .expect("Encoding failed");
+
+        let mut low = vec![];
+        super::ImageWriter::new(&encoded)
+            .with_jpeg_quality(10)
+            .write(&mut low, ImageFormat::Jpeg)
+            .expect("Write failed");
+
+        let mut high = vec![];
+        super::ImageWriter::new(&encoded)
+            .with_jpeg_quality(255)
+            .write(&mut high, ImageFormat::Jpeg)
+            .expect("Write failed");
+
+        assert!(low.len() < high.len());
+        let decoded = image::load_from_memory_with_format(&high, image::ImageFormat::Jpeg).unwrap();
+        assert_eq!(decoded.dimensions(), encoded.altered_image.dimensions());
+    }
+
+    #[test]
+    fn simple_encoding() {
+        ensure_out_dir().unwrap();
+
+        let encode_result = super::ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+            .set_use_n_lsb(2)
+            .set_use_channel(RgbChannel::Blue)
+            .encode_data(
+                b"
+                Midway upon the journey of our life
+                I found myself within a forest dark,
+                For the straightforward pathway had been lost.
+                Ah me! how hard a thing it is to say
+                What was this forest savage, rough, and stern,
+                Which in the very thought renews the fear.
+                So bitter is it, death is little more;
+                But of the good to treat, which there I found,
+                Speak will I of the other things I saw there.
+                I cannot well repeat how there I entered,
+                So full was I of slumber at the moment
+                In which I had abandoned the true way.",
+            );
+
+        assert!(encode_result.is_ok(), "Encoding failed");
+
+        let encoded = encode_result.unwrap();
+        assert!(matches!(
+            encoded.save("tests/out/red_panda_steg.jpeg", ImageFormat::Jpeg),
+            Err(SteganographyError::LossyFormat(ImageFormat::Jpeg))
+        ));
 
-        encode_result
-            .unwrap()
-            .save("tests/out/red_panda_steg.jpeg", ImageFormat::Jpeg)
+        encoded
+            .force_save("tests/out/red_panda_steg.jpeg", ImageFormat::Jpeg)
             .expect("Could not create output file");
     }
 }
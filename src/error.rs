@@ -0,0 +1,147 @@
+use std::fmt::Display;
+
+#[cfg(feature = "crypto")]
+use crate::crypto::CryptoError;
+use crate::{ecc::EccError, prelude::ImageFormat};
+
+/// Enumerates the reasons an encoding or decoding operation can fail
+#[derive(Debug)]
+pub enum SteganographyError {
+    /// The source image does not have enough room to fit the requested data.
+    /// Both values are expressed in bytes of payload
+    InsufficientCapacity { required: usize, available: usize },
+
+    /// The encoder or decoder has been configured with invalid or incompatible options
+    InvalidConfiguration(String),
+
+    /// The image could not be loaded or encoded by the `image` crate
+    ImageLoadError(image::ImageError),
+
+    /// An error occurred while reading from or writing to an I/O resource
+    IoError(std::io::Error),
+
+    /// The encoded image was about to be saved with a lossy format, which would
+    /// destroy the encoded data
+    LossyFormat(ImageFormat),
+
+    /// The decoded header has a version this library does not understand. This usually
+    /// means the image holds no header, or the decoder configuration is wrong
+    UnsupportedHeaderVersion(u8),
+
+    /// The decoded payload does not match the checksum stored in its header
+    ChecksumMismatch { expected: u32, got: u32 },
+
+    /// A payload transform could not be applied or reversed
+    TransformError(String),
+
+    /// The decoded data holds more errors than the error correction code can fix
+    EccError(EccError),
+
+    /// The decoded payload could not be decrypted with the configured key
+    #[cfg(feature = "crypto")]
+    CryptoError(CryptoError),
+}
+
+impl Display for SteganographyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SteganographyError::InsufficientCapacity {
+                required,
+                available,
+            } => write!(
+                f,
+                "Not enough space in image to fit specified data ({} bytes required, {} available)",
+                required, available
+            ),
+            SteganographyError::InvalidConfiguration(reason) => {
+                write!(f, "Invalid configuration: {}", reason)
+            }
+            SteganographyError::ImageLoadError(e) => write!(f, "Image error: {}", e),
+            SteganographyError::IoError(e) => write!(f, "I/O error: {}", e),
+            SteganographyError::LossyFormat(format) => write!(
+                f,
+                "{} is a lossy format, the encoded data would be unrecoverable",
+                format
+            ),
+            SteganographyError::UnsupportedHeaderVersion(version) => {
+                write!(f, "Unsupported header version {}", version)
+            }
+            SteganographyError::ChecksumMismatch { expected, got } => write!(
+                f,
+                "Payload checksum mismatch (expected {:#010x}, found {:#010x})",
+                expected, got
+            ),
+            SteganographyError::TransformError(reason) => {
+                write!(f, "Payload transform failed: {}", reason)
+            }
+            SteganographyError::EccError(e) => write!(f, "Error correction failed: {}", e),
+            #[cfg(feature = "crypto")]
+            SteganographyError::CryptoError(e) => write!(f, "Decryption failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SteganographyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SteganographyError::ImageLoadError(e) => Some(e),
+            SteganographyError::IoError(e) => Some(e),
+            SteganographyError::EccError(e) => Some(e),
+            #[cfg(feature = "crypto")]
+            SteganographyError::CryptoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for SteganographyError {
+    fn from(e: image::ImageError) -> Self {
+        SteganographyError::ImageLoadError(e)
+    }
+}
+
+impl From<gif::DecodingError> for SteganographyError {
+    fn from(e: gif::DecodingError) -> Self {
+        SteganographyError::ImageLoadError(image::ImageError::Decoding(image::error::DecodingError::new(
+            image::error::ImageFormatHint::Exact(image::ImageFormat::Gif),
+            e,
+        )))
+    }
+}
+
+impl From<tiff::TiffError> for SteganographyError {
+    fn from(e: tiff::TiffError) -> Self {
+        SteganographyError::ImageLoadError(image::ImageError::Decoding(image::error::DecodingError::new(
+            image::error::ImageFormatHint::Exact(image::ImageFormat::Tiff),
+            e,
+        )))
+    }
+}
+
+impl From<gif::EncodingError> for SteganographyError {
+    fn from(e: gif::EncodingError) -> Self {
+        SteganographyError::ImageLoadError(image::ImageError::Encoding(image::error::EncodingError::new(
+            image::error::ImageFormatHint::Exact(image::ImageFormat::Gif),
+            e,
+        )))
+    }
+}
+
+impl From<EccError> for SteganographyError {
+    fn from(e: EccError) -> Self {
+        SteganographyError::EccError(e)
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl From<CryptoError> for SteganographyError {
+    fn from(e: CryptoError) -> Self {
+        SteganographyError::CryptoError(e)
+    }
+}
+
+impl From<std::io::Error> for SteganographyError {
+    fn from(e: std::io::Error) -> Self {
+        SteganographyError::IoError(e)
+    }
+}
@@ -0,0 +1,311 @@
+use std::{borrow::Cow, io::Read};
+
+use ::gif::{ColorOutput, DecodeOptions, Frame, Repeat};
+use bitvec::prelude::*;
+
+pub use crate::header::LENGTH_SIZE;
+use crate::{
+    error::SteganographyError,
+    header::{complete_bytes, frame_with_length, read_length_framed, BYTE_STEP},
+};
+
+/// Two palette entries whose colors differ by at most this distance, summed over the
+/// red, green and blue components, can be swapped without visibly changing the image
+pub const MAX_COLOR_DISTANCE: u32 = 4;
+
+/// For each palette index, the pair of interchangeable indices it belongs to, lowest first
+type PalettePairs = [Option<(u8, u8)>; 256];
+
+/// The frames of a GIF image, along with the global palette they may refer to
+struct GifImage {
+    width: u16,
+    height: u16,
+    global_palette: Option<Vec<u8>>,
+    frames: Vec<Frame<'static>>,
+}
+
+impl GifImage {
+    fn read<R>(reader: R) -> Result<Self, SteganographyError>
+    where
+        R: Read,
+    {
+        let mut options = DecodeOptions::new();
+        options.set_color_output(ColorOutput::Indexed);
+        let mut decoder = options.read_info(reader)?;
+
+        let width = decoder.width();
+        let height = decoder.height();
+        let global_palette = decoder.global_palette().map(|palette| palette.to_vec());
+        let mut frames = vec![];
+        while let Some(frame) = decoder.read_next_frame()? {
+            frames.push(frame.clone());
+        }
+
+        Ok(Self {
+            width,
+            height,
+            global_palette,
+            frames,
+        })
+    }
+
+    /// The interchangeable palette indices of every frame, in order
+    fn frame_pairs(&self) -> Vec<PalettePairs> {
+        self.frames
+            .iter()
+            .map(|frame| {
+                let palette = frame
+                    .palette
+                    .as_deref()
+                    .or(self.global_palette.as_deref())
+                    .unwrap_or(&[]);
+                palette_pairs(palette, frame.transparent)
+            })
+            .collect()
+    }
+
+    /// The number of bits that can be hidden in the palette indices of all frames
+    fn bit_capacity(&self) -> usize {
+        self.frames
+            .iter()
+            .zip(self.frame_pairs())
+            .map(|(frame, pairs)| {
+                frame
+                    .buffer
+                    .iter()
+                    .filter(|index| pairs[**index as usize].is_some())
+                    .count()
+            })
+            .sum()
+    }
+}
+
+/// Hides data in a GIF image by choosing, for each pixel whose color has a nearly identical
+/// twin in the palette, which one of the two palette indices it uses. The colors shown never
+/// change by more than `MAX_COLOR_DISTANCE`.
+///
+/// Every frame is used, in order, and the payload is preceded by its length so that
+/// `GifDecoder` needs no configuration. Images with few similar palette entries have little
+/// or no capacity
+pub struct GifEncoder {
+    image: GifImage,
+}
+
+impl GifEncoder {
+    /// Reads the GIF image to encode data into
+    pub fn from_reader<R>(reader: R) -> Result<Self, SteganographyError>
+    where
+        R: Read,
+    {
+        Ok(Self {
+            image: GifImage::read(reader)?,
+        })
+    }
+
+    /// Reads the GIF image to encode data into from its encoded bytes
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        Self::from_reader(data)
+    }
+
+    /// The number of payload bytes that fit into the image
+    pub fn estimate_capacity(&self) -> usize {
+        (self.image.bit_capacity() / BYTE_STEP).saturating_sub(LENGTH_SIZE)
+    }
+
+    /// Encodes arbitrary bytes into the image, returning the encoded GIF file. Frames are
+    /// written without interlacing, and animations loop forever
+    pub fn encode_bytes(&self, data: impl AsRef<[u8]>) -> Result<Vec<u8>, SteganographyError> {
+        let data = data.as_ref();
+        let available = self.estimate_capacity();
+        if data.len() > available {
+            return Err(SteganographyError::InsufficientCapacity {
+                required: data.len(),
+                available,
+            });
+        }
+        let payload = frame_with_length(data)?;
+        let mut bits = payload.view_bits::<Lsb0>().iter().map(|bit| *bit);
+
+        let global_palette = self.image.global_palette.as_deref().unwrap_or(&[]);
+        let mut encoder = ::gif::Encoder::new(Vec::new(), self.image.width, self.image.height, global_palette)?;
+        if self.image.frames.len() > 1 {
+            encoder.set_repeat(Repeat::Infinite)?;
+        }
+
+        for (frame, pairs) in self.image.frames.iter().zip(self.image.frame_pairs()) {
+            let mut buffer = frame.buffer.to_vec();
+            for index in buffer.iter_mut() {
+                if let Some((low, high)) = pairs[*index as usize] {
+                    match bits.next() {
+                        Some(bit) => *index = if bit { high } else { low },
+                        None => break,
+                    }
+                }
+            }
+
+            encoder.write_frame(&Frame {
+                interlaced: false,
+                buffer: Cow::Owned(buffer),
+                ..frame.clone()
+            })?;
+        }
+
+        Ok(encoder.into_inner()?)
+    }
+}
+
+/// Recovers data hidden in a GIF image by `GifEncoder`
+pub struct GifDecoder {
+    image: GifImage,
+}
+
+impl GifDecoder {
+    /// Reads the GIF image to decode data from
+    pub fn from_reader<R>(reader: R) -> Result<Self, SteganographyError>
+    where
+        R: Read,
+    {
+        Ok(Self {
+            image: GifImage::read(reader)?,
+        })
+    }
+
+    /// Reads the GIF image to decode data from its encoded bytes
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        Self::from_reader(data)
+    }
+
+    /// Decodes the payload hidden in the image. Fails if the image holds fewer bytes than
+    /// the length read from it, which usually means it holds no payload
+    pub fn decode(&self) -> Result<Vec<u8>, SteganographyError> {
+        let mut bits: BitVec<Lsb0, u8> = BitVec::with_capacity(self.image.bit_capacity());
+        for (frame, pairs) in self.image.frames.iter().zip(self.image.frame_pairs()) {
+            for index in frame.buffer.iter() {
+                if let Some((_, high)) = pairs[*index as usize] {
+                    bits.push(*index == high);
+                }
+            }
+        }
+        Ok(read_length_framed(&complete_bytes(bits))?.to_vec())
+    }
+}
+
+/// Pairs up the entries of `palette` whose colors are within `MAX_COLOR_DISTANCE`, closest
+/// colors first. Each entry belongs to at most one pair, and the transparent one to none
+fn palette_pairs(palette: &[u8], transparent: Option<u8>) -> PalettePairs {
+    let colors: Vec<&[u8]> = palette.chunks_exact(3).take(256).collect();
+    let mut candidates = vec![];
+    for low in 0..colors.len() {
+        for high in low + 1..colors.len() {
+            let distance = color_distance(colors[low], colors[high]);
+            if distance <= MAX_COLOR_DISTANCE {
+                candidates.push((distance, low as u8, high as u8));
+            }
+        }
+    }
+    candidates.sort_unstable();
+
+    let mut pairs = [None; 256];
+    for (_, low, high) in candidates {
+        let taken = |index: u8| pairs[index as usize].is_some() || transparent == Some(index);
+        if !taken(low) && !taken(high) {
+            pairs[low as usize] = Some((low, high));
+            pairs[high as usize] = Some((low, high));
+        }
+    }
+
+    pairs
+}
+
+fn color_distance(a: &[u8], b: &[u8]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (*a as i32 - *b as i32).unsigned_abs())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use ::gif::{Encoder, Frame, Repeat};
+
+    use super::{color_distance, palette_pairs, GifDecoder, GifEncoder, GifImage, MAX_COLOR_DISTANCE};
+    use crate::error::SteganographyError;
+
+    const PALETTE: [u8; 18] = [0, 0, 0, 1, 1, 1, 200, 10, 10, 202, 8, 10, 90, 90, 90, 0, 0, 255];
+
+    /// A two frames animation using every palette entry
+    fn animation() -> Vec<u8> {
+        let mut encoder = Encoder::new(Vec::new(), 16, 16, &PALETTE).unwrap();
+        encoder.set_repeat(Repeat::Infinite).unwrap();
+        for offset in 0..2 {
+            let buffer: Vec<u8> = (0..256).map(|i| ((i / 3 + offset) % 6) as u8).collect();
+            let frame = Frame {
+                width: 16,
+                height: 16,
+                buffer: Cow::Owned(buffer),
+                ..Frame::default()
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+        encoder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn pairs_similar_colors() {
+        let pairs = palette_pairs(&PALETTE, None);
+        assert_eq!(pairs[0], Some((0, 1)));
+        assert_eq!(pairs[1], Some((0, 1)));
+        assert_eq!(pairs[3], Some((2, 3)));
+        assert_eq!(pairs[4], None);
+        assert_eq!(pairs[5], None);
+
+        let pairs = palette_pairs(&PALETTE, Some(1));
+        assert_eq!(pairs[0], None);
+        assert_eq!(pairs[2], Some((2, 3)));
+    }
+
+    #[test]
+    fn encode_decode() {
+        let source = animation();
+        let encoder = GifEncoder::from_bytes(&source).unwrap();
+        assert!(encoder.estimate_capacity() >= 30);
+
+        let encoded = encoder.encode_bytes(b"Midway upon the journey").unwrap();
+        let decoded = GifDecoder::from_bytes(&encoded).unwrap().decode().unwrap();
+        assert_eq!(decoded, b"Midway upon the journey");
+
+        // Every pixel keeps a nearly identical color
+        let original = GifImage::read(&source[..]).unwrap();
+        let altered = GifImage::read(&encoded[..]).unwrap();
+        assert_eq!(altered.frames.len(), 2);
+        // The palette read back is padded to a power of two with black entries
+        let palette = altered.global_palette.as_deref().unwrap();
+        assert_eq!(&palette[..PALETTE.len()], PALETTE);
+        let color = |index: u8| &palette[index as usize * 3..index as usize * 3 + 3];
+        let mut changed = 0;
+        for (original, altered) in original.frames.iter().zip(&altered.frames) {
+            for (a, b) in original.buffer.iter().zip(altered.buffer.iter()) {
+                assert!(color_distance(color(*a), color(*b)) <= MAX_COLOR_DISTANCE);
+                changed += (a != b) as usize;
+            }
+        }
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn insufficient_capacity() {
+        let encoder = GifEncoder::from_bytes(&animation()).unwrap();
+        let data = vec![0u8; encoder.estimate_capacity() + 1];
+        assert!(matches!(
+            encoder.encode_bytes(data),
+            Err(SteganographyError::InsufficientCapacity { .. })
+        ));
+
+        assert!(matches!(
+            GifDecoder::from_bytes(b"not a gif"),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+    }
+}
@@ -0,0 +1,507 @@
+use std::{
+    borrow::Cow,
+    io::Read,
+    mem,
+    os::raw::{c_int, c_ulong},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use bitvec::prelude::*;
+use image::{jpeg::JpegEncoder, ColorType, DynamicImage, ImageFormat, RgbImage};
+use mozjpeg_sys::{
+    boolean, jpeg_common_struct, jpeg_compress_struct, jpeg_copy_critical_parameters,
+    jpeg_create_compress, jpeg_create_decompress, jpeg_decompress_struct, jpeg_destroy_compress,
+    jpeg_destroy_decompress, jpeg_error_mgr, jpeg_finish_compress, jpeg_finish_decompress,
+    jpeg_mem_dest, jpeg_mem_src, jpeg_read_coefficients, jpeg_read_header, jpeg_std_error,
+    jpeg_write_coefficients, jvirt_barray_control, JBLOCK,
+};
+
+pub use crate::header::LENGTH_SIZE;
+use crate::{
+    ecc::{hamming_decode, hamming_encode},
+    error::SteganographyError,
+    header::{complete_bytes, frame_with_length, read_length_framed, BYTE_STEP},
+};
+
+/// The JPEG quality images that are not JPEG files already are compressed with, unless
+/// configured otherwise
+pub const DEFAULT_QUALITY: u8 = 80;
+
+/// Quantized coefficients whose magnitude is at most this value are left untouched, unless
+/// configured otherwise. Changing them would visibly alter flat areas of the image
+pub const DEFAULT_THRESHOLD: u16 = 1;
+
+/// Only the coefficients quantized with at least this step hold data. Finer steps do not
+/// survive the rounding of pixel values when the image is saved again. At qualities above
+/// 90 most steps are finer, leaving little to no capacity
+pub const MIN_QUANTIZATION_STEP: u16 = 8;
+
+/// The number of bytes the error correction code turns each byte of data into
+const ECC_RATIO: usize = 2;
+
+/// The number of coefficients of a DCT block
+const BLOCK_LEN: usize = 64;
+
+/// Hides data in the frequency domain of a JPEG image, in the least significant bit of its
+/// quantized luminance DCT coefficients. The coefficients are read from and written back to
+/// the JPEG file as they are, through libjpeg, so the rest of the image is left untouched.
+///
+/// Only the AC coefficients whose magnitude is above the threshold, and whose quantization
+/// step is at least `MIN_QUANTIZATION_STEP`, hold data. The payload and its length are
+/// protected by the Hamming code of `ecc`, halving the capacity, so that the occasional
+/// coefficient moved by saving the image again as a JPEG file with the same quality does not
+/// corrupt it.
+///
+/// Covers that are not JPEG files are first compressed with the configured quality, dropping
+/// any transparency
+pub struct DctStegoEncoder {
+    cover: Cover,
+    quality: u8,
+    threshold: u16,
+}
+
+/// The image `DctStegoEncoder` encodes data into
+enum Cover {
+    /// Pixels still to be compressed as a JPEG file
+    Pixels(RgbImage),
+
+    /// A JPEG file, whose coefficients are used as they are
+    Jpeg(Vec<u8>),
+}
+
+impl DctStegoEncoder {
+    /// Uses `image` as the source to encode data into. It is compressed as a JPEG file with
+    /// the configured quality before encoding
+    pub fn from_image(image: DynamicImage) -> Self {
+        Self {
+            cover: Cover::Pixels(image.to_rgb8()),
+            quality: DEFAULT_QUALITY,
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+
+    /// Reads the image to encode data into, in any format supported by the `image` crate
+    pub fn from_reader<R>(reader: &mut R) -> Result<Self, SteganographyError>
+    where
+        R: Read + ?Sized,
+    {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+        Self::from_bytes(&data)
+    }
+
+    /// Reads the image to encode data into from its encoded bytes. JPEG files keep their
+    /// own coefficients and quality, any other format is compressed with the configured
+    /// quality before encoding
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        if image::guess_format(data)? == ImageFormat::Jpeg {
+            // Fail early on files libjpeg cannot read
+            read_luma(data)?;
+            Ok(Self {
+                cover: Cover::Jpeg(data.to_vec()),
+                quality: DEFAULT_QUALITY,
+                threshold: DEFAULT_THRESHOLD,
+            })
+        } else {
+            Ok(Self::from_image(image::load_from_memory(data)?))
+        }
+    }
+
+    /// Sets the quality, from 1 to 100, covers that are not JPEG files are compressed with.
+    /// Lower qualities give coarser coefficients, making the payload more robust but the
+    /// image less faithful
+    pub fn with_quality(mut self, quality: u8) -> Self {
+        self.quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Sets the magnitude quantized coefficients must exceed to hold data. The decoder must
+    /// use the same threshold
+    pub fn with_threshold(mut self, threshold: u16) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// The number of payload bytes that fit into the image
+    pub fn estimate_capacity(&self) -> Result<usize, SteganographyError> {
+        let coefficients = read_luma(&self.jpeg()?)?;
+        let usable = coefficients.usable(self.threshold).count();
+
+        Ok((usable / BYTE_STEP / ECC_RATIO).saturating_sub(LENGTH_SIZE))
+    }
+
+    /// The cover as a JPEG file
+    fn jpeg(&self) -> Result<Cow<'_, [u8]>, SteganographyError> {
+        match &self.cover {
+            Cover::Jpeg(jpeg) => Ok(Cow::Borrowed(jpeg)),
+            Cover::Pixels(image) => {
+                let mut jpeg = vec![];
+                let (width, height) = image.dimensions();
+                JpegEncoder::new_with_quality(&mut jpeg, self.quality).encode(image, width, height, ColorType::Rgb8)?;
+                Ok(Cow::Owned(jpeg))
+            }
+        }
+    }
+
+    /// Encodes arbitrary bytes into the image, returning the encoded JPEG file
+    pub fn encode_bytes(&self, data: impl AsRef<[u8]>) -> Result<Vec<u8>, SteganographyError> {
+        let data = data.as_ref();
+        let jpeg = self.jpeg()?;
+        let mut coefficients = read_luma(&jpeg)?;
+
+        let available = (coefficients.usable(self.threshold).count() / BYTE_STEP / ECC_RATIO)
+            .saturating_sub(LENGTH_SIZE);
+        if data.len() > available {
+            return Err(SteganographyError::InsufficientCapacity {
+                required: data.len(),
+                available,
+            });
+        }
+        let payload = hamming_encode(&frame_with_length(data)?);
+
+        let usable: Vec<(usize, usize)> = coefficients.usable(self.threshold).collect();
+        for ((block, index), bit) in usable.into_iter().zip(payload.view_bits::<Lsb0>().iter()) {
+            let coefficient = &mut coefficients.blocks[block][index];
+            *coefficient = embed_bit(*coefficient, *bit, self.threshold);
+        }
+
+        write_luma(&jpeg, &coefficients)
+    }
+}
+
+/// Recovers data hidden in a JPEG image by `DctStegoEncoder`. The quantization table is read
+/// from the file, so the quality it was saved with does not need to be known
+pub struct DctStegoDecoder {
+    coefficients: LumaCoefficients,
+    threshold: u16,
+}
+
+impl DctStegoDecoder {
+    /// Reads the JPEG file to decode data from
+    pub fn from_reader<R>(reader: &mut R) -> Result<Self, SteganographyError>
+    where
+        R: Read + ?Sized,
+    {
+        let mut jpeg = vec![];
+        reader.read_to_end(&mut jpeg)?;
+        Self::from_bytes(&jpeg)
+    }
+
+    /// Reads the JPEG file to decode data from its bytes
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        Ok(Self {
+            coefficients: read_luma(data)?,
+            threshold: DEFAULT_THRESHOLD,
+        })
+    }
+
+    /// Sets the magnitude quantized coefficients must exceed to hold data, which must be the
+    /// one used by the encoder
+    pub fn with_threshold(mut self, threshold: u16) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Decodes the payload hidden in the image. Fails if the image holds fewer bytes than
+    /// the length read from it, which usually means it holds no payload
+    pub fn decode(&self) -> Result<Vec<u8>, SteganographyError> {
+        let bits: BitVec<Lsb0, u8> = self
+            .coefficients
+            .usable(self.threshold)
+            .map(|(block, index)| self.coefficients.blocks[block][index].unsigned_abs() & 1 == 1)
+            .collect();
+        let bytes = complete_bytes(bits);
+
+        // The bits following the payload are not encoded, so the length is decoded first
+        // and only the bytes of the framed payload are decoded afterwards
+        let framed_len = match bytes.get(..LENGTH_SIZE * ECC_RATIO) {
+            Some(encoded_length) => {
+                let mut length = [0u8; LENGTH_SIZE];
+                length.copy_from_slice(&hamming_decode(encoded_length)?);
+                LENGTH_SIZE + u32::from_le_bytes(length) as usize
+            }
+            None => LENGTH_SIZE,
+        };
+        let encoded_len = (framed_len * ECC_RATIO).min(bytes.len() - bytes.len() % ECC_RATIO);
+
+        Ok(read_length_framed(&hamming_decode(&bytes[..encoded_len])?)?.to_vec())
+    }
+}
+
+/// The quantized DCT coefficients of the first component of a JPEG file, usually the
+/// luminance, along with the table they were quantized with. Both are in natural order
+struct LumaCoefficients {
+    /// The blocks of the component, in row order
+    blocks: Vec<JBLOCK>,
+    table: [u16; BLOCK_LEN],
+}
+
+impl LumaCoefficients {
+    /// The block and coefficient indices of the coefficients holding data, in order
+    fn usable(&self, threshold: u16) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.blocks.iter().enumerate().flat_map(move |(block, coefficients)| {
+            (1..BLOCK_LEN)
+                .filter(move |index| holds_data(coefficients[*index], self.table[*index], threshold))
+                .map(move |index| (block, index))
+        })
+    }
+}
+
+/// Whether a coefficient quantized with `step` is large and coarse enough to hold a bit
+fn holds_data(coefficient: i16, step: u16, threshold: u16) -> bool {
+    step >= MIN_QUANTIZATION_STEP && coefficient.unsigned_abs() > threshold
+}
+
+/// Sets the least significant bit of the magnitude of `coefficient` to `bit`, keeping the
+/// magnitude above `threshold` so that the coefficient still holds data
+fn embed_bit(coefficient: i16, bit: bool, threshold: u16) -> i16 {
+    let mut magnitude = (coefficient.unsigned_abs() & !1) | bit as u16;
+    if magnitude <= threshold {
+        magnitude += 2;
+    }
+
+    coefficient.signum() * magnitude as i16
+}
+
+/// Reads the luminance coefficients of a JPEG file
+fn read_luma(jpeg: &[u8]) -> Result<LumaCoefficients, SteganographyError> {
+    let mut coefficients = None;
+    with_coefficients(jpeg, |source, arrays| unsafe {
+        let component = &*source.comp_info;
+        let table = component
+            .quant_table
+            .as_ref()
+            .ok_or_else(|| invalid_jpeg(String::from("missing quantization table")))?
+            .quantval;
+        let mut blocks = Vec::with_capacity((component.width_in_blocks * component.height_in_blocks) as usize);
+        for row in 0..component.height_in_blocks {
+            blocks.extend_from_slice(block_row(source, arrays, row, false));
+        }
+        coefficients = Some(LumaCoefficients { blocks, table });
+        Ok(())
+    })?;
+
+    coefficients.ok_or_else(|| invalid_jpeg(String::from("missing coefficients")))
+}
+
+/// Replaces the luminance coefficients of a JPEG file, returning the new file. Every other
+/// coefficient and the quantization tables are kept as they are
+fn write_luma(jpeg: &[u8], coefficients: &LumaCoefficients) -> Result<Vec<u8>, SteganographyError> {
+    let mut output = vec![];
+    with_coefficients(jpeg, |source, arrays| unsafe {
+        let width = (*source.comp_info).width_in_blocks as usize;
+        for (row, blocks) in coefficients.blocks.chunks(width).enumerate() {
+            block_row(source, arrays, row as u32, true).copy_from_slice(blocks);
+        }
+
+        let mut error_manager = error_manager();
+        let mut destination: jpeg_compress_struct = mem::zeroed();
+        destination.common.err = &mut *error_manager;
+        jpeg_create_compress(&mut destination);
+        let mut buffer: *mut u8 = ptr::null_mut();
+        let mut size: c_ulong = 0;
+        let result = unwind_to_error(|| {
+            jpeg_mem_dest(&mut destination, &mut buffer, &mut size);
+            jpeg_copy_critical_parameters(source, &mut destination);
+            jpeg_write_coefficients(&mut destination, arrays);
+            jpeg_finish_compress(&mut destination);
+        });
+        jpeg_destroy_compress(&mut destination);
+        if !buffer.is_null() {
+            output.extend_from_slice(std::slice::from_raw_parts(buffer, size as usize));
+            libc::free(buffer as *mut libc::c_void);
+        }
+
+        result
+    })?;
+
+    Ok(output)
+}
+
+/// Reads the coefficients of a JPEG file with libjpeg and passes them to `visit`, along with
+/// the decompressor holding them, which is destroyed afterwards
+fn with_coefficients<F>(jpeg: &[u8], visit: F) -> Result<(), SteganographyError>
+where
+    F: FnOnce(&mut jpeg_decompress_struct, *mut *mut jvirt_barray_control) -> Result<(), SteganographyError>,
+{
+    unsafe {
+        let mut error_manager = error_manager();
+        let mut source: jpeg_decompress_struct = mem::zeroed();
+        source.common.err = &mut *error_manager;
+        jpeg_create_decompress(&mut source);
+        let mut visit = Some(visit);
+        let result = unwind_to_error(|| {
+            jpeg_mem_src(&mut source, jpeg.as_ptr(), jpeg.len() as c_ulong);
+            jpeg_read_header(&mut source, true as boolean);
+            let arrays = jpeg_read_coefficients(&mut source);
+            let visited = visit.take().map(|visit| visit(&mut source, arrays));
+            jpeg_finish_decompress(&mut source);
+            visited
+        });
+        jpeg_destroy_decompress(&mut source);
+
+        result?.unwrap_or(Ok(()))
+    }
+}
+
+/// The blocks of a row of the first component
+///
+/// # Safety
+///
+/// `arrays` must be the coefficient arrays read by `source`
+unsafe fn block_row<'a>(
+    source: &mut jpeg_decompress_struct,
+    arrays: *mut *mut jvirt_barray_control,
+    row: u32,
+    writable: bool,
+) -> &'a mut [JBLOCK] {
+    let width = (*source.comp_info).width_in_blocks as usize;
+    let access = (*source.common.mem)
+        .access_virt_barray
+        .expect("libjpeg memory managers can access block arrays");
+    let rows = access(&mut source.common, *arrays, row, 1, writable as boolean);
+    std::slice::from_raw_parts_mut(*rows, width)
+}
+
+/// A libjpeg error manager that unwinds instead of exiting the process on fatal errors, and
+/// keeps warnings quiet
+fn error_manager() -> Box<jpeg_error_mgr> {
+    unsafe {
+        let mut error_manager = Box::new(mem::zeroed());
+        jpeg_std_error(&mut error_manager);
+        error_manager.error_exit = Some(unwind_error_exit);
+        error_manager.emit_message = Some(silence_message);
+        error_manager
+    }
+}
+
+extern "C-unwind" fn silence_message(_cinfo: &mut jpeg_common_struct, _level: c_int) {}
+
+extern "C-unwind" fn unwind_error_exit(cinfo: &mut jpeg_common_struct) {
+    let mut buffer = [0u8; 80];
+    unsafe {
+        if let Some(format_message) = (*cinfo.err).format_message {
+            // The bindings declare the buffer as shared, although libjpeg writes to it
+            let format_message = mem::transmute::<
+                unsafe extern "C-unwind" fn(&mut jpeg_common_struct, &[u8; 80]),
+                unsafe extern "C-unwind" fn(&mut jpeg_common_struct, &mut [u8; 80]),
+            >(format_message);
+            format_message(cinfo, &mut buffer);
+        }
+    }
+    let message = buffer.split(|byte| *byte == 0).next().unwrap_or_default();
+    // Resuming the unwind skips the panic hook, which would print the error
+    panic::resume_unwind(Box::new(String::from_utf8_lossy(message).into_owned()));
+}
+
+/// Runs `f`, turning a libjpeg fatal error raised by `unwind_error_exit` into an error
+fn unwind_to_error<T, F: FnOnce() -> T>(f: F) -> Result<T, SteganographyError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| match payload.downcast::<String>() {
+        Ok(message) => invalid_jpeg(*message),
+        Err(payload) => panic::resume_unwind(payload),
+    })
+}
+
+fn invalid_jpeg(reason: String) -> SteganographyError {
+    SteganographyError::ImageLoadError(image::ImageError::Decoding(image::error::DecodingError::new(
+        image::error::ImageFormatHint::Exact(ImageFormat::Jpeg),
+        reason,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{jpeg::JpegEncoder, DynamicImage};
+
+    use super::{embed_bit, read_luma, DctStegoDecoder, DctStegoEncoder};
+    use crate::error::SteganographyError;
+
+    fn cover() -> DynamicImage {
+        image::open("tests/images/red_panda.jpg").expect("Could not load cover image")
+    }
+
+    #[test]
+    fn encode_decode() {
+        let verses = b"Midway upon the journey of our life I found myself within a forest dark";
+        let encoder = DctStegoEncoder::from_image(cover()).with_quality(80);
+        assert!(encoder.estimate_capacity().unwrap() > verses.len());
+
+        let encoded = encoder.encode_bytes(verses).unwrap();
+        let decoded = DctStegoDecoder::from_bytes(&encoded).unwrap().decode().unwrap();
+        assert_eq!(decoded, verses);
+
+        // Saving the encoded image again with the same quality keeps the payload
+        let reencoded_image = image::load_from_memory(&encoded).unwrap();
+        let mut reencoded = vec![];
+        JpegEncoder::new_with_quality(&mut reencoded, 80)
+            .encode_image(&reencoded_image)
+            .unwrap();
+        assert_ne!(reencoded, encoded);
+        let decoded = DctStegoDecoder::from_bytes(&reencoded).unwrap().decode().unwrap();
+        assert_eq!(decoded, verses);
+    }
+
+    #[test]
+    fn keeps_jpeg_covers() {
+        let cover = std::fs::read("tests/images/red_panda.jpg").unwrap();
+        let encoder = DctStegoEncoder::from_bytes(&cover).unwrap();
+        let encoded = encoder.encode_bytes(b"in a forest dark").unwrap();
+        assert_eq!(DctStegoDecoder::from_bytes(&encoded).unwrap().decode().unwrap(), b"in a forest dark");
+
+        // Only the coefficients holding data change
+        let before = read_luma(&cover).unwrap();
+        let after = read_luma(&encoded).unwrap();
+        assert_eq!(before.table, after.table);
+        assert_eq!(before.blocks.len(), after.blocks.len());
+        let changed = before
+            .blocks
+            .iter()
+            .zip(after.blocks.iter())
+            .flat_map(|(before, after)| before.iter().zip(after.iter()))
+            .filter(|(before, after)| before != after)
+            .count();
+        assert!(changed > 0 && changed <= (4 + 16) * 2 * 8);
+    }
+
+    #[test]
+    fn insufficient_capacity() {
+        let encoder = DctStegoEncoder::from_image(cover());
+        let too_large = vec![0u8; encoder.estimate_capacity().unwrap() + 1];
+        assert!(matches!(
+            encoder.encode_bytes(&too_large),
+            Err(SteganographyError::InsufficientCapacity { .. })
+        ));
+
+        // A flat image has no coefficient large enough to hold data
+        let flat = DctStegoEncoder::from_image(DynamicImage::new_rgb8(32, 32));
+        assert_eq!(flat.estimate_capacity().unwrap(), 0);
+    }
+
+    #[test]
+    fn coefficients() {
+        assert_eq!(embed_bit(6, true, 1), 7);
+        assert_eq!(embed_bit(-7, false, 1), -6);
+        assert_eq!(embed_bit(-2, true, 1), -3);
+        // Clearing the bit of 3 would leave a coefficient no longer holding data
+        assert_eq!(embed_bit(3, false, 2), 4);
+    }
+
+    #[test]
+    fn rejects_invalid_jpeg() {
+        let mut jpeg = vec![];
+        JpegEncoder::new_with_quality(&mut jpeg, 65)
+            .encode_image(&DynamicImage::new_rgb8(8, 8))
+            .unwrap();
+        // A file cut within its headers holds no image
+        jpeg.truncate(20);
+        assert!(matches!(
+            DctStegoDecoder::from_bytes(&jpeg),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+        assert!(matches!(
+            DctStegoDecoder::from_bytes(b"not a jpeg"),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+    }
+}
@@ -0,0 +1,12 @@
+/// The module holding the encoder and decoder hiding data in the palette indices of GIF images
+pub mod gif;
+
+/// The module holding the encoder and decoder hiding data in an ancillary chunk of PNG images
+pub mod png_chunk;
+
+/// The module holding the encoder and decoder hiding data in private tags of TIFF images
+pub mod tiff;
+
+/// The module holding the encoder and decoder hiding data in the DCT coefficients of JPEG images
+#[cfg(feature = "jpeg_dct")]
+pub mod jpeg_dct;
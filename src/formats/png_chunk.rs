@@ -0,0 +1,233 @@
+//! The data is written with `png::Writer::write_chunk`. `png::Decoder` skips unknown chunks
+//! without exposing their data, so the decoder drives the `png::StreamingDecoder` underneath
+//! it instead, which still checks the signature, the structure and the checksums of the file,
+//! and takes the data of the `stEG` chunk at the offset it reports.
+
+use std::io::Read;
+
+use png::{Decoded, DecodingError, StreamingDecoder, Transformations};
+
+use crate::error::SteganographyError;
+
+/// The type of the chunk holding the payload. Its lowercase first two letters mark it as
+/// an ancillary, private chunk, which renderers ignore
+pub const CHUNK_TYPE: [u8; 4] = *b"stEG";
+
+/// The largest chunk data length allowed by the PNG specification
+const MAX_CHUNK_LEN: usize = (1 << 31) - 1;
+
+/// Hides data in a `stEG` chunk of a PNG file, leaving its pixels untouched. Unlike LSB
+/// encoding, the payload survives pixel analysis, but is lost as soon as the image is saved
+/// again by a program that drops unknown chunks.
+///
+/// The image is written again from its decoded samples, keeping its color type, bit depth,
+/// palette and transparency. Other ancillary chunks are dropped, and interlaced images are
+/// written without interlacing
+pub struct PngChunkEncoder {
+    info: png::Info,
+    samples: Vec<u8>,
+}
+
+impl PngChunkEncoder {
+    /// Reads the PNG file to encode data into
+    pub fn from_reader<R>(reader: &mut R) -> Result<Self, SteganographyError>
+    where
+        R: Read + ?Sized,
+    {
+        let mut png = vec![];
+        reader.read_to_end(&mut png)?;
+        Self::from_bytes(&png)
+    }
+
+    /// Reads the PNG file to encode data into from its bytes. Fails if they do not hold
+    /// a valid PNG image
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(Transformations::IDENTITY);
+        let (output, mut reader) = decoder.read_info().map_err(invalid_png)?;
+        let mut samples = vec![0; output.buffer_size()];
+        reader.next_frame(&mut samples).map_err(invalid_png)?;
+
+        Ok(Self {
+            info: reader.info().clone(),
+            samples,
+        })
+    }
+
+    /// Encodes arbitrary bytes into a `stEG` chunk, returning the resulting PNG file. The
+    /// chunk is placed right after the header, replacing any previous one
+    pub fn encode_bytes(&self, data: impl AsRef<[u8]>) -> Result<Vec<u8>, SteganographyError> {
+        let data = data.as_ref();
+        if data.len() > MAX_CHUNK_LEN {
+            return Err(SteganographyError::InsufficientCapacity {
+                required: data.len(),
+                available: MAX_CHUNK_LEN,
+            });
+        }
+
+        let mut png = Vec::with_capacity(self.samples.len() + data.len());
+        let mut encoder = png::Encoder::new(&mut png, self.info.width, self.info.height);
+        encoder.set_color(self.info.color_type);
+        encoder.set_depth(self.info.bit_depth);
+        if let Some(palette) = &self.info.palette {
+            encoder.set_palette(palette.clone());
+        }
+        if let Some(trns) = &self.info.trns {
+            encoder.set_trns(trns.clone());
+        }
+        let mut writer = encoder.write_header().map_err(encoding_error)?;
+        writer.write_chunk(CHUNK_TYPE, data).map_err(encoding_error)?;
+        writer.write_image_data(&self.samples).map_err(encoding_error)?;
+        // The end of the image is written when the writer is dropped
+        drop(writer);
+
+        Ok(png)
+    }
+}
+
+/// Recovers data hidden in a PNG file by `PngChunkEncoder`
+pub struct PngChunkDecoder {
+    png: Vec<u8>,
+}
+
+impl PngChunkDecoder {
+    /// Reads the PNG file to decode data from
+    pub fn from_reader<R>(reader: &mut R) -> Result<Self, SteganographyError>
+    where
+        R: Read + ?Sized,
+    {
+        let mut png = vec![];
+        reader.read_to_end(&mut png)?;
+        Self::from_bytes(&png)
+    }
+
+    /// Reads the PNG file to decode data from its bytes. The file is checked while decoding,
+    /// so that a corrupted `stEG` chunk is reported as such
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        Ok(Self { png: data.to_vec() })
+    }
+
+    /// Decodes the data of the `stEG` chunk, or `None` if the file has no such chunk.
+    /// Fails if the chunk does not match its checksum, or if the file is not a valid PNG
+    /// chunk stream
+    pub fn decode(&self) -> Result<Option<Vec<u8>>, SteganographyError> {
+        let mut decoder = StreamingDecoder::new();
+        let mut image_data = vec![];
+        let mut position = 0;
+        let mut chunk = None;
+        loop {
+            let (consumed, decoded) = decoder
+                .update(&self.png[position..], &mut image_data)
+                .map_err(|error| match error {
+                    DecodingError::CrcMismatch {
+                        crc_val,
+                        crc_sum,
+                        chunk: CHUNK_TYPE,
+                        ..
+                    } => SteganographyError::ChecksumMismatch {
+                        expected: crc_sum,
+                        got: crc_val,
+                    },
+                    error => invalid_png(error),
+                })?;
+            position += consumed;
+            // The pixels are not needed
+            image_data.clear();
+
+            match decoded {
+                // The decoder stops right after the chunk type, where its data starts
+                Decoded::ChunkBegin(length, CHUNK_TYPE) if chunk.is_none() => {
+                    chunk = self.png.get(position..position + length as usize);
+                }
+                Decoded::ImageEnd => return Ok(chunk.map(|data| data.to_vec())),
+                Decoded::Nothing if position == self.png.len() => {
+                    return Err(invalid_png(DecodingError::Format("unexpected EOF".into())));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn invalid_png(error: DecodingError) -> SteganographyError {
+    SteganographyError::ImageLoadError(image::ImageError::Decoding(image::error::DecodingError::new(
+        image::error::ImageFormatHint::Exact(image::ImageFormat::Png),
+        error,
+    )))
+}
+
+fn encoding_error(error: png::EncodingError) -> SteganographyError {
+    SteganographyError::ImageLoadError(image::ImageError::Encoding(image::error::EncodingError::new(
+        image::error::ImageFormatHint::Exact(image::ImageFormat::Png),
+        error,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PngChunkDecoder, PngChunkEncoder};
+    use crate::{encoder::ImageEncoder, error::SteganographyError};
+
+    fn png() -> Vec<u8> {
+        ImageEncoder::default()
+            .encode_bytes(b"seagul")
+            .unwrap()
+            .to_png_bytes()
+            .unwrap()
+    }
+
+    #[test]
+    fn encode_decode() {
+        let source = png();
+        assert_eq!(PngChunkDecoder::from_bytes(&source).unwrap().decode().unwrap(), None);
+
+        let encoded = PngChunkEncoder::from_bytes(&source)
+            .unwrap()
+            .encode_bytes(b"Midway upon the journey")
+            .unwrap();
+        assert_eq!(
+            PngChunkDecoder::from_bytes(&encoded).unwrap().decode().unwrap().unwrap(),
+            b"Midway upon the journey"
+        );
+        assert!(image::load_from_memory(&encoded).unwrap() == image::load_from_memory(&source).unwrap());
+
+        // A new payload replaces the previous one
+        let reencoded = PngChunkEncoder::from_reader(&mut &encoded[..])
+            .unwrap()
+            .encode_bytes(b"of our life")
+            .unwrap();
+        assert_eq!(reencoded.windows(4).filter(|window| *window == b"stEG").count(), 1);
+        assert_eq!(
+            PngChunkDecoder::from_bytes(&reencoded).unwrap().decode().unwrap().unwrap(),
+            b"of our life"
+        );
+        assert!(image::load_from_memory(&reencoded).unwrap() == image::load_from_memory(&source).unwrap());
+    }
+
+    #[test]
+    fn corrupted_chunks() {
+        let encoded = PngChunkEncoder::from_bytes(&png()).unwrap().encode_bytes(b"Midway").unwrap();
+        let truncated = &encoded[..encoded.len() - 4];
+        assert!(matches!(
+            PngChunkDecoder::from_bytes(truncated).unwrap().decode(),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+
+        let mut encoded = encoded;
+        let payload_start = encoded.windows(6).position(|window| window == b"Midway").unwrap();
+        encoded[payload_start] ^= 1;
+        assert!(matches!(
+            PngChunkDecoder::from_bytes(&encoded).unwrap().decode(),
+            Err(SteganographyError::ChecksumMismatch { .. })
+        ));
+
+        assert!(matches!(
+            PngChunkEncoder::from_bytes(b"not a png"),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+        assert!(matches!(
+            PngChunkDecoder::from_bytes(b"not a png").unwrap().decode(),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+use std::io::{Cursor, Read};
+
+use ::tiff::{
+    decoder::Decoder,
+    encoder::{colortype, TiffEncoder},
+    tags::Tag,
+};
+use image::{DynamicImage, GenericImageView, ImageFormat};
+
+use crate::error::SteganographyError;
+
+/// The private tag holding the first chunk of the payload. The following chunks are stored,
+/// in order, in the tags right after it
+pub const FIRST_TAG: u16 = 0xe000;
+
+/// The largest number of payload bytes a single tag holds
+pub const MAX_CHUNK_LEN: usize = u16::MAX as usize;
+
+/// The number of private tags available to the payload, up to the last tag code
+const MAX_CHUNKS: usize = (u16::MAX - FIRST_TAG) as usize + 1;
+
+/// Hides data in private tags of a TIFF file, each one holding up to `MAX_CHUNK_LEN` bytes.
+/// The pixels are left untouched, and since tags are metadata the payload survives most
+/// pixel processing, as long as the image is saved again as a TIFF file keeping unknown tags.
+///
+/// The image is written back as a single uncompressed image, so any other tag, page or
+/// compression of the original file is dropped
+pub struct TiffTagEncoder {
+    image: DynamicImage,
+}
+
+impl TiffTagEncoder {
+    /// Reads the TIFF file to encode data into
+    pub fn from_reader<R>(reader: &mut R) -> Result<Self, SteganographyError>
+    where
+        R: Read + ?Sized,
+    {
+        let mut tiff = vec![];
+        reader.read_to_end(&mut tiff)?;
+        Self::from_bytes(&tiff)
+    }
+
+    /// Reads the TIFF file to encode data into from its bytes
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        Ok(Self {
+            image: image::load_from_memory_with_format(data, ImageFormat::Tiff)?,
+        })
+    }
+
+    /// The number of payload bytes that fit into the private tags
+    pub fn estimate_capacity(&self) -> usize {
+        MAX_CHUNKS * MAX_CHUNK_LEN
+    }
+
+    /// Encodes arbitrary bytes into private tags, returning the resulting TIFF file. An empty
+    /// payload is still written as an empty tag, so it can be told apart from no payload
+    pub fn encode_bytes(&self, data: impl AsRef<[u8]>) -> Result<Vec<u8>, SteganographyError> {
+        let data = data.as_ref();
+        let available = self.estimate_capacity();
+        if data.len() > available {
+            return Err(SteganographyError::InsufficientCapacity {
+                required: data.len(),
+                available,
+            });
+        }
+
+        let chunks: Vec<&[u8]> = if data.is_empty() {
+            vec![data]
+        } else {
+            data.chunks(MAX_CHUNK_LEN).collect()
+        };
+
+        let mut tiff = Cursor::new(Vec::new());
+        let mut encoder = TiffEncoder::new(&mut tiff)?;
+        let (width, height) = self.image.dimensions();
+        match &self.image {
+            DynamicImage::ImageLuma8(buffer) => {
+                write_image::<colortype::Gray8, _>(&mut encoder, width, height, buffer, &chunks)?
+            }
+            DynamicImage::ImageLuma16(buffer) => {
+                write_image::<colortype::Gray16, _>(&mut encoder, width, height, buffer, &chunks)?
+            }
+            DynamicImage::ImageRgb16(buffer) => {
+                write_image::<colortype::RGB16, _>(&mut encoder, width, height, buffer, &chunks)?
+            }
+            DynamicImage::ImageRgba16(buffer) => {
+                write_image::<colortype::RGBA16, _>(&mut encoder, width, height, buffer, &chunks)?
+            }
+            DynamicImage::ImageLumaA8(_) | DynamicImage::ImageRgba8(_) | DynamicImage::ImageBgra8(_) => {
+                let buffer = self.image.to_rgba8();
+                write_image::<colortype::RGBA8, _>(&mut encoder, width, height, &buffer, &chunks)?
+            }
+            _ => {
+                let buffer = self.image.to_rgb8();
+                write_image::<colortype::RGB8, _>(&mut encoder, width, height, &buffer, &chunks)?
+            }
+        }
+
+        Ok(tiff.into_inner())
+    }
+}
+
+/// Recovers data hidden in a TIFF file by `TiffTagEncoder`
+pub struct TiffTagDecoder {
+    tiff: Vec<u8>,
+}
+
+impl TiffTagDecoder {
+    /// Reads the TIFF file to decode data from
+    pub fn from_reader<R>(reader: &mut R) -> Result<Self, SteganographyError>
+    where
+        R: Read + ?Sized,
+    {
+        let mut tiff = vec![];
+        reader.read_to_end(&mut tiff)?;
+        Self::from_bytes(&tiff)
+    }
+
+    /// Reads the TIFF file to decode data from its bytes. Fails if they do not start
+    /// with a readable TIFF image directory
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        Decoder::new(Cursor::new(data))?;
+        Ok(Self { tiff: data.to_vec() })
+    }
+
+    /// Decodes the data held by the private tags of the first image, or `None` if the file
+    /// has no such tag. Chunks are read from `FIRST_TAG` on, up to the first missing tag
+    pub fn decode(&self) -> Result<Option<Vec<u8>>, SteganographyError> {
+        let mut decoder = Decoder::new(Cursor::new(&self.tiff[..]))?;
+        let mut data = vec![];
+        for code in FIRST_TAG..=u16::MAX {
+            let values = match decoder.find_tag(Tag::Unknown(code))? {
+                Some(value) => value.into_u32_vec()?,
+                None if code == FIRST_TAG => return Ok(None),
+                None => break,
+            };
+            for value in values {
+                data.push(value as u8);
+            }
+        }
+
+        Ok(Some(data))
+    }
+}
+
+/// Writes a single image made of the samples of `buffer`, along with one `BYTE` tag per chunk
+fn write_image<C, W>(
+    encoder: &mut TiffEncoder<W>,
+    width: u32,
+    height: u32,
+    buffer: &[C::Inner],
+    chunks: &[&[u8]],
+) -> Result<(), SteganographyError>
+where
+    C: colortype::ColorType,
+    [C::Inner]: ::tiff::encoder::TiffValue,
+    W: std::io::Write + std::io::Seek,
+{
+    let mut image = encoder.new_image::<C>(width, height)?;
+    for (code, chunk) in (FIRST_TAG..=u16::MAX).zip(chunks) {
+        image.encoder().write_tag(Tag::Unknown(code), *chunk)?;
+    }
+    image.write_data(buffer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use ::tiff::encoder::{colortype, TiffEncoder};
+    use image::RgbImage;
+
+    use super::{TiffTagDecoder, TiffTagEncoder, MAX_CHUNK_LEN};
+    use crate::error::SteganographyError;
+
+    fn source() -> Vec<u8> {
+        let image = RgbImage::from_fn(32, 16, |x, y| image::Rgb([x as u8 * 8, y as u8 * 16, 128]));
+        let mut tiff = Cursor::new(Vec::new());
+        TiffEncoder::new(&mut tiff)
+            .unwrap()
+            .write_image::<colortype::RGB8>(32, 16, &image)
+            .unwrap();
+        tiff.into_inner()
+    }
+
+    #[test]
+    fn encode_decode() {
+        let source = source();
+        assert_eq!(TiffTagDecoder::from_bytes(&source).unwrap().decode().unwrap(), None);
+
+        let encoder = TiffTagEncoder::from_bytes(&source).unwrap();
+        let encoded = encoder.encode_bytes(b"Midway upon the journey").unwrap();
+        let decoded = TiffTagDecoder::from_bytes(&encoded).unwrap().decode().unwrap();
+        assert_eq!(decoded.as_deref(), Some(&b"Midway upon the journey"[..]));
+
+        // The pixels are untouched
+        let original = image::load_from_memory(&source).unwrap();
+        let altered = image::load_from_memory(&encoded).unwrap();
+        assert!(original == altered);
+
+        let encoded = encoder.encode_bytes(b"").unwrap();
+        let decoded = TiffTagDecoder::from_bytes(&encoded).unwrap().decode().unwrap();
+        assert_eq!(decoded, Some(vec![]));
+    }
+
+    #[test]
+    fn spans_several_tags() {
+        let data: Vec<u8> = (0..MAX_CHUNK_LEN * 2 + 10).map(|i| (i % 251) as u8).collect();
+        let encoded = TiffTagEncoder::from_bytes(&source()).unwrap().encode_bytes(&data).unwrap();
+        let decoded = TiffTagDecoder::from_bytes(&encoded).unwrap().decode().unwrap();
+        assert_eq!(decoded, Some(data));
+    }
+
+    #[test]
+    fn invalid_sources() {
+        assert!(matches!(
+            TiffTagEncoder::from_bytes(b"not a tiff"),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+        assert!(matches!(
+            TiffTagDecoder::from_bytes(b"not a tiff"),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+    }
+}
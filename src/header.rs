@@ -0,0 +1,364 @@
+use std::convert::TryInto;
+
+use bitvec::{order::Lsb0, vec::BitVec};
+
+use crate::error::SteganographyError;
+
+/// Size in bytes of an encoded `Header`
+pub const HEADER_SIZE: usize = 12;
+
+/// Size in bytes of the CRC32 appended to the payload by `ImageEncoder::encode_with_crc`
+pub const CRC_SIZE: usize = std::mem::size_of::<u32>();
+
+/// Size in bytes of the little endian payload length written by `frame_with_length`
+pub const LENGTH_SIZE: usize = 4;
+
+/// The number of bits in a byte
+pub(crate) const BYTE_STEP: usize = std::mem::size_of::<u8>() * 8;
+
+/// The only header version understood by this library
+pub const HEADER_VERSION: u8 = 1;
+
+/// Header flag set when the payload is spread across the whole image
+pub const FLAG_SPREAD: u8 = 0b0000_0001;
+
+/// Header flag set when the payload is encrypted
+pub const FLAG_ENCRYPTED: u8 = 0b0000_0010;
+
+/// Header flag set when the payload carries error correction codes
+pub const FLAG_ECC: u8 = 0b0000_0100;
+
+/// Describes the payload that follows it in an image encoded with
+/// `ImageEncoder::encode_with_header`.
+///
+/// The encoded layout is, in order: `version`, `payload_len` (little endian), `lsb_c`,
+/// `channel`, `checksum` (little endian) and `flags`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    /// The version of the header layout
+    pub version: u8,
+
+    /// The length of the payload, in bytes
+    pub payload_len: u32,
+
+    /// The number of least significant bits used on each channel
+    pub lsb_c: u8,
+
+    /// The first channel used to store the payload
+    pub channel: u8,
+
+    /// The CRC32 of the payload
+    pub checksum: u32,
+
+    /// A combination of `FLAG_SPREAD`, `FLAG_ENCRYPTED` and `FLAG_ECC`
+    pub flags: u8,
+}
+
+impl Header {
+    /// Creates a header of the current version describing `payload`
+    pub fn new(payload: &[u8], lsb_c: u8, channel: u8, flags: u8) -> Result<Self, SteganographyError> {
+        let payload_len = payload.len().try_into().map_err(|_| {
+            SteganographyError::InvalidConfiguration(format!(
+                "Payloads longer than {} bytes cannot be described by a header",
+                u32::MAX
+            ))
+        })?;
+
+        Ok(Self {
+            version: HEADER_VERSION,
+            payload_len,
+            lsb_c,
+            channel,
+            checksum: crc32(payload),
+            flags,
+        })
+    }
+
+    pub fn encode(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[0] = self.version;
+        bytes[1..5].copy_from_slice(&self.payload_len.to_le_bytes());
+        bytes[5] = self.lsb_c;
+        bytes[6] = self.channel;
+        bytes[7..11].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes[11] = self.flags;
+        bytes
+    }
+
+    /// Reads a header from its encoded form, failing if its version is not supported
+    pub fn decode(bytes: &[u8; HEADER_SIZE]) -> Result<Header, SteganographyError> {
+        if bytes[0] != HEADER_VERSION {
+            return Err(SteganographyError::UnsupportedHeaderVersion(bytes[0]));
+        }
+
+        Ok(Self {
+            version: bytes[0],
+            payload_len: u32::from_le_bytes(bytes[1..5].try_into().unwrap()),
+            lsb_c: bytes[5],
+            channel: bytes[6],
+            checksum: u32::from_le_bytes(bytes[7..11].try_into().unwrap()),
+            flags: bytes[11],
+        })
+    }
+
+    /// Checks that `payload` matches the checksum in this header
+    pub fn verify(&self, payload: &[u8]) -> Result<(), SteganographyError> {
+        let got = crc32(payload);
+        if got == self.checksum {
+            Ok(())
+        } else {
+            Err(SteganographyError::ChecksumMismatch {
+                expected: self.checksum,
+                got,
+            })
+        }
+    }
+
+    pub fn is_spread(&self) -> bool {
+        self.flags & FLAG_SPREAD != 0
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    pub fn has_ecc(&self) -> bool {
+        self.flags & FLAG_ECC != 0
+    }
+}
+
+/// Describes a file encoded with `ImageEncoder::encode_file`, which is followed by the
+/// file contents.
+///
+/// The encoded layout is, in order: `filename_len`, `filename` and `file_len` (little endian)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHeader {
+    /// The length of the file name, in bytes
+    pub filename_len: u8,
+
+    /// The UTF-8 name of the file, without any directory
+    pub filename: Vec<u8>,
+
+    /// The length of the file contents, in bytes
+    pub file_len: u32,
+}
+
+impl FileHeader {
+    /// Creates a header describing a file named `filename` holding `file_len` bytes. Fails
+    /// if the name is empty, is not a plain file name, or is longer than 255 bytes
+    pub fn new(filename: &str, file_len: usize) -> Result<Self, SteganographyError> {
+        let filename_len = filename.len().try_into().map_err(|_| {
+            SteganographyError::InvalidConfiguration(format!(
+                "File names longer than {} bytes cannot be described by a header",
+                u8::MAX
+            ))
+        })?;
+        let file_len = file_len.try_into().map_err(|_| {
+            SteganographyError::InvalidConfiguration(format!(
+                "Files longer than {} bytes cannot be described by a header",
+                u32::MAX
+            ))
+        })?;
+
+        let header = Self {
+            filename_len,
+            filename: filename.as_bytes().to_vec(),
+            file_len,
+        };
+        header.file_name()?;
+        Ok(header)
+    }
+
+    /// The size in bytes of this header once encoded
+    pub fn encoded_len(&self) -> usize {
+        1 + self.filename.len() + 4
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        bytes.push(self.filename_len);
+        bytes.extend_from_slice(&self.filename);
+        bytes.extend_from_slice(&self.file_len.to_le_bytes());
+        bytes
+    }
+
+    /// Reads a header from the start of `bytes`, failing if they are too short to hold it
+    pub fn decode(bytes: &[u8]) -> Result<FileHeader, SteganographyError> {
+        let truncated = |required: usize| SteganographyError::InsufficientCapacity {
+            required,
+            available: bytes.len(),
+        };
+
+        let filename_len = *bytes.first().ok_or_else(|| truncated(1))?;
+        let filename_end = 1 + filename_len as usize;
+        let encoded_len = filename_end + 4;
+        if bytes.len() < encoded_len {
+            return Err(truncated(encoded_len));
+        }
+
+        Ok(Self {
+            filename_len,
+            filename: bytes[1..filename_end].to_vec(),
+            file_len: u32::from_le_bytes(bytes[filename_end..encoded_len].try_into().unwrap()),
+        })
+    }
+
+    /// The name of the file, failing if it is not valid UTF-8 or could point outside of
+    /// the directory the file is written to
+    pub fn file_name(&self) -> Result<&str, SteganographyError> {
+        let invalid = || {
+            SteganographyError::InvalidConfiguration(format!(
+                "Invalid file name {:?} in header",
+                String::from_utf8_lossy(&self.filename)
+            ))
+        };
+
+        let filename = std::str::from_utf8(&self.filename).map_err(|_| invalid())?;
+        let mut components = std::path::Path::new(filename).components();
+        match (components.next(), components.next()) {
+            (Some(std::path::Component::Normal(name)), None) if name == filename => Ok(filename),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Prefixes `data` with its length, as a `LENGTH_SIZE` bytes little endian integer, so that
+/// `read_length_framed` can tell it apart from the bytes following it
+pub fn frame_with_length(data: &[u8]) -> Result<Vec<u8>, SteganographyError> {
+    let length: u32 = data.len().try_into().map_err(|_| {
+        SteganographyError::InvalidConfiguration(String::from(
+            "The payload is too large to be described by its length",
+        ))
+    })?;
+
+    let mut framed = Vec::with_capacity(LENGTH_SIZE + data.len());
+    framed.extend_from_slice(&length.to_le_bytes());
+    framed.extend_from_slice(data);
+    Ok(framed)
+}
+
+/// Reads the payload framed by `frame_with_length` at the start of `bytes`, ignoring the
+/// bytes following it. Fails if `bytes` holds fewer bytes than the length read from them,
+/// which usually means they hold no payload
+pub fn read_length_framed(bytes: &[u8]) -> Result<&[u8], SteganographyError> {
+    if bytes.len() < LENGTH_SIZE {
+        return Err(SteganographyError::InsufficientCapacity {
+            required: LENGTH_SIZE,
+            available: bytes.len(),
+        });
+    }
+
+    let required = u32::from_le_bytes(bytes[..LENGTH_SIZE].try_into().unwrap()) as usize;
+    let payload = &bytes[LENGTH_SIZE..];
+    if required > payload.len() {
+        return Err(SteganographyError::InsufficientCapacity {
+            required,
+            available: payload.len(),
+        });
+    }
+
+    Ok(&payload[..required])
+}
+
+/// The bytes fully held by `bits`, dropping the last one if it is incomplete
+pub(crate) fn complete_bytes(bits: BitVec<Lsb0, u8>) -> Vec<u8> {
+    let complete_bytes = bits.len() / BYTE_STEP;
+    let mut bytes = bits.into_vec();
+    bytes.truncate(complete_bytes);
+    bytes
+}
+
+/// The CRC32 (IEEE 802.3) checksum of `data`
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::prelude::*;
+
+    use super::{
+        complete_bytes, crc32, frame_with_length, read_length_framed, FileHeader, Header, FLAG_ECC,
+        FLAG_SPREAD, HEADER_VERSION,
+    };
+    use crate::error::SteganographyError;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn length_framing() {
+        let mut framed = frame_with_length(b"seagul").unwrap();
+        assert_eq!(&framed[..4], &[6, 0, 0, 0]);
+        framed.extend_from_slice(b"trailing");
+        assert_eq!(read_length_framed(&framed).unwrap(), b"seagul");
+
+        assert!(matches!(
+            read_length_framed(&framed[..3]),
+            Err(SteganographyError::InsufficientCapacity { required: 4, available: 3 })
+        ));
+        assert!(matches!(
+            read_length_framed(&framed[..8]),
+            Err(SteganographyError::InsufficientCapacity { required: 6, available: 4 })
+        ));
+
+        let bits: BitVec<Lsb0, u8> = b"sea".view_bits::<Lsb0>()[..20].to_bitvec();
+        assert_eq!(complete_bytes(bits), b"se");
+    }
+
+    #[test]
+    fn encode_decode() {
+        let header = Header::new(b"seagul", 2, 1, FLAG_SPREAD).unwrap();
+        let bytes = header.encode();
+
+        assert_eq!(bytes[0], HEADER_VERSION);
+        assert_eq!(&bytes[1..5], &[6, 0, 0, 0]);
+        assert_eq!(Header::decode(&bytes).unwrap(), header);
+        assert!(header.is_spread());
+        assert!(!header.is_encrypted());
+        assert!(!header.has_ecc());
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut bytes = Header::new(b"seagul", 1, 2, FLAG_ECC).unwrap().encode();
+        bytes[0] = HEADER_VERSION + 1;
+
+        assert!(matches!(
+            Header::decode(&bytes),
+            Err(SteganographyError::UnsupportedHeaderVersion(version)) if version == HEADER_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn file_header() {
+        let header = FileHeader::new("seagul.txt", 6).unwrap();
+        let mut bytes = header.encode();
+        assert_eq!(bytes.len(), header.encoded_len());
+        assert_eq!(bytes[0], 10);
+
+        bytes.extend_from_slice(b"seagul");
+        let decoded = FileHeader::decode(&bytes).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(decoded.file_name().unwrap(), "seagul.txt");
+
+        assert!(FileHeader::decode(&bytes[..8]).is_err());
+        for filename in ["", "..", "/etc/passwd", "seagul/../passwd"] {
+            assert!(FileHeader::new(filename, 0).is_err());
+        }
+    }
+
+    #[test]
+    fn checksum_verification() {
+        let header = Header::new(b"seagul", 1, 2, 0).unwrap();
+
+        assert!(header.verify(b"seagul").is_ok());
+        assert!(matches!(
+            header.verify(b"seagull"),
+            Err(SteganographyError::ChecksumMismatch { .. })
+        ));
+    }
+}
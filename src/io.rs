@@ -0,0 +1,160 @@
+use std::{convert::TryFrom, path::Path};
+#[cfg(feature = "hash")]
+use std::io::Write;
+
+#[cfg(feature = "hash")]
+use digest::{Digest, Output};
+
+use crate::{config::{DecoderConfig, EncoderConfig}, decoder::ImageDecoder, encoder::ImageEncoder, error::SteganographyError, prelude::ImageFormat};
+
+/// Encodes into and decodes from the image at a path in a single call, configuring the
+/// encoder or decoder with an `EncoderConfig` or a `DecoderConfig`
+///
+/// ```no_run
+/// use std::path::Path;
+/// use seagul_core::{config::EncoderConfig, io::PathExt};
+///
+/// let config: EncoderConfig = "lsb=2,channel=blue".parse()?;
+/// Path::new("input.png").encode_to(b"secret", Path::new("output.png"), &config)?;
+/// # Ok::<(), seagul_core::error::SteganographyError>(())
+/// ```
+pub trait PathExt {
+    /// Encodes `data` into the image at this path with the options of `config`, then saves
+    /// it to `output` in the format matching its extension. Fails with
+    /// `SteganographyError::LossyFormat`, without creating `output`, for JPEG outputs
+    fn encode_to(&self, data: &[u8], output: &Path, config: &EncoderConfig) -> Result<(), SteganographyError>;
+
+    /// Decodes the data held by the image at this path with the options of `config`, the
+    /// same way `ImageDecoder::decode` does
+    fn decode_from(&self, config: &DecoderConfig) -> Result<Vec<u8>, SteganographyError>;
+}
+
+impl PathExt for Path {
+    fn encode_to(&self, data: &[u8], output: &Path, config: &EncoderConfig) -> Result<(), SteganographyError> {
+        let format = output_format(output)?;
+        let mut encoder = ImageEncoder::try_from(self)?;
+        encoder.apply_config(config.clone());
+
+        let mut bytes = vec![];
+        encoder.encode_bytes(data)?.write(&mut bytes, format)?;
+        std::fs::write(output, bytes)?;
+        Ok(())
+    }
+
+    fn decode_from(&self, config: &DecoderConfig) -> Result<Vec<u8>, SteganographyError> {
+        let mut decoder = ImageDecoder::try_from(self)?;
+        decoder.apply_config(config.clone());
+
+        Ok(decoder.decode()?.embedded_data().clone())
+    }
+}
+
+/// The output format matching the extension of `path`
+fn output_format(path: &Path) -> Result<ImageFormat, SteganographyError> {
+    match image::ImageFormat::from_path(path) {
+        Ok(format @ (image::ImageFormat::Png | image::ImageFormat::Jpeg | image::ImageFormat::Bmp)) => {
+            Ok(format.into())
+        }
+        _ => Err(SteganographyError::InvalidConfiguration(format!(
+            "Unsupported output format for {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Writes bytes into a writer while hashing them, so that the hash of an exported image
+/// is known without reading it back. Only the bytes accepted by the inner writer are hashed
+#[cfg(feature = "hash")]
+pub struct TeeWriter<W, H> {
+    writer: W,
+    hasher: H,
+}
+
+#[cfg(feature = "hash")]
+impl<W, H> TeeWriter<W, H>
+where
+    W: Write,
+    H: Digest,
+{
+    pub fn new(writer: W) -> Self {
+        Self::with_hasher(writer, H::new())
+    }
+
+    /// Hashes the written bytes with `hasher`, which may already hold some data
+    pub fn with_hasher(writer: W, hasher: H) -> Self {
+        Self { writer, hasher }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes this `TeeWriter`, returning the inner writer and the hash of every byte
+    /// written into it
+    pub fn finalize_hash(self) -> (W, Output<H>) {
+        (self.writer, self.hasher.finalize())
+    }
+}
+
+#[cfg(feature = "hash")]
+impl<W, H> Write for TeeWriter<W, H>
+where
+    W: Write,
+    H: Digest,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::PathExt;
+    use crate::{config::{DecoderConfig, EncoderConfig}, error::SteganographyError, prelude::ImageFormat};
+
+    #[test]
+    fn encode_decode_paths() {
+        let input = Path::new("tests/images/red_panda.jpg");
+        let output = std::env::temp_dir().join("seagul_core_path_ext.png");
+        let encoder_config: EncoderConfig = "lsb=2,channel=red+blue".parse().unwrap();
+        input
+            .encode_to(b"Midway upon the journey of our life", &output, &encoder_config)
+            .expect("Encoding failed");
+
+        let decoder_config: DecoderConfig = "lsb=2,channel=red+blue,max-bytes=35".parse().unwrap();
+        let decoded = output.decode_from(&decoder_config).expect("Decoding failed");
+        assert_eq!(decoded, b"Midway upon the journey of our life");
+        std::fs::remove_file(&output).unwrap();
+
+        let lossy_output = std::env::temp_dir().join("seagul_core_path_ext.jpg");
+        assert!(matches!(
+            input.encode_to(b"Midway", &lossy_output, &encoder_config),
+            Err(SteganographyError::LossyFormat(ImageFormat::Jpeg))
+        ));
+        assert!(!lossy_output.exists());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn hashes_written_bytes() {
+        use std::io::Write;
+
+        use super::TeeWriter;
+
+        let mut writer = TeeWriter::<Vec<u8>, blake3::Hasher>::new(Vec::new());
+        writer.write_all(b"Midway upon ").unwrap();
+        writer.write_all(b"the journey of our life").unwrap();
+
+        let (bytes, hash) = writer.finalize_hash();
+        assert_eq!(bytes, b"Midway upon the journey of our life");
+        assert_eq!(hash.as_slice(), blake3::hash(&bytes).as_bytes());
+    }
+}
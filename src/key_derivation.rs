@@ -0,0 +1,62 @@
+use hmac::Hmac;
+use sha2::Sha256;
+
+use crate::crypto::KEY_SIZE;
+
+/// Size in bytes of the salt mixed with a password to derive a key
+pub const SALT_SIZE: usize = 16;
+
+/// The number of PBKDF2 iterations used by `ImageEncoder::set_password_with_salt` and
+/// `ImageDecoder::set_password_with_salt`
+pub const DEFAULT_ITERATIONS: u32 = 600_000;
+
+/// Derives an encryption key from `password` with PBKDF2-HMAC-SHA256, running `iterations`
+/// rounds. The same password, salt and iterations always derive the same key, so the salt
+/// does not need to be secret but must be known to the decoder
+pub fn derive_key(password: &str, salt: &[u8; SALT_SIZE], iterations: u32) -> [u8; KEY_SIZE] {
+    let mut key = [0u8; KEY_SIZE];
+    pbkdf2_sha256(password.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+/// Fills `output` with the PBKDF2-HMAC-SHA256 derivation of `password` and `salt`
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32, output: &mut [u8]) {
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, iterations, output)
+        .expect("HMAC accepts keys of any length");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{derive_key, pbkdf2_sha256};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// The test cases of RFC 6070, which is written for SHA-1, computed with SHA-256
+    #[test]
+    fn rfc_6070_vectors() {
+        let vectors: [(&[u8], &[u8], u32, &str); 4] = [
+            (b"password", b"salt", 1, "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"),
+            (b"password", b"salt", 2, "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"),
+            (b"password", b"salt", 4096, "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"),
+            (b"pass\0word", b"sa\0lt", 4096, "89b69d0516f829893c696226650a8687"),
+        ];
+
+        for (password, salt, iterations, expected) in vectors {
+            let mut output = vec![0u8; expected.len() / 2];
+            pbkdf2_sha256(password, salt, iterations, &mut output);
+            assert_eq!(hex(&output), expected);
+        }
+    }
+
+    #[test]
+    fn deterministic_keys() {
+        let salt = *b"saltSALTsaltSALT";
+        let key = derive_key("password", &salt, 2);
+
+        assert_eq!(key, derive_key("password", &salt, 2));
+        assert_ne!(key, derive_key("password", &salt, 3));
+        assert_ne!(key, derive_key("password", b"SALTsaltSALTsalt", 2));
+    }
+}
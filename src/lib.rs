@@ -13,7 +13,9 @@
 //! ```ignore
 //! # use seagul_core::prelude::*;
 //! # use seagul_core::encoder::ImageEncoder;
-//! let encode_result = ImageEncoder::from("source.png")
+//! # use std::convert::TryFrom;
+//! let encode_result = ImageEncoder::try_from("source.png")
+//!     .expect("Could not load source image")
 //!     .set_use_n_lsb(2)
 //!     .set_use_channel(RgbChannel::Blue)
 //!     .encode_bytes(
@@ -45,7 +47,9 @@
 //! ```ignore
 //! # use seagul_core::prelude::*;
 //! # use seagul_core::decoder::ImageDecoder;
-//! let decoded = ImageDecoder::from("encoded.png")
+//! # use std::convert::TryFrom;
+//! let decoded = ImageDecoder::try_from("encoded.png")
+//!     .expect("Could not load encoded image")
 //!     .set_use_n_lsb(2)
 //!     .set_use_channel(RgbChannel::Blue)
 //!     .until_marker(Some(b"way.")) // <- If you know how the message ends
@@ -60,18 +64,130 @@
 //! 
 //! ```
 //!
+//! ## From file to string
+//!
+//! The whole flow, from the path of a source image to the recovered string. The decoder
+//! must use the same settings as the encoder, and stops at the marker ending the message
+//!
+//! ```
+//! # use std::convert::TryFrom;
+//! use seagul_core::{decoder::ImageDecoder, encoder::ImageEncoder, prelude::*};
+//!
+//! let output = std::env::temp_dir().join("seagul_core_doc_example.png");
+//! let output = output.to_str().unwrap();
+//!
+//! ImageEncoder::try_from("tests/images/red_panda.jpg")?
+//!     .with_lsb(2)
+//!     .encode_string("Midway upon the journey of our life--")?
+//!     .save(output, ImageFormat::Png)?;
+//!
+//! let decoded = ImageDecoder::try_from(output)?
+//!     .with_lsb(2)
+//!     .with_marker(Some("--"))
+//!     .decode()?;
+//! assert_eq!(decoded.as_string()?, "Midway upon the journey of our life--");
+//! # std::fs::remove_file(output)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
 //! # Supported formats
 //!
 //! While almost every major image format is supported as input, at the moment only
-//! PNG and BMP are supported as output formats. JPEG and other formats support is planned.
+//! PNG and BMP are supported as output formats. JPEG output is available through
+//! `EncodedImage::force_save`, but its lossy compression destroys the encoded data.
+//!
+//! # Optional features
+//!
+//! - `rayon`: processes pixels in parallel while encoding. The output is the same
+//!   as the sequential encoder.
+//! - `serde`: implements `Serialize` and `Deserialize` for the configuration types,
+//!   such as `EncoderConfig` and `DecoderConfig`.
+//! - `crypto`: encrypts payloads with ChaCha20-Poly1305 when encoders and decoders
+//!   are given a key, through `ImageEncoder::with_key` and `ImageDecoder::with_key`.
+//!   Keys can also be derived from a password with `key_derivation::derive_key`.
+//! - `hash`: hashes encoded images while writing them, through `io::TeeWriter` and
+//!   `EncodedImage::save_with_hash`.
+//! - `tokio`: runs encoding and decoding on the blocking thread pool of a tokio runtime,
+//!   through the `async_support` module.
+//! - `jpeg_dct`: hides data in the DCT coefficients of JPEG images, where it survives
+//!   recompression, through `formats::jpeg_dct`. Builds libjpeg through `mozjpeg-sys`.
 
 /// The module holding all the base components and traits for the library
 pub mod prelude;
 
 mod conversion;
 
+mod permutation;
+
+/// The module holding the error types returned by encoders and decoders
+pub mod error;
+
+/// The module holding the plain configurations of encoders and decoders
+pub mod config;
+
 /// The module holding all the encoders
 pub mod encoder;
 
 /// The module holding all the decoders
 pub mod decoder;
+
+/// The module holding the header that describes a payload encoded along with it
+pub mod header;
+
+/// The module holding the encoder settings stored in self describing images
+pub mod metadata;
+
+/// The module holding the strategies to choose which pixels hold data
+pub mod pixel_selectors;
+
+/// The module holding utilities to compute how much data fits into an image
+pub mod capacity;
+
+/// The module holding the error correction code protecting encoded data
+pub mod ecc;
+
+/// The module holding the transforms applied to payloads before encoding them
+pub mod pipeline;
+
+/// The module holding the encryption of payloads
+#[cfg(feature = "crypto")]
+pub mod crypto;
+
+/// The module holding the derivation of encryption keys from passwords
+#[cfg(feature = "crypto")]
+pub mod key_derivation;
+
+/// The module holding the comparison between an image and its altered version
+pub mod diff;
+
+/// The module holding tools to estimate whether an image carries hidden data
+pub mod steganalysis;
+
+/// The module holding the RS analysis estimating the embedding rate of an image channel,
+/// which is implemented in `steganalysis` along with the chi-square attack
+pub mod rs_analysis;
+
+/// The module holding the encoders and decoders of specific image formats
+pub mod formats;
+
+/// The module holding the encoders that adapt to the image content to be harder to detect
+pub mod stealth;
+
+/// The module holding the visible watermarks blended into images
+pub mod watermark;
+
+/// The module holding the annotated previews of the pixels altered by an encoding
+pub mod preview;
+
+/// The module holding the statistics of the bits changed by an encoding
+pub mod stats;
+
+/// The module holding the comparison of the payloads of different images
+pub mod comparison;
+
+/// The module holding the helpers to read and write encoded images
+pub mod io;
+
+/// The module holding the asynchronous wrappers of encoding and decoding
+#[cfg(feature = "tokio")]
+pub mod async_support;
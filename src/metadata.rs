@@ -0,0 +1,261 @@
+use std::convert::TryInto;
+
+use crate::{encoder::ImageEncoder, error::SteganographyError, prelude::{BitOrder, ImagePosition, ImageRules, RgbChannel}};
+
+/// Size in bytes of encoded `Metadata`
+pub const METADATA_SIZE: usize = 8;
+
+/// The number of pixels holding the metadata of a self describing image. Metadata is always
+/// stored in the least significant bit of the blue channel of the first pixels, row by row,
+/// or of the luma channel on grayscale images
+pub const METADATA_PIXELS: usize = METADATA_SIZE * 8;
+
+/// The only metadata version understood by this library
+pub const METADATA_VERSION: u8 = 1;
+
+const FLAG_SPREAD: u8 = 0b0000_0001;
+const FLAG_ECC: u8 = 0b0000_0010;
+const FLAG_MSB_FIRST: u8 = 0b0000_0100;
+
+/// The settings needed to decode an image encoded with `ImageEncoder::encode_self_describing`.
+///
+/// The encoded layout is, in order: `version`, `lsb_c`, `channel`, `position`, `skip_c`
+/// (little endian), a byte of flags for `spread`, `ecc` and `bit_order`, and a check byte
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    /// The version of the metadata layout
+    pub version: u8,
+
+    /// The number of least significant bits used on each channel
+    pub lsb_c: u8,
+
+    /// One pixel every `skip_c` pixels is used
+    pub skip_c: u16,
+
+    /// The color channel used to store the payload
+    pub channel: RgbChannel,
+
+    /// The position on the image the payload starts from. `ImagePosition::At` is not supported
+    pub position: ImagePosition,
+
+    /// Wheter the payload is spread across the whole image
+    pub spread: bool,
+
+    /// Wheter the payload is protected by an error correction code
+    pub ecc: bool,
+
+    /// The order in which the bits of each payload byte are stored
+    pub bit_order: BitOrder,
+}
+
+impl Metadata {
+    /// Describes the settings of `rules`, failing if they can not be described by metadata.
+    /// Only a single channel, no offset, region, permutation, custom pixel selection or
+    /// spread stride are supported
+    pub fn new<R>(rules: &R) -> Result<Self, SteganographyError>
+    where
+        R: ImageRules,
+    {
+        let unsupported = |setting: &str| {
+            Err(SteganographyError::InvalidConfiguration(format!(
+                "{} can not be described by metadata",
+                setting
+            )))
+        };
+
+        let channel = match rules.get_channel_order() {
+            [channel] => channel.clone(),
+            _ => return unsupported("A channel order"),
+        };
+        if let ImagePosition::At(_, _) = rules.get_position() {
+            return unsupported("A custom position");
+        }
+        if rules.get_offset() != 0 {
+            return unsupported("An offset");
+        }
+        if rules.get_region().is_some() {
+            return unsupported("A region");
+        }
+        if rules.get_permutation_seed().is_some() {
+            return unsupported("A permutation");
+        }
+        if !rules.get_pixel_selector().is_linear() {
+            return unsupported("A pixel selector");
+        }
+        if rules.get_spread() && rules.get_spread_stride() > 0 {
+            return unsupported("A spread stride");
+        }
+        let skip_c = match rules.get_step_by_n_pixels().try_into() {
+            Ok(skip_c) => skip_c,
+            Err(_) => return unsupported("A step this large"),
+        };
+
+        Ok(Self {
+            version: METADATA_VERSION,
+            lsb_c: rules.get_use_n_lsb() as u8,
+            skip_c,
+            channel,
+            position: rules.get_position().clone(),
+            spread: rules.get_spread(),
+            ecc: rules.get_ecc(),
+            bit_order: rules.get_bit_order(),
+        })
+    }
+
+    /// The encoded metadata describing the settings of `encoder`
+    pub fn encode_into(encoder: &ImageEncoder) -> Result<[u8; METADATA_SIZE], SteganographyError> {
+        Ok(Self::new(encoder)?.encode())
+    }
+
+    pub fn encode(&self) -> [u8; METADATA_SIZE] {
+        let mut flags = 0;
+        if self.spread {
+            flags |= FLAG_SPREAD;
+        }
+        if self.ecc {
+            flags |= FLAG_ECC;
+        }
+        if self.bit_order == BitOrder::MsbFirst {
+            flags |= FLAG_MSB_FIRST;
+        }
+
+        let mut bytes = [0u8; METADATA_SIZE];
+        bytes[0] = self.version;
+        bytes[1] = self.lsb_c;
+        bytes[2] = u8::from(self.channel.clone());
+        bytes[3] = position_tag(&self.position);
+        bytes[4..6].copy_from_slice(&self.skip_c.to_le_bytes());
+        bytes[6] = flags;
+        bytes[7] = check_byte(&bytes);
+        bytes
+    }
+
+    /// Reads metadata from its encoded form, failing if its version is not supported or
+    /// the bytes do not hold valid metadata
+    pub fn decode_from(bytes: &[u8; METADATA_SIZE]) -> Result<Metadata, SteganographyError> {
+        if bytes[0] != METADATA_VERSION {
+            return Err(SteganographyError::UnsupportedHeaderVersion(bytes[0]));
+        }
+
+        let expected = check_byte(bytes);
+        if bytes[7] != expected {
+            return Err(SteganographyError::ChecksumMismatch {
+                expected: expected as u32,
+                got: bytes[7] as u32,
+            });
+        }
+
+        let invalid = |field: &str, value: u8| {
+            SteganographyError::InvalidConfiguration(format!("Invalid {} {} in metadata", field, value))
+        };
+        let channel = match bytes[2] {
+            0 => RgbChannel::Red,
+            1 => RgbChannel::Green,
+            2 => RgbChannel::Blue,
+            3 => RgbChannel::Alpha,
+            4 => RgbChannel::Luma,
+            u8::MAX => RgbChannel::All,
+            other => return Err(invalid("channel", other)),
+        };
+        let position = match bytes[3] {
+            0 => ImagePosition::TopLeft,
+            1 => ImagePosition::TopRight,
+            2 => ImagePosition::BottomLeft,
+            3 => ImagePosition::BottomRight,
+            4 => ImagePosition::Center,
+            other => return Err(invalid("position", other)),
+        };
+
+        Ok(Self {
+            version: bytes[0],
+            lsb_c: bytes[1],
+            skip_c: u16::from_le_bytes([bytes[4], bytes[5]]),
+            channel,
+            position,
+            spread: bytes[6] & FLAG_SPREAD != 0,
+            ecc: bytes[6] & FLAG_ECC != 0,
+            bit_order: if bytes[6] & FLAG_MSB_FIRST != 0 {
+                BitOrder::MsbFirst
+            } else {
+                BitOrder::LsbFirst
+            },
+        })
+    }
+
+    /// Configures `rules` with these settings, skipping the pixels holding the metadata
+    pub fn apply_to<R>(&self, rules: &mut R)
+    where
+        R: ImageRules,
+    {
+        rules
+            .set_use_n_lsb(self.lsb_c as usize)
+            .set_step_by_n_pixels(self.skip_c as usize)
+            .set_use_channel(self.channel.clone())
+            .set_position(self.position.clone())
+            .set_spread(self.spread)
+            .set_ecc(self.ecc)
+            .set_bit_order(self.bit_order)
+            .set_offset(METADATA_PIXELS);
+    }
+}
+
+fn position_tag(position: &ImagePosition) -> u8 {
+    match position {
+        ImagePosition::TopLeft | ImagePosition::At(_, _) => 0,
+        ImagePosition::TopRight => 1,
+        ImagePosition::BottomLeft => 2,
+        ImagePosition::BottomRight => 3,
+        ImagePosition::Center => 4,
+    }
+}
+
+/// Detects images that hold no metadata, as random bytes rarely have a matching check byte
+fn check_byte(bytes: &[u8; METADATA_SIZE]) -> u8 {
+    bytes[..METADATA_SIZE - 1].iter().fold(0x5a, |check, byte| check.rotate_left(1) ^ byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Metadata, METADATA_VERSION};
+    use crate::{encoder::ImageEncoder, prelude::*};
+
+    #[test]
+    fn encode_decode() {
+        let encoder = ImageEncoder::default()
+            .with_lsb(2)
+            .with_step_by_n_pixels(300)
+            .with_channel(RgbChannel::Red)
+            .with_position(ImagePosition::Center)
+            .with_bit_order(BitOrder::MsbFirst);
+        let bytes = Metadata::encode_into(&encoder).unwrap();
+        assert_eq!(bytes[0], METADATA_VERSION);
+
+        let metadata = Metadata::decode_from(&bytes).unwrap();
+        assert_eq!(metadata, Metadata::new(&encoder).unwrap());
+        assert_eq!(metadata.skip_c, 300);
+        assert!(!metadata.spread);
+
+        let mut corrupted = bytes;
+        corrupted[4] ^= 1;
+        assert!(matches!(
+            Metadata::decode_from(&corrupted),
+            Err(SteganographyError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn unsupported_settings() {
+        for encoder in [
+            ImageEncoder::default().with_offset(3),
+            ImageEncoder::default().with_position(ImagePosition::At(1, 1)),
+            ImageEncoder::default().with_channel_order(vec![RgbChannel::Red, RgbChannel::Blue]),
+            ImageEncoder::default().with_password("seagul"),
+            ImageEncoder::default().with_spread(true).with_spread_stride(4),
+        ] {
+            assert!(matches!(
+                Metadata::encode_into(&encoder),
+                Err(SteganographyError::InvalidConfiguration(_))
+            ));
+        }
+    }
+}
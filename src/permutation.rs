@@ -0,0 +1,162 @@
+use crate::pixel_selectors::{region_dimensions, PixelSelector, Region, SelectorConfig};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Derives a 64 bit seed from `password` using the FNV-1a hash function
+pub(crate) fn password_seed(password: &str) -> u64 {
+    password.as_bytes().iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// The xoshiro256++ pseudo random number generator by Blackman and Vigna.
+/// Not suitable for cryptographic purposes, but fast and stable across platforms
+pub(crate) struct Xoshiro256PlusPlus {
+    state: [u64; 4],
+}
+
+impl Xoshiro256PlusPlus {
+    /// Creates a generator whose state is expanded from `seed` with SplitMix64,
+    /// as recommended by the authors
+    pub(crate) fn from_seed(seed: u64) -> Self {
+        let mut splitmix_state = seed;
+        let mut state = [0u64; 4];
+        for word in state.iter_mut() {
+            splitmix_state = splitmix_state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = splitmix_state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            *word = z ^ (z >> 31);
+        }
+
+        Self { state }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s0.wrapping_add(*s3).rotate_left(23).wrapping_add(*s0);
+        let t = *s1 << 17;
+
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+
+        result
+    }
+
+    /// A uniformly distributed value in `0..bound`. `bound` must not be zero
+    fn next_below(&mut self, bound: u64) -> u64 {
+        // Reject the values that would make the lowest results more likely
+        let zone = u64::MAX - (u64::MAX % bound);
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
+/// A permutation of `0..count`, shuffled with a Fisher-Yates shuffle driven by `seed`
+pub(crate) fn shuffled_sequence(count: usize, seed: u64) -> Vec<usize> {
+    let mut rng = Xoshiro256PlusPlus::from_seed(seed);
+    let mut sequence: Vec<usize> = (0..count).collect();
+    for i in (1..count).rev() {
+        let j = rng.next_below(i as u64 + 1) as usize;
+        sequence.swap(i, j);
+    }
+
+    sequence
+}
+
+/// The pixels of `area` chosen by `selector`, in order. When a `seed` is given the same
+/// pixels are visited in a pseudo random order that depends only on the seed
+pub(crate) fn pixel_order(
+    selector: &dyn PixelSelector,
+    area: Region,
+    config: &SelectorConfig,
+    seed: Option<u64>,
+) -> Box<dyn Iterator<Item = (u32, u32)>> {
+    let (left, top) = (area.0, area.1);
+    let selection: Box<dyn Iterator<Item = (u32, u32)>> = if left == 0 && top == 0 {
+        selector.select(region_dimensions(area), config)
+    } else {
+        Box::new(
+            selector
+                .select(region_dimensions(area), config)
+                .map(move |(x, y)| (x + left, y + top)),
+        )
+    };
+
+    match seed {
+        None => selection,
+        Some(seed) => {
+            let pixels: Vec<(u32, u32)> = selection.collect();
+            Box::new(
+                shuffled_sequence(pixels.len(), seed)
+                    .into_iter()
+                    .map(move |position| pixels[position]),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{password_seed, pixel_order, shuffled_sequence, Xoshiro256PlusPlus};
+    use crate::pixel_selectors::{LinearPixelSelector, SelectorConfig};
+
+    #[test]
+    fn fnv1a_seed() {
+        assert_eq!(password_seed(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(password_seed("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn xoshiro_reference_output() {
+        let mut rng = Xoshiro256PlusPlus { state: [1, 2, 3, 4] };
+        assert_eq!(rng.next_u64(), 41_943_041);
+        assert_eq!(rng.next_u64(), 58_720_359);
+        assert_eq!(rng.next_u64(), 3_588_806_011_781_223);
+    }
+
+    #[test]
+    fn shuffle_is_a_stable_permutation() {
+        let shuffled = shuffled_sequence(1000, password_seed("seagul"));
+        assert_eq!(shuffled, shuffled_sequence(1000, password_seed("seagul")));
+        assert_ne!(shuffled, shuffled_sequence(1000, password_seed("seagull")));
+        assert_ne!(shuffled, (0..1000).collect::<Vec<usize>>());
+
+        let mut sorted = shuffled.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..1000).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn permuted_order_visits_the_same_pixels() {
+        let config = SelectorConfig { start: 10, step: 3 };
+        let linear: Vec<(u32, u32)> =
+            pixel_order(&LinearPixelSelector, (0, 0, 10, 10), &config, None).collect();
+        let mut permuted: Vec<(u32, u32)> =
+            pixel_order(&LinearPixelSelector, (0, 0, 10, 10), &config, Some(42)).collect();
+        assert_ne!(linear, permuted);
+
+        permuted.sort_unstable_by_key(|(x, y)| (*y, *x));
+        assert_eq!(linear, permuted);
+
+        let config = SelectorConfig { start: 200, step: 1 };
+        assert_eq!(pixel_order(&LinearPixelSelector, (0, 0, 10, 10), &config, Some(42)).count(), 0);
+    }
+
+    #[test]
+    fn order_within_area() {
+        let config = SelectorConfig { start: 1, step: 2 };
+        let pixels: Vec<(u32, u32)> =
+            pixel_order(&LinearPixelSelector, (2, 3, 5, 5), &config, None).collect();
+        assert_eq!(pixels, vec![(3, 3), (2, 4), (4, 4)]);
+    }
+}
@@ -0,0 +1,188 @@
+use std::{fmt::Debug, io::Write};
+
+use flate2::{write::{ZlibDecoder, ZlibEncoder}, Compression};
+
+use crate::error::SteganographyError;
+
+/// A reversible transformation of the payload, applied before encoding it into an image
+/// and reversed after decoding it
+pub trait PayloadTransform: Debug {
+    /// Transforms the payload before it is encoded
+    fn apply(&self, data: Vec<u8>) -> Result<Vec<u8>, SteganographyError>;
+
+    /// Restores the payload transformed by `apply`
+    fn reverse(&self, data: Vec<u8>) -> Result<Vec<u8>, SteganographyError>;
+}
+
+/// Encodes the payload as standard base64, making it printable
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Base64Encode;
+
+impl PayloadTransform for Base64Encode {
+    fn apply(&self, data: Vec<u8>) -> Result<Vec<u8>, SteganographyError> {
+        Ok(base64::encode(data).into_bytes())
+    }
+
+    fn reverse(&self, data: Vec<u8>) -> Result<Vec<u8>, SteganographyError> {
+        base64::decode(data).map_err(|e| SteganographyError::TransformError(format!("Invalid base64: {}", e)))
+    }
+}
+
+/// Compresses the payload with zlib
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZlibCompress;
+
+impl PayloadTransform for ZlibCompress {
+    fn apply(&self, data: Vec<u8>) -> Result<Vec<u8>, SteganographyError> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&data)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn reverse(&self, data: Vec<u8>) -> Result<Vec<u8>, SteganographyError> {
+        let mut decoder = ZlibDecoder::new(Vec::new());
+        decoder
+            .write_all(&data)
+            .and_then(|_| decoder.finish())
+            .map_err(|e| SteganographyError::TransformError(format!("Invalid zlib stream: {}", e)))
+    }
+}
+
+/// XORs every byte of the payload with a fixed mask. This only hides the payload from
+/// a casual look, use the `crypto` feature to protect it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XorMask(pub u8);
+
+impl PayloadTransform for XorMask {
+    fn apply(&self, mut data: Vec<u8>) -> Result<Vec<u8>, SteganographyError> {
+        data.iter_mut().for_each(|byte| *byte ^= self.0);
+        Ok(data)
+    }
+
+    fn reverse(&self, data: Vec<u8>) -> Result<Vec<u8>, SteganographyError> {
+        self.apply(data)
+    }
+}
+
+/// A sequence of transforms applied, in order, to a payload before encoding it
+#[derive(Debug, Default)]
+pub struct EncoderPipeline {
+    transforms: Vec<Box<dyn PayloadTransform>>,
+}
+
+impl EncoderPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a transform to the pipeline
+    pub fn add_transform(&mut self, transform: Box<dyn PayloadTransform>) -> &mut Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    /// Consuming version of `add_transform`
+    pub fn with_transform<T>(mut self, transform: T) -> Self
+    where
+        T: PayloadTransform + 'static,
+    {
+        self.add_transform(Box::new(transform));
+        self
+    }
+
+    /// Runs every transform on `data`, in the order they were added
+    pub fn apply_all(&self, data: &[u8]) -> Result<Vec<u8>, SteganographyError> {
+        self.transforms
+            .iter()
+            .try_fold(data.to_vec(), |data, transform| transform.apply(data))
+    }
+}
+
+/// Reverses the transforms of an `EncoderPipeline` on a decoded payload.
+///
+/// Transforms are added in the same order as in the `EncoderPipeline`, and reversed
+/// starting from the last one
+#[derive(Debug, Default)]
+pub struct DecoderPipeline {
+    transforms: Vec<Box<dyn PayloadTransform>>,
+}
+
+impl DecoderPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a transform to the pipeline
+    pub fn add_transform(&mut self, transform: Box<dyn PayloadTransform>) -> &mut Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    /// Consuming version of `add_transform`
+    pub fn with_transform<T>(mut self, transform: T) -> Self
+    where
+        T: PayloadTransform + 'static,
+    {
+        self.add_transform(Box::new(transform));
+        self
+    }
+
+    /// Reverses every transform on `data`, from the last one added to the first
+    pub fn apply_all(&self, data: &[u8]) -> Result<Vec<u8>, SteganographyError> {
+        self.transforms
+            .iter()
+            .rev()
+            .try_fold(data.to_vec(), |data, transform| transform.reverse(data))
+    }
+}
+
+impl From<EncoderPipeline> for DecoderPipeline {
+    fn from(pipeline: EncoderPipeline) -> Self {
+        Self {
+            transforms: pipeline.transforms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Base64Encode, DecoderPipeline, EncoderPipeline, PayloadTransform, XorMask, ZlibCompress};
+    use crate::error::SteganographyError;
+
+    const VERSES: &[u8] = b"Midway upon the journey of our life, midway upon the journey of our life";
+
+    #[test]
+    fn transforms() {
+        assert_eq!(Base64Encode.apply(b"seagul".to_vec()).unwrap(), b"c2VhZ3Vs");
+        assert_eq!(XorMask(0xff).apply(vec![0x0f, 0x00]).unwrap(), [0xf0, 0xff]);
+
+        let compressed = ZlibCompress.apply(VERSES.to_vec()).unwrap();
+        assert!(compressed.len() < VERSES.len());
+        assert_eq!(ZlibCompress.reverse(compressed).unwrap(), VERSES);
+
+        assert!(matches!(
+            Base64Encode.reverse(b"not base64!".to_vec()),
+            Err(SteganographyError::TransformError(_))
+        ));
+        assert!(matches!(
+            ZlibCompress.reverse(VERSES.to_vec()),
+            Err(SteganographyError::TransformError(_))
+        ));
+    }
+
+    #[test]
+    fn pipeline_roundtrip() {
+        let pipeline = EncoderPipeline::new()
+            .with_transform(ZlibCompress)
+            .with_transform(XorMask(0x5a))
+            .with_transform(Base64Encode);
+        let transformed = pipeline.apply_all(VERSES).unwrap();
+        assert!(transformed.iter().all(|byte| byte.is_ascii_alphanumeric() || b"+/=".contains(byte)));
+
+        let decoder_pipeline = DecoderPipeline::from(pipeline);
+        assert_eq!(decoder_pipeline.apply_all(&transformed).unwrap(), VERSES);
+
+        let empty = EncoderPipeline::new();
+        assert_eq!(empty.apply_all(VERSES).unwrap(), VERSES);
+    }
+}
@@ -0,0 +1,318 @@
+use std::fmt::Debug;
+
+/// A rectangular area of an image, as `(x1, y1, x2, y2)`. Includes the pixels with
+/// `x1 <= x < x2` and `y1 <= y < y2`
+pub type Region = (u32, u32, u32, u32);
+
+/// The part of `region` that lies inside an image of size `dims`, or the whole
+/// image if there is no region
+pub(crate) fn clamp_region(dims: (u32, u32), region: Option<Region>) -> Region {
+    match region {
+        None => (0, 0, dims.0, dims.1),
+        Some((x1, y1, x2, y2)) => {
+            let x2 = x2.min(dims.0);
+            let y2 = y2.min(dims.1);
+            (x1.min(x2), y1.min(y2), x2, y2)
+        }
+    }
+}
+
+/// The `(width, height)` of `region`
+pub(crate) fn region_dimensions(region: Region) -> (u32, u32) {
+    (region.2 - region.0, region.3 - region.1)
+}
+
+/// Options shared by every `PixelSelector`
+#[derive(Debug, Clone)]
+pub struct SelectorConfig {
+    /// Number of pixels of the traversal to skip before selecting the first one
+    pub start: usize,
+
+    /// One pixel every `step` pixels of the traversal is selected
+    pub step: usize,
+}
+
+impl Default for SelectorConfig {
+    fn default() -> Self {
+        Self { start: 0, step: 1 }
+    }
+}
+
+/// Decides which pixels of an image hold data, and in which order they are visited.
+///
+/// Implementations traverse every pixel of the image exactly once, then apply the
+/// `start` and `step` of the given `SelectorConfig` to that traversal
+pub trait PixelSelector: PixelSelectorClone + Debug + Send + Sync {
+    /// The coordinates of the selected pixels of an image of size `dims`, in the order
+    /// they are visited
+    fn select(&self, dims: (u32, u32), config: &SelectorConfig) -> Box<dyn Iterator<Item = (u32, u32)>>;
+
+    /// Whether this selector visits pixels in their natural order, row by row. Encoders
+    /// can process such a selection in contiguous chunks
+    fn is_linear(&self) -> bool {
+        false
+    }
+}
+
+/// Allows cloning boxed selectors. Implemented for every `PixelSelector` that is also `Clone`
+pub trait PixelSelectorClone {
+    fn clone_box(&self) -> Box<dyn PixelSelector>;
+}
+
+impl<T> PixelSelectorClone for T
+where
+    T: PixelSelector + Clone + 'static,
+{
+    fn clone_box(&self) -> Box<dyn PixelSelector> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn PixelSelector> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Visits pixels row by row, from the top left corner
+#[derive(Debug, Clone, Default)]
+pub struct LinearPixelSelector;
+
+impl PixelSelector for LinearPixelSelector {
+    fn select(&self, dims: (u32, u32), config: &SelectorConfig) -> Box<dyn Iterator<Item = (u32, u32)>> {
+        let width = dims.0 as usize;
+        let total_pixels = width * dims.1 as usize;
+        Box::new(
+            (config.start.min(total_pixels)..total_pixels)
+                .step_by(config.step)
+                .map(move |index| ((index % width) as u32, (index / width) as u32)),
+        )
+    }
+
+    fn is_linear(&self) -> bool {
+        true
+    }
+}
+
+/// Visits pixels along the anti-diagonals of the image, from the top left corner.
+/// Each diagonal is walked from its top right end to its bottom left end
+#[derive(Debug, Clone, Default)]
+pub struct DiagonalPixelSelector;
+
+impl PixelSelector for DiagonalPixelSelector {
+    fn select(&self, dims: (u32, u32), config: &SelectorConfig) -> Box<dyn Iterator<Item = (u32, u32)>> {
+        Box::new(
+            diagonals(dims)
+                .flatten()
+                .skip(config.start)
+                .step_by(config.step),
+        )
+    }
+}
+
+/// Visits pixels along the anti-diagonals of the image, from the top left corner,
+/// alternating the direction of each diagonal like the JPEG zigzag scan
+#[derive(Debug, Clone, Default)]
+pub struct ZigZagPixelSelector;
+
+impl PixelSelector for ZigZagPixelSelector {
+    fn select(&self, dims: (u32, u32), config: &SelectorConfig) -> Box<dyn Iterator<Item = (u32, u32)>> {
+        Box::new(
+            diagonals(dims)
+                .enumerate()
+                .flat_map(|(index, diagonal)| {
+                    let diagonal: Vec<(u32, u32)> = diagonal.collect();
+                    if index % 2 == 0 {
+                        diagonal.into_iter().rev().collect::<Vec<_>>()
+                    } else {
+                        diagonal
+                    }
+                })
+                .skip(config.start)
+                .step_by(config.step),
+        )
+    }
+}
+
+/// Visits pixels following an Archimedean spiral, starting from the center of the
+/// image and moving outwards clockwise, one pixel apart on each turn
+#[derive(Debug, Clone, Default)]
+pub struct SpiralPixelSelector;
+
+impl PixelSelector for SpiralPixelSelector {
+    fn select(&self, dims: (u32, u32), config: &SelectorConfig) -> Box<dyn Iterator<Item = (u32, u32)>> {
+        use std::f64::consts::PI;
+
+        let center = ((dims.0 as f64 - 1.0) / 2.0, (dims.1 as f64 - 1.0) / 2.0);
+
+        // Each pixel is placed on the spiral r = θ / 2π at the turn closest to its radius
+        let mut pixels: Vec<(f64, u32, u32)> = (0..dims.1)
+            .flat_map(|y| (0..dims.0).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let dx = x as f64 - center.0;
+                let dy = y as f64 - center.1;
+                let radius = (dx * dx + dy * dy).sqrt();
+                let angle = dy.atan2(dx).rem_euclid(2.0 * PI);
+                let turn = (radius - angle / (2.0 * PI)).round().max(0.0);
+                (angle + 2.0 * PI * turn, x, y)
+            })
+            .collect();
+        pixels.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Box::new(
+            pixels
+                .into_iter()
+                .map(|(_, x, y)| (x, y))
+                .skip(config.start)
+                .step_by(config.step),
+        )
+    }
+}
+
+/// Visits pixels at intervals following the Fibonacci sequence (1, 1, 2, 3, 5, 8...) over
+/// the pixels in row order, wrapping around the end of the image. When an interval lands on
+/// a pixel already visited, the next pixel not visited yet in row order is taken instead,
+/// so that the pixels are spread non uniformly while each one is still visited once
+#[derive(Debug, Clone, Default)]
+pub struct FibonacciPixelSelector;
+
+impl PixelSelector for FibonacciPixelSelector {
+    fn select(&self, dims: (u32, u32), config: &SelectorConfig) -> Box<dyn Iterator<Item = (u32, u32)>> {
+        let width = dims.0 as usize;
+        let total_pixels = width * dims.1 as usize;
+
+        // `next_free[index]` leads to the first pixel not visited yet from `index` on, the
+        // paths being shortened as they are followed
+        let mut next_free: Vec<usize> = (0..total_pixels).collect();
+        let mut indices = Vec::with_capacity(total_pixels);
+        let (mut interval, mut next_interval) = (1 % total_pixels.max(1), 1 % total_pixels.max(1));
+        let mut index = 0;
+        for _ in 0..total_pixels {
+            index = first_free(&mut next_free, index);
+            indices.push(index);
+            next_free[index] = (index + 1) % total_pixels;
+
+            index = (index + interval) % total_pixels;
+            let following = (interval + next_interval) % total_pixels;
+            interval = next_interval;
+            next_interval = following;
+        }
+
+        Box::new(
+            indices
+                .into_iter()
+                .map(move |index| ((index % width) as u32, (index / width) as u32))
+                .skip(config.start)
+                .step_by(config.step),
+        )
+    }
+}
+
+/// Follows `next_free` from `index` to the first pixel pointing to itself, making every
+/// pixel along the way point to it directly
+fn first_free(next_free: &mut [usize], index: usize) -> usize {
+    let mut free = index;
+    while next_free[free] != free {
+        free = next_free[free];
+    }
+
+    let mut current = index;
+    while next_free[current] != free {
+        let next = next_free[current];
+        next_free[current] = free;
+        current = next;
+    }
+
+    free
+}
+
+/// The anti-diagonals of an image of size `dims`, each one from its top right end
+fn diagonals(dims: (u32, u32)) -> impl Iterator<Item = impl Iterator<Item = (u32, u32)>> {
+    let (width, height) = (dims.0 as u64, dims.1 as u64);
+    let count = if width == 0 || height == 0 { 0 } else { width + height - 1 };
+    (0..count).map(move |sum| {
+        let first_x = sum.saturating_sub(height - 1);
+        let last_x = sum.min(width - 1);
+        (first_x..=last_x)
+            .rev()
+            .map(move |x| (x as u32, (sum - x) as u32))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DiagonalPixelSelector, FibonacciPixelSelector, LinearPixelSelector, PixelSelector,
+        SelectorConfig, SpiralPixelSelector, ZigZagPixelSelector,
+    };
+
+    fn select_all(selector: &dyn PixelSelector, dims: (u32, u32)) -> Vec<(u32, u32)> {
+        selector.select(dims, &SelectorConfig::default()).collect()
+    }
+
+    #[test]
+    fn selectors_visit_every_pixel_once() {
+        let selectors: Vec<Box<dyn PixelSelector>> = vec![
+            Box::new(LinearPixelSelector),
+            Box::new(DiagonalPixelSelector),
+            Box::new(ZigZagPixelSelector),
+            Box::new(SpiralPixelSelector),
+            Box::new(FibonacciPixelSelector),
+        ];
+
+        for selector in selectors {
+            for dims in [(7, 5), (5, 7), (1, 9), (16, 16), (0, 4)] {
+                let mut pixels = select_all(selector.as_ref(), dims);
+                pixels.sort_unstable_by_key(|(x, y)| (*y, *x));
+                assert_eq!(pixels, select_all(&LinearPixelSelector, dims), "{:?}", selector);
+            }
+        }
+    }
+
+    #[test]
+    fn start_and_step() {
+        let config = SelectorConfig { start: 2, step: 3 };
+        let pixels: Vec<(u32, u32)> = LinearPixelSelector.select((4, 3), &config).collect();
+        assert_eq!(pixels, vec![(2, 0), (1, 1), (0, 2), (3, 2)]);
+    }
+
+    #[test]
+    fn diagonal_and_zigzag_order() {
+        assert_eq!(
+            select_all(&DiagonalPixelSelector, (3, 2)),
+            vec![(0, 0), (1, 0), (0, 1), (2, 0), (1, 1), (2, 1)]
+        );
+        assert_eq!(
+            select_all(&ZigZagPixelSelector, (3, 3)),
+            vec![(0, 0), (1, 0), (0, 1), (0, 2), (1, 1), (2, 0), (2, 1), (1, 2), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn fibonacci_intervals() {
+        let pixels = select_all(&FibonacciPixelSelector, (10, 10));
+        // Pixels 0, 1, 2, 4, 7, 12, 20, 33, 54, 88 then 143 wrapping to 43
+        assert_eq!(
+            pixels[..11],
+            [(0, 0), (1, 0), (2, 0), (4, 0), (7, 0), (2, 1), (0, 2), (3, 3), (4, 5), (8, 8), (3, 4)]
+        );
+
+        // Pixel 1 is taken when landing on it again, so pixel 2 is visited instead
+        let pixels: Vec<(u32, u32)> = select_all(&FibonacciPixelSelector, (3, 1));
+        assert_eq!(pixels, vec![(0, 0), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn spiral_moves_outwards() {
+        let pixels = select_all(&SpiralPixelSelector, (9, 9));
+        assert_eq!(pixels[0], (4, 4));
+
+        // Each pixel is at most one turn closer to the center than the ones before it
+        let distance = |(x, y): (u32, u32)| (x as f64 - 4.0).hypot(y as f64 - 4.0);
+        let mut farthest = 0.0_f64;
+        for pixel in pixels {
+            assert!(distance(pixel) > farthest - 1.0);
+            farthest = farthest.max(distance(pixel));
+        }
+    }
+}
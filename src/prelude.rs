@@ -1,9 +1,69 @@
-use std::ops::Deref;
+use std::{fmt::Display, ops::{BitOr, Deref}, path::Path, str::FromStr};
 
-use image::Primitive;
+use image::{DynamicImage, GenericImageView, Primitive};
 
+pub use crate::error::SteganographyError;
+pub use crate::pixel_selectors::{PixelSelector, Region};
+
+/// An image loaded to be inspected before encoding data into it, or decoding data from it
+#[derive(Debug, Clone)]
 pub struct Image {
     inner: image::DynamicImage,
+    format: Option<image::ImageFormat>,
+}
+
+impl Image {
+    /// Opens the image stored at `path`, guessing its format from its content
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SteganographyError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("Cannot open {}: {}", path.display(), e))
+        })?;
+        Self::from_bytes(&data)
+    }
+
+    /// Loads an image from its encoded bytes, guessing its format from their content
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        let format = image::guess_format(data)?;
+        let inner = image::load_from_memory_with_format(data, format)?;
+        Ok(Self {
+            inner,
+            format: Some(format),
+        })
+    }
+
+    /// The `(width, height)` of the image, in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.inner.width(), self.inner.height())
+    }
+
+    /// The format the image was loaded from, if it was loaded from encoded bytes
+    pub fn format(&self) -> Option<image::ImageFormat> {
+        self.format
+    }
+
+    /// Whether the image was loaded from a lossy format, meaning it was likely altered
+    /// by compression and may not hold data encoded before it was saved
+    pub fn is_lossy(&self) -> bool {
+        matches!(
+            self.format,
+            Some(image::ImageFormat::Jpeg) | Some(image::ImageFormat::WebP)
+        )
+    }
+
+    /// Consumes the wrapper, returning the underlying image
+    pub fn into_inner(self) -> DynamicImage {
+        self.inner
+    }
+}
+
+impl From<DynamicImage> for Image {
+    fn from(inner: DynamicImage) -> Self {
+        Self {
+            inner,
+            format: None,
+        }
+    }
 }
 
 impl Deref for Image {
@@ -14,20 +74,89 @@ impl Deref for Image {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ImagePosition {
     TopLeft,
     TopRight,
     BottomLeft,
     BottomRight,
+    /// The pixel at column `width / 2` and row `height / 2`, rounded down. Encoding starts
+    /// there and goes on row by row, so only the lower half of the image holds data
     Center,
+    /// The pixel at column `x` and row `y`, as `At(x, y)`
     At(u32, u32),
 }
 
+impl ImagePosition {
+    /// The number of pixels to skip, in linear traversal order, to reach this position
+    /// on an image with the given `(width, height)` dimensions
+    pub fn pixel_offset(&self, image_dimensions: (u32, u32)) -> usize {
+        match self {
+            ImagePosition::TopLeft => 0,
+            ImagePosition::TopRight => image_dimensions.0 as usize,
+            ImagePosition::BottomLeft => image_dimensions.1 as usize,
+            ImagePosition::BottomRight => {
+                image_dimensions.0 as usize + image_dimensions.1 as usize
+            }
+            ImagePosition::Center => {
+                let (width, height) = (image_dimensions.0 as usize, image_dimensions.1 as usize);
+                height / 2 * width + width / 2
+            }
+            ImagePosition::At(x, y) => *y as usize * image_dimensions.0 as usize + *x as usize,
+        }
+    }
+}
+
+impl Display for ImagePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImagePosition::TopLeft => f.write_str("top-left"),
+            ImagePosition::TopRight => f.write_str("top-right"),
+            ImagePosition::BottomLeft => f.write_str("bottom-left"),
+            ImagePosition::BottomRight => f.write_str("bottom-right"),
+            ImagePosition::Center => f.write_str("center"),
+            ImagePosition::At(x, y) => write!(f, "at({}, {})", x, y),
+        }
+    }
+}
+
+impl FromStr for ImagePosition {
+    type Err = SteganographyError;
+
+    /// Parses the names produced by `Display`, such as `top-left` or `at(3, 4)`
+    fn from_str(repr: &str) -> Result<Self, Self::Err> {
+        let invalid = || SteganographyError::InvalidConfiguration(format!("Unknown position \"{}\"", repr));
+        match repr {
+            "top-left" => Ok(ImagePosition::TopLeft),
+            "top-right" => Ok(ImagePosition::TopRight),
+            "bottom-left" => Ok(ImagePosition::BottomLeft),
+            "bottom-right" => Ok(ImagePosition::BottomRight),
+            "center" => Ok(ImagePosition::Center),
+            _ => {
+                let coordinates = repr
+                    .strip_prefix("at(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .and_then(|rest| rest.split_once(','))
+                    .ok_or_else(invalid)?;
+                let x = coordinates.0.trim().parse().map_err(|_| invalid())?;
+                let y = coordinates.1.trim().parse().map_err(|_| invalid())?;
+                Ok(ImagePosition::At(x, y))
+            }
+        }
+    }
+}
+
 /// Describes an RGB color
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Rgb<T>(T, T, T);
 
+impl<T: Display> Display for Rgb<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rgb({}, {}, {})", self.0, self.1, self.2)
+    }
+}
+
 impl<T: Primitive> From<image::Rgb<T>> for Rgb<T> {
     fn from(color: image::Rgb<T>) -> Self {
         let c = color.0;
@@ -41,23 +170,57 @@ impl<T: Primitive> From<[T; 3]> for Rgb<T> {
     }
 }
 
-impl<T: Primitive> Into<image::Rgb<T>> for Rgb<T> {
-    fn into(self) -> image::Rgb<T> {
-        image::Rgb([self.0, self.1, self.2])
+impl<T: Primitive> From<Rgb<T>> for image::Rgb<T> {
+    fn from(color: Rgb<T>) -> Self {
+        image::Rgb([color.0, color.1, color.2])
     }
 }
 
 /// Represents a color channel in a pixel
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RgbChannel {
     Red,
     Green,
     Blue,
+    /// The transparency channel, only available on images that have one
+    Alpha,
+    /// Red, green and blue, used in this order. As it does not stand for a single
+    /// channel, it converts to `u8::MAX` and `usize::MAX`
+    All,
+    /// The only channel of grayscale images, which is used instead of the color channels
+    /// by default when the source image is grayscale. It converts to `4` as `u8` and to
+    /// the index `0` of grayscale pixels as `usize`
+    Luma,
+}
+
+impl RgbChannel {
+    /// The indices of the pixel channels this channel stands for
+    pub fn indices(&self) -> &'static [usize] {
+        match self {
+            RgbChannel::Red => &[0],
+            RgbChannel::Green => &[1],
+            RgbChannel::Blue => &[2],
+            RgbChannel::Alpha => &[3],
+            RgbChannel::All => &[0, 1, 2],
+            RgbChannel::Luma => &[0],
+        }
+    }
+}
+
+/// The indices of the pixel channels used by `order`, in order, with `RgbChannel::All` expanded
+pub(crate) fn channel_indices(order: &[RgbChannel]) -> Vec<usize> {
+    order.iter().flat_map(RgbChannel::indices).copied().collect()
+}
+
+/// If `img` is stored with a single luminance channel, which `RgbChannel::Luma` stands for
+pub(crate) fn is_grayscale(img: &DynamicImage) -> bool {
+    matches!(img, DynamicImage::ImageLuma8(_) | DynamicImage::ImageLuma16(_))
 }
 
 impl AsRef<RgbChannel> for RgbChannel {
     fn as_ref(&self) -> &RgbChannel {
-        &self
+        self
     }
 }
 
@@ -67,17 +230,54 @@ impl From<&str> for RgbChannel {
             "red" | "r" => RgbChannel::Red,
             "green" | "g" => RgbChannel::Green,
             "blue" | "b" => RgbChannel::Blue,
+            "alpha" | "a" => RgbChannel::Alpha,
+            "all" | "rgb" => RgbChannel::All,
+            "luma" | "l" => RgbChannel::Luma,
             _ => RgbChannel::Blue,
         }
     }
 }
 
+impl FromStr for RgbChannel {
+    type Err = SteganographyError;
+
+    /// Parses the names accepted by `From<&str>`, failing on unknown ones instead of
+    /// falling back to `RgbChannel::Blue`
+    fn from_str(repr: &str) -> Result<Self, Self::Err> {
+        match repr {
+            "red" | "r" | "green" | "g" | "blue" | "b" | "alpha" | "a" | "all" | "rgb" | "luma" | "l" => {
+                Ok(RgbChannel::from(repr))
+            }
+            _ => Err(SteganographyError::InvalidConfiguration(format!(
+                "Unknown channel \"{}\"",
+                repr
+            ))),
+        }
+    }
+}
+
+impl Display for RgbChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RgbChannel::Red => "red",
+            RgbChannel::Green => "green",
+            RgbChannel::Blue => "blue",
+            RgbChannel::Alpha => "alpha",
+            RgbChannel::All => "all",
+            RgbChannel::Luma => "luma",
+        })
+    }
+}
+
 impl From<RgbChannel> for u8 {
     fn from(val: RgbChannel) -> Self {
         match val {
             RgbChannel::Red => { 0 }
             RgbChannel::Green => { 1 }
             RgbChannel::Blue => { 2 }
+            RgbChannel::Alpha => { 3 }
+            RgbChannel::All => { u8::MAX }
+            RgbChannel::Luma => { 4 }
         }
     }
 }
@@ -88,6 +288,9 @@ impl From<RgbChannel> for usize {
             RgbChannel::Red => { 0 }
             RgbChannel::Green => { 1 }
             RgbChannel::Blue => { 2 }
+            RgbChannel::Alpha => { 3 }
+            RgbChannel::All => { usize::MAX }
+            RgbChannel::Luma => { 0 }
         }
     }
 }
@@ -98,24 +301,134 @@ impl From<&RgbChannel> for usize {
             RgbChannel::Red => { 0 }
             RgbChannel::Green => { 1 }
             RgbChannel::Blue => { 2 }
+            RgbChannel::Alpha => { 3 }
+            RgbChannel::All => { usize::MAX }
+            RgbChannel::Luma => { 0 }
+        }
+    }
+}
+
+/// A combination of the red, green and blue channels, where bit 0 stands for red, bit 1
+/// for green and bit 2 for blue. The other bits are ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelMask(pub u8);
+
+impl ChannelMask {
+    pub const RED: ChannelMask = ChannelMask(0b001);
+    pub const GREEN: ChannelMask = ChannelMask(0b010);
+    pub const BLUE: ChannelMask = ChannelMask(0b100);
+    pub const RG: ChannelMask = ChannelMask(0b011);
+    pub const ALL: ChannelMask = ChannelMask(0b111);
+
+    /// The channels enabled by this mask, in red, green, blue order
+    pub fn channels(&self) -> Vec<RgbChannel> {
+        [RgbChannel::Red, RgbChannel::Green, RgbChannel::Blue]
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| self.0 & (1 << bit) != 0)
+            .map(|(_, channel)| channel.clone())
+            .collect()
+    }
+}
+
+impl BitOr for ChannelMask {
+    type Output = ChannelMask;
+
+    fn bitor(self, other: ChannelMask) -> ChannelMask {
+        ChannelMask(self.0 | other.0)
+    }
+}
+
+/// The order in which the bits of each payload byte are stored
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitOrder {
+    /// The least significant bit of each byte is stored first
+    LsbFirst,
+    /// The most significant bit of each byte is stored first
+    MsbFirst,
+}
+
+impl BitOrder {
+    /// Rearranges the bits of `byte` so that, read starting from the least significant one,
+    /// they follow this order. Applying it twice gives back the original byte
+    pub(crate) fn arrange(&self, byte: u8) -> u8 {
+        match self {
+            BitOrder::LsbFirst => byte,
+            BitOrder::MsbFirst => byte.reverse_bits(),
+        }
+    }
+}
+
+impl FromStr for BitOrder {
+    type Err = SteganographyError;
+
+    /// Parses `lsb-first` or `msb-first`
+    fn from_str(repr: &str) -> Result<Self, Self::Err> {
+        match repr {
+            "lsb-first" => Ok(BitOrder::LsbFirst),
+            "msb-first" => Ok(BitOrder::MsbFirst),
+            _ => Err(SteganographyError::InvalidConfiguration(format!(
+                "Unknown bit order \"{}\"",
+                repr
+            ))),
         }
     }
 }
 
 /// Enumerates supported image formats
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ImageFormat {
     Jpeg,
     Png,
     Bmp
 }
 
+impl ImageFormat {
+    /// Whether the format stores pixels exactly. Data encoded into an image saved
+    /// with a lossy format can not be recovered
+    pub fn is_lossless(&self) -> bool {
+        match self {
+            ImageFormat::Jpeg => false,
+            ImageFormat::Png | ImageFormat::Bmp => true,
+        }
+    }
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::Png => "PNG",
+            ImageFormat::Bmp => "BMP",
+        })
+    }
+}
+
 impl From<image::ImageFormat> for ImageFormat {
     fn from(f: image::ImageFormat) -> Self {
-        f.into()
+        match f {
+            image::ImageFormat::Png => ImageFormat::Png,
+            image::ImageFormat::Jpeg => ImageFormat::Jpeg,
+            image::ImageFormat::Bmp => ImageFormat::Bmp,
+            other => panic!("Unsupported output image format: {:?}", other),
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+impl From<ImageFormat> for image::ImageFormat {
+    fn from(f: ImageFormat) -> Self {
+        match f {
+            ImageFormat::Png => image::ImageFormat::Png,
+            ImageFormat::Jpeg => image::ImageFormat::Jpeg,
+            ImageFormat::Bmp => image::ImageFormat::Bmp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompressionType {
     /// Default compression level
     Default,
@@ -154,7 +467,8 @@ impl From<CompressionType> for image::png::CompressionType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FilterType {
     /// No processing done, best used for low bit depth greyscale or data with a
     /// low color count
@@ -199,47 +513,345 @@ pub trait ImageRules {
     /// Sets the number of least significative bits to edit for each
     /// byte in the source buffer. The higher the value gets
     /// the least space is required to encode data into the source, but the resulting
-    /// image will get noticeably different from the original
+    /// image will get noticeably different from the original. Values not dividing 8 are
+    /// supported, a byte continuing in the next channel where the previous one ends
     fn set_use_n_lsb(&mut self, n: usize) -> &mut Self;
 
-    /// Skip the first `offset` bytes in the source buffer
+    /// Skips the first `offset` pixels of the traversal, counted from the configured
+    /// position. The offset is the index of the first pixel used, in absolute pixels: it is
+    /// applied before `set_step_by_n_pixels`, and is not multiplied by the step
     fn set_offset(&mut self, offset: usize) -> &mut Self;
 
-    /// When encoding data, `n` pixels will be skipped after each edited pixel
+    /// Uses one pixel every `n` pixels of the traversal, `1` using every pixel. Values below
+    /// `1` are taken as `1`.
+    ///
+    /// The step starts from the offset pixel: an offset of `10` and a step of `3` use
+    /// pixels `10`, `13`, `16` and so on
     fn set_step_by_n_pixels(&mut self, n: usize) -> &mut Self;
 
     /// Specifies wich color channel will be the one used to store information bits.
+    /// This is equivalent to a channel order made of `channel` alone.
     fn set_use_channel(&mut self, channel: RgbChannel) -> &mut Self;
 
-    /// If the message is spread across the image
+    /// Specifies the color channels used to store information bits, in the order they
+    /// are written. Channels are cycled for each pixel, using `n` least significative
+    /// bits (see `set_use_n_lsb`) of each one before moving to the next pixel.
+    fn set_channel_order(&mut self, order: Vec<RgbChannel>) -> &mut Self;
+
+    /// Uses the channels enabled by `mask`, in red, green, blue order. This is equivalent
+    /// to the channel order returned by `ChannelMask::channels`
+    fn set_channel_mask(&mut self, mask: ChannelMask) -> &mut Self {
+        self.set_channel_order(mask.channels())
+    }
+
+    /// If the message is spread across the image. A single copy of the message is stored,
+    /// using one selected pixel every `spread_stride` so that it reaches the end of the image.
+    /// Decoding spread data requires its length, unless the stride is set explicitly
     fn set_spread(&mut self, value: bool) -> &mut Self;
 
-    /// Sets a byte value to use for message padding across the image
-    fn set_padding(&mut self, value: &str) -> &mut Self;
+    /// Sets the number of selected pixels between two pixels holding data when spreading.
+    /// The default, `0`, computes it from the length of the message and the pixels available
+    fn set_spread_stride(&mut self, stride: usize) -> &mut Self;
 
-    /// Starting position for the encoding. Irrelevant if spread is true
+    /// Sets a string whose bits are repeated to fill the pixels not holding any message data,
+    /// both before the starting position and after the end of the message.
+    /// When spreading, only the pixels in the stride after the message are padded
+    fn set_padding(&mut self, value: impl AsRef<str>) -> &mut Self;
+
+    /// Starting position for the encoding
     fn set_position(&mut self, value: ImagePosition) -> &mut Self;
 
+    /// Sets the seed of the pseudo random order in which pixels are visited.
+    /// `None` visits pixels in their natural order
+    fn set_permutation_seed(&mut self, seed: Option<u64>) -> &mut Self;
+
+    /// Visits pixels in a pseudo random order derived from `password`, instead of
+    /// their natural order. The same password must be used to decode the data
+    fn set_password(&mut self, password: &str) -> &mut Self {
+        self.set_permutation_seed(Some(crate::permutation::password_seed(password)))
+    }
+
+    /// Restricts encoding to the rectangle from `(x1, y1)` included to `(x2, y2)` excluded,
+    /// leaving the rest of the image untouched. Positions and offsets are relative to it
+    fn set_region(&mut self, x1: u32, y1: u32, x2: u32, y2: u32) -> &mut Self;
+
+    /// Sets the order in which the bits of each payload byte are stored.
+    /// The default is `BitOrder::LsbFirst`
+    fn set_bit_order(&mut self, order: BitOrder) -> &mut Self;
+
+    /// Sets whether the data is protected by a Hamming(7,4) error correction code,
+    /// which doubles its size. See the `ecc` module
+    fn set_ecc(&mut self, enabled: bool) -> &mut Self;
+
+    /// Sets the strategy choosing which pixels hold data and in which order.
+    /// The default is `LinearPixelSelector`
+    fn set_pixel_selector(&mut self, selector: Box<dyn PixelSelector>) -> &mut Self;
+
     /// Sets the number of least significative bits to edit for each
     /// byte in the source buffer. The higher the value gets
     /// the least space is required to encode data into the source, but the resulting
     /// image will get noticeably different from the original
     fn get_use_n_lsb(&self) -> usize;
 
-    /// Skip the first `offset` bytes in the source buffer
+    /// The number of pixels skipped before the first one used, see `set_offset`
     fn get_offset(&self) -> usize;
 
-    /// When encoding data, one pixdel each `n` pixels will be used to encode.
+    /// When encoding data, one pixel each `n` pixels will be used to encode.
     ///
     /// For example: using `1` means skipping no pixels
     fn get_step_by_n_pixels(&self) -> usize;
 
     /// Specifies wich color channel will be the one used to store information bits.
+    /// When multiple channels are in use, this is the first one in the channel order.
     fn get_use_channel(&self) -> &RgbChannel;
 
+    /// The color channels used to store information bits, in the order they are written
+    fn get_channel_order(&self) -> &[RgbChannel];
+
     /// If the message is spread across the image
     fn get_spread(&self) -> bool;
 
-    /// Starting position for the encoding. Irrelevant if spread is true
+    /// The number of selected pixels between two pixels holding data when spreading,
+    /// or `0` if it is computed from the length of the message
+    fn get_spread_stride(&self) -> usize;
+
+    /// Starting position for the encoding
     fn get_position(&self) -> &ImagePosition;
+
+    /// The string whose bits are used to pad the pixels not holding any message data
+    fn get_padding(&self) -> Option<&str>;
+
+    /// The seed of the pseudo random order in which pixels are visited, if any
+    fn get_permutation_seed(&self) -> Option<u64>;
+
+    /// The strategy choosing which pixels hold data and in which order
+    fn get_pixel_selector(&self) -> &dyn PixelSelector;
+
+    /// The order in which the bits of each payload byte are stored
+    fn get_bit_order(&self) -> BitOrder;
+
+    /// Whether the data is protected by an error correction code
+    fn get_ecc(&self) -> bool;
+
+    /// The rectangle encoding is restricted to, as `(x1, y1, x2, y2)`, if any
+    fn get_region(&self) -> Option<Region>;
+
+    /// Consuming version of `set_use_n_lsb`
+    fn with_lsb(mut self, n: usize) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_use_n_lsb(n);
+        self
+    }
+
+    /// Consuming version of `set_offset`
+    fn with_offset(mut self, offset: usize) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_offset(offset);
+        self
+    }
+
+    /// Consuming version of `set_step_by_n_pixels`
+    fn with_step_by_n_pixels(mut self, n: usize) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_step_by_n_pixels(n);
+        self
+    }
+
+    /// Consuming version of `set_use_channel`
+    fn with_channel(mut self, channel: RgbChannel) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_use_channel(channel);
+        self
+    }
+
+    /// Consuming version of `set_channel_order`
+    fn with_channel_order(mut self, order: Vec<RgbChannel>) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_channel_order(order);
+        self
+    }
+
+    /// Consuming version of `set_channel_mask`
+    fn with_channel_mask(mut self, mask: ChannelMask) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_channel_mask(mask);
+        self
+    }
+
+    /// Consuming version of `set_spread`
+    fn with_spread(mut self, value: bool) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_spread(value);
+        self
+    }
+
+    /// Consuming version of `set_spread_stride`
+    fn with_spread_stride(mut self, stride: usize) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_spread_stride(stride);
+        self
+    }
+
+    /// Consuming version of `set_padding`
+    fn with_padding(mut self, value: impl AsRef<str>) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_padding(value);
+        self
+    }
+
+    /// Consuming version of `set_position`
+    fn with_position(mut self, value: ImagePosition) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_position(value);
+        self
+    }
+
+    /// Consuming version of `set_region`
+    fn with_region(mut self, x1: u32, y1: u32, x2: u32, y2: u32) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_region(x1, y1, x2, y2);
+        self
+    }
+
+    /// Consuming version of `set_bit_order`
+    fn with_bit_order(mut self, order: BitOrder) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_bit_order(order);
+        self
+    }
+
+    /// Consuming version of `set_ecc(true)`
+    fn with_ecc(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_ecc(true);
+        self
+    }
+
+    /// Consuming version of `set_pixel_selector`
+    fn with_pixel_selector(mut self, selector: Box<dyn PixelSelector>) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_pixel_selector(selector);
+        self
+    }
+
+    /// Consuming version of `set_password`
+    fn with_password(mut self, password: &str) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_password(password);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GenericImageView;
+
+    use super::{ChannelMask, Image, ImageFormat, ImagePosition, Rgb, RgbChannel};
+    use crate::error::SteganographyError;
+
+    #[test]
+    fn display() {
+        assert_eq!(Rgb::from([255u8, 0, 12]).to_string(), "rgb(255, 0, 12)");
+        assert_eq!(ImagePosition::BottomLeft.to_string(), "bottom-left");
+        assert_eq!(ImagePosition::At(10, 20).to_string(), "at(10, 20)");
+        assert_eq!(ImageFormat::Jpeg.to_string(), "JPEG");
+    }
+
+    #[test]
+    fn format_conversion() {
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Bmp] {
+            assert_eq!(ImageFormat::from(image::ImageFormat::from(format)), format);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported output image format")]
+    fn unsupported_format() {
+        let _ = ImageFormat::from(image::ImageFormat::Gif);
+    }
+
+    #[test]
+    fn channel_masks() {
+        assert_eq!(ChannelMask::RG.channels(), vec![RgbChannel::Red, RgbChannel::Green]);
+        assert_eq!(
+            ChannelMask::ALL.channels(),
+            vec![RgbChannel::Red, RgbChannel::Green, RgbChannel::Blue]
+        );
+        assert_eq!(ChannelMask::BLUE | ChannelMask::RED, ChannelMask(0b101));
+        assert_eq!(ChannelMask(0b1111_0101).channels(), vec![RgbChannel::Red, RgbChannel::Blue]);
+        assert!(ChannelMask(0).channels().is_empty());
+    }
+
+    #[test]
+    fn channel_parsing() {
+        for channel in [
+            RgbChannel::Red,
+            RgbChannel::Green,
+            RgbChannel::Blue,
+            RgbChannel::Alpha,
+            RgbChannel::All,
+            RgbChannel::Luma,
+        ] {
+            assert_eq!(channel.to_string().parse::<RgbChannel>().unwrap(), channel);
+        }
+
+        assert_eq!("g".parse::<RgbChannel>().unwrap(), RgbChannel::Green);
+        assert!("purple".parse::<RgbChannel>().is_err());
+    }
+
+    #[test]
+    fn image_loading() {
+        let image = Image::open("tests/images/red_panda.jpg").unwrap();
+        assert_eq!(image.format(), Some(image::ImageFormat::Jpeg));
+        assert!(image.is_lossy());
+        assert_eq!(image.dimensions(), (image.width(), image.height()));
+
+        let mut png = vec![];
+        image
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let image = Image::from_bytes(&png).unwrap();
+        assert_eq!(image.format(), Some(image::ImageFormat::Png));
+        assert!(!image.is_lossy());
+
+        assert!(matches!(
+            Image::from_bytes(b"not an image"),
+            Err(SteganographyError::ImageLoadError(_))
+        ));
+        assert!(matches!(
+            Image::open("tests/images/missing.png"),
+            Err(SteganographyError::IoError(_))
+        ));
+        assert_eq!(Image::from(image.into_inner()).format(), None);
+    }
 }
@@ -0,0 +1,115 @@
+use image::{DynamicImage, GenericImageView};
+
+use crate::{encoder::EncodedImage, prelude::Rgb};
+
+/// The color of the borders drawn by `AnnotationStyle::Outline`
+const OUTLINE_COLOR: [u8; 3] = [255, 0, 0];
+
+/// How `annotate_encoded_pixels` highlights the pixels altered by the encoding
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationStyle {
+    /// Draws a 1 pixel red border around each altered pixel, leaving the pixel itself visible
+    Outline,
+    /// Replaces each altered pixel with a solid color
+    Fill(Rgb<u8>),
+    /// Blends each altered pixel with `color`, `alpha` ranging from `0.0` (the pixel is left
+    /// untouched) to `1.0` (the pixel is replaced with `color`)
+    Overlay { color: Rgb<u8>, alpha: f32 },
+}
+
+/// Generates a copy of the altered image where the pixels that differ from the original
+/// image are highlighted according to `style`. The annotated image has the dimensions of
+/// the source image, and is always RGB8
+pub fn annotate_encoded_pixels(encoded: &EncodedImage, style: AnnotationStyle) -> DynamicImage {
+    let mut annotated = encoded.altered_image().to_rgb8();
+    let (width, height) = encoded.altered_image().dimensions();
+    let changed = encoded.diff().changed_pixels();
+
+    match style {
+        AnnotationStyle::Outline => {
+            let mut modified = vec![false; width as usize * height as usize];
+            for (x, y, _, _) in &changed {
+                modified[(*y * width + *x) as usize] = true;
+            }
+            for (x, y, pixel) in annotated.enumerate_pixels_mut() {
+                let is_border = !modified[(y * width + x) as usize]
+                    && neighbours(x, y, width, height).any(|(nx, ny)| modified[(ny * width + nx) as usize]);
+                if is_border {
+                    *pixel = image::Rgb(OUTLINE_COLOR);
+                }
+            }
+        }
+        AnnotationStyle::Fill(color) => {
+            for (x, y, _, _) in changed {
+                annotated.put_pixel(x, y, color.into());
+            }
+        }
+        AnnotationStyle::Overlay { color, alpha } => {
+            let alpha = if alpha.is_nan() { 0.0 } else { alpha.clamp(0.0, 1.0) };
+            let color = image::Rgb::from(color);
+            for (x, y, _, _) in changed {
+                let pixel = annotated.get_pixel_mut(x, y);
+                for (channel, value) in pixel.0.iter_mut().enumerate() {
+                    let blended = color[channel] as f32 * alpha + *value as f32 * (1.0 - alpha);
+                    *value = blended.round() as u8;
+                }
+            }
+        }
+    }
+
+    DynamicImage::ImageRgb8(annotated)
+}
+
+/// The coordinates of the up to eight pixels surrounding `(x, y)`
+fn neighbours(x: u32, y: u32, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    let columns = x.saturating_sub(1)..=(x + 1).min(width - 1);
+    let rows = y.saturating_sub(1)..=(y + 1).min(height - 1);
+    rows.flat_map(move |ny| columns.clone().map(move |nx| (nx, ny)))
+        .filter(move |neighbour| *neighbour != (x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView};
+
+    use super::{annotate_encoded_pixels, AnnotationStyle};
+    use crate::{encoder::ImageEncoder, prelude::Rgb};
+
+    #[test]
+    fn annotation_styles() {
+        let encoded = ImageEncoder::from_image(DynamicImage::new_rgb8(40, 30))
+            .encode_bytes([0xffu8])
+            .expect("Encoding failed");
+        let changed = encoded.diff().changed_pixels();
+        assert!(!changed.is_empty());
+        let (x, y, _, altered) = changed[0];
+
+        let filled = annotate_encoded_pixels(&encoded, AnnotationStyle::Fill(Rgb::from([0, 255, 0])));
+        assert_eq!(filled.dimensions(), (40, 30));
+        let filled = filled.to_rgb8();
+        assert_eq!(filled.get_pixel(x, y).0, [0, 255, 0]);
+        assert_eq!(filled.get_pixel(39, 29).0, [0, 0, 0]);
+
+        let overlay = AnnotationStyle::Overlay {
+            color: Rgb::from([255, 255, 255]),
+            alpha: 0.5,
+        };
+        let overlaid = annotate_encoded_pixels(&encoded, overlay).to_rgb8();
+        let expected = image::Rgb::from(altered).0.map(|value| ((255.0 + value as f32) / 2.0).round() as u8);
+        assert_eq!(overlaid.get_pixel(x, y).0, expected);
+
+        let outlined = annotate_encoded_pixels(&encoded, AnnotationStyle::Outline).to_rgb8();
+        assert_eq!(outlined.get_pixel(x, y).0, image::Rgb::from(altered).0);
+        for (x, y, pixel) in outlined.enumerate_pixels() {
+            let is_changed = changed.iter().any(|(cx, cy, _, _)| (*cx, *cy) == (x, y));
+            let touches_change = changed
+                .iter()
+                .any(|(cx, cy, _, _)| cx.abs_diff(x) <= 1 && cy.abs_diff(y) <= 1);
+            if !is_changed && touches_change {
+                assert_eq!(pixel.0, [255, 0, 0]);
+            } else if !touches_change {
+                assert_eq!(pixel.0, [0, 0, 0]);
+            }
+        }
+    }
+}
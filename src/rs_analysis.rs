@@ -0,0 +1 @@
+pub use crate::steganalysis::{rs_analysis, rs_estimate_embedding_rate};
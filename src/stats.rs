@@ -0,0 +1,160 @@
+use crate::encoder::EncodedImage;
+
+/// The number of bits of a RGB8 pixel
+const PIXEL_BITS: usize = 24;
+
+/// How the bits changed by an encoding are distributed over the channels, the bit positions
+/// and the surface of an image, as computed by `bit_change_histogram`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitChangeHistogram {
+    /// The number of bits changed in the red, green and blue channels
+    pub per_channel: [u64; 3],
+
+    /// The number of bits changed at each position of a channel, from the least
+    /// significant bit to the most significant one
+    pub per_bit_position: [u64; 8],
+
+    /// The regions of changed pixels touching each other horizontally or vertically, as
+    /// `(x, y, size)` where `(x, y)` is the first pixel of the region in row order and `size`
+    /// its number of pixels. Regions are listed in the order of their first pixel
+    pub spatial_clusters: Vec<(u32, u32, u32)>,
+
+    /// The Shannon entropy, in bits, of the number of bits changed in each pixel. It is `0.0`
+    /// when every pixel had the same number of bits changed, unchanged images included
+    pub entropy: f64,
+}
+
+/// Computes how the bits changed between the original and the altered image of `encoded`
+/// are distributed, comparing their RGB8 representations. The alpha channel is ignored
+pub fn bit_change_histogram(encoded: &EncodedImage) -> BitChangeHistogram {
+    let original = encoded.original_image().to_rgb8();
+    let altered = encoded.altered_image().to_rgb8();
+    let (width, height) = original.dimensions();
+
+    let mut per_channel = [0u64; 3];
+    let mut per_bit_position = [0u64; 8];
+    let mut pixels_by_bits_changed = [0u64; PIXEL_BITS + 1];
+    let mut changed = vec![false; width as usize * height as usize];
+    for (index, (before, after)) in original.pixels().zip(altered.pixels()).enumerate() {
+        let mut bits_changed = 0;
+        for channel in 0..3 {
+            let difference = before[channel] ^ after[channel];
+            per_channel[channel] += difference.count_ones() as u64;
+            for (position, count) in per_bit_position.iter_mut().enumerate() {
+                *count += ((difference >> position) & 1) as u64;
+            }
+            bits_changed += difference.count_ones() as usize;
+        }
+        pixels_by_bits_changed[bits_changed] += 1;
+        changed[index] = bits_changed > 0;
+    }
+
+    BitChangeHistogram {
+        per_channel,
+        per_bit_position,
+        spatial_clusters: clusters(&mut changed, width, height),
+        entropy: entropy(&pixels_by_bits_changed),
+    }
+}
+
+/// Finds the 4-connected regions of `true` values of a `width` by `height` grid, clearing
+/// them along the way
+fn clusters(changed: &mut [bool], width: u32, height: u32) -> Vec<(u32, u32, u32)> {
+    let mut clusters = vec![];
+    let mut pending = vec![];
+    for start in 0..changed.len() {
+        if !changed[start] {
+            continue;
+        }
+
+        changed[start] = false;
+        pending.push(start);
+        let mut size = 0;
+        while let Some(index) = pending.pop() {
+            size += 1;
+            let (x, y) = ((index % width as usize) as u32, (index / width as usize) as u32);
+            let neighbours = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < width).then(|| index + 1),
+                (y > 0).then(|| index - width as usize),
+                (y + 1 < height).then(|| index + width as usize),
+            ];
+            for neighbour in neighbours.iter().flatten() {
+                if changed[*neighbour] {
+                    changed[*neighbour] = false;
+                    pending.push(*neighbour);
+                }
+            }
+        }
+
+        clusters.push(((start % width as usize) as u32, (start / width as usize) as u32, size));
+    }
+
+    clusters
+}
+
+/// The Shannon entropy, in bits, of the distribution described by `counts`
+fn entropy(counts: &[u64]) -> f64 {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let probability = *count as f64 / total as f64;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::DynamicImage;
+
+    use super::{bit_change_histogram, clusters, entropy};
+    use crate::{encoder::ImageEncoder, prelude::*};
+
+    #[test]
+    fn histogram() {
+        let encoded = ImageEncoder::from_image(DynamicImage::new_rgb8(16, 16))
+            .with_channel_order(vec![RgbChannel::Red])
+            .with_lsb(2)
+            .encode_bytes([0xffu8])
+            .unwrap();
+        let histogram = bit_change_histogram(&encoded);
+
+        assert_eq!(histogram.per_channel, [8, 0, 0]);
+        assert_eq!(histogram.per_bit_position, [4, 4, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(histogram.spatial_clusters, vec![(0, 0, 4)]);
+        // 4 pixels with 2 bits changed out of 256
+        let expected = entropy(&[252, 0, 4]);
+        assert!((histogram.entropy - expected).abs() < 1e-12);
+
+        let unchanged = bit_change_histogram(&ImageEncoder::default().encode_bytes(b"").unwrap());
+        assert_eq!(unchanged.per_channel, [0, 0, 0]);
+        assert!(unchanged.spatial_clusters.is_empty());
+        assert_eq!(unchanged.entropy, 0.0);
+    }
+
+    #[test]
+    fn connected_regions() {
+        let mut grid = [
+            true, true, false, false, //
+            false, true, false, true, //
+            true, false, false, true,
+        ];
+        assert_eq!(clusters(&mut grid, 4, 3), vec![(0, 0, 3), (3, 1, 2), (0, 2, 1)]);
+        assert!(grid.iter().all(|changed| !changed));
+    }
+
+    #[test]
+    fn shannon_entropy() {
+        assert_eq!(entropy(&[]), 0.0);
+        assert_eq!(entropy(&[10, 0]), 0.0);
+        assert!((entropy(&[5, 5]) - 1.0).abs() < 1e-12);
+        assert!((entropy(&[1, 1, 1, 1]) - 2.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,328 @@
+use bitvec::prelude::*;
+use image::{DynamicImage, RgbImage};
+
+pub use crate::header::LENGTH_SIZE;
+use crate::{
+    error::SteganographyError,
+    header::{complete_bytes, frame_with_length, read_length_framed, BYTE_STEP},
+    prelude::RgbChannel,
+};
+
+/// The largest number of least significant bits used on a pixel
+pub const MAX_LSB: usize = 4;
+
+/// The variance threshold used unless another one is set. Each multiple of the threshold in
+/// the variance of a neighborhood adds one bit to the pixel at its center
+pub const DEFAULT_THRESHOLD: f32 = 400.0;
+
+/// The distance from a pixel to the edges of the neighborhood its variance is computed on
+const NEIGHBORHOOD_RADIUS: usize = 2;
+
+/// The settings shared by `AdaptiveLsbEncoder` and `AdaptiveLsbDecoder`, which must match
+/// for the data to be decoded
+#[derive(Debug, Clone, PartialEq)]
+struct AdaptiveRules {
+    threshold: f32,
+    channel: RgbChannel,
+}
+
+impl Default for AdaptiveRules {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            channel: RgbChannel::Blue,
+        }
+    }
+}
+
+impl AdaptiveRules {
+    /// The index of the channel holding data, failing if the settings can not be used
+    fn validate(&self) -> Result<usize, SteganographyError> {
+        if self.threshold.is_nan() || self.threshold <= 0.0 {
+            return Err(SteganographyError::InvalidConfiguration(format!(
+                "The adaptive threshold must be positive, got {}",
+                self.threshold
+            )));
+        }
+
+        match self.channel {
+            RgbChannel::Red | RgbChannel::Green | RgbChannel::Blue => Ok(self.channel.indices()[0]),
+            _ => Err(SteganographyError::InvalidConfiguration(format!(
+                "Adaptive encoding only supports the red, green or blue channel, got {}",
+                self.channel
+            ))),
+        }
+    }
+
+    /// The number of least significant bits used on each pixel of `img`, row by row.
+    ///
+    /// Each pixel uses `1 + variance / threshold` bits, up to `MAX_LSB`, where the variance
+    /// is the one of `channel` in the 5x5 neighborhood of the pixel. The variance ignores
+    /// the `MAX_LSB` least significant bits, so that encoding does not change it and the
+    /// decoder computes the same depths
+    fn depth_map(&self, img: &RgbImage, channel: usize) -> Vec<usize> {
+        let (width, height) = (img.width() as usize, img.height() as usize);
+        let mask = u8::MAX << MAX_LSB;
+
+        // Summed area tables of the values and of their squares, with a leading row and
+        // column of zeros
+        let mut sums = vec![0u64; (width + 1) * (height + 1)];
+        let mut squares = vec![0u64; (width + 1) * (height + 1)];
+        for y in 0..height {
+            for x in 0..width {
+                let value = (img.get_pixel(x as u32, y as u32)[channel] & mask) as u64;
+                let at = (y + 1) * (width + 1) + x + 1;
+                let above = y * (width + 1) + x + 1;
+                sums[at] = value + sums[above] + sums[at - 1] - sums[above - 1];
+                squares[at] = value * value + squares[above] + squares[at - 1] - squares[above - 1];
+            }
+        }
+        let area_sum = |table: &[u64], x1: usize, y1: usize, x2: usize, y2: usize| {
+            table[y2 * (width + 1) + x2] + table[y1 * (width + 1) + x1]
+                - table[y1 * (width + 1) + x2]
+                - table[y2 * (width + 1) + x1]
+        };
+
+        let mut depths = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (x1, y1) = (x.saturating_sub(NEIGHBORHOOD_RADIUS), y.saturating_sub(NEIGHBORHOOD_RADIUS));
+                let (x2, y2) = ((x + NEIGHBORHOOD_RADIUS + 1).min(width), (y + NEIGHBORHOOD_RADIUS + 1).min(height));
+                let count = ((x2 - x1) * (y2 - y1)) as f32;
+                let mean = area_sum(&sums, x1, y1, x2, y2) as f32 / count;
+                let variance = area_sum(&squares, x1, y1, x2, y2) as f32 / count - mean * mean;
+
+                depths.push((1 + (variance.max(0.0) / self.threshold) as usize).min(MAX_LSB));
+            }
+        }
+
+        depths
+    }
+}
+
+/// Hides data in the least significant bits of an image, using more bits on the pixels of
+/// textured regions, where changes are hard to notice, than on the ones of flat regions.
+///
+/// Pixels are used row by row, and the payload is preceded by its length so that
+/// `AdaptiveLsbDecoder` only needs the same threshold and channel
+pub struct AdaptiveLsbEncoder {
+    source_image: DynamicImage,
+    rules: AdaptiveRules,
+}
+
+impl AdaptiveLsbEncoder {
+    /// Creates an encoder for an image already loaded in memory, taking ownership of it
+    pub fn from_image(img: DynamicImage) -> Self {
+        Self {
+            source_image: img,
+            rules: AdaptiveRules::default(),
+        }
+    }
+
+    /// Creates an encoder for an encoded image (PNG, JPEG, BMP...) held in memory
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        Ok(Self::from_image(image::load_from_memory(data)?))
+    }
+
+    /// Sets the variance threshold. Lower thresholds use more bits on each pixel, increasing
+    /// the capacity and making the changes easier to detect
+    pub fn set_adaptive_threshold(&mut self, threshold: f32) -> &mut Self {
+        self.rules.threshold = threshold;
+        self
+    }
+
+    /// Consuming version of `set_adaptive_threshold`
+    pub fn with_adaptive_threshold(mut self, threshold: f32) -> Self {
+        self.set_adaptive_threshold(threshold);
+        self
+    }
+
+    /// Sets the color channel used to store information bits, which defaults to blue
+    pub fn set_channel(&mut self, channel: RgbChannel) -> &mut Self {
+        self.rules.channel = channel;
+        self
+    }
+
+    /// Consuming version of `set_channel`
+    pub fn with_channel(mut self, channel: RgbChannel) -> Self {
+        self.set_channel(channel);
+        self
+    }
+
+    /// The number of payload bytes that fit into the image
+    pub fn estimate_capacity(&self) -> Result<usize, SteganographyError> {
+        let channel = self.rules.validate()?;
+        let bits: usize = self.rules.depth_map(&self.source_image.to_rgb8(), channel).iter().sum();
+        Ok((bits / BYTE_STEP).saturating_sub(LENGTH_SIZE))
+    }
+
+    /// Encodes arbitrary bytes into the source image. The encoded image has no alpha channel
+    pub fn encode_bytes(&self, data: impl AsRef<[u8]>) -> Result<DynamicImage, SteganographyError> {
+        let data = data.as_ref();
+        let channel = self.rules.validate()?;
+        let mut img = self.source_image.to_rgb8();
+        let depths = self.rules.depth_map(&img, channel);
+
+        let available = (depths.iter().sum::<usize>() / BYTE_STEP).saturating_sub(LENGTH_SIZE);
+        if data.len() > available {
+            return Err(SteganographyError::InsufficientCapacity {
+                required: data.len(),
+                available,
+            });
+        }
+        let payload = frame_with_length(data)?;
+        let mut bits = payload.view_bits::<Lsb0>().iter().map(|bit| *bit).peekable();
+
+        for (pixel, depth) in img.pixels_mut().zip(depths) {
+            if bits.peek().is_none() {
+                break;
+            }
+            let value_bits = pixel[channel].view_bits_mut::<Lsb0>();
+            for i in 0..depth {
+                if let Some(bit) = bits.next() {
+                    value_bits.set(i, bit);
+                }
+            }
+        }
+
+        Ok(DynamicImage::ImageRgb8(img))
+    }
+}
+
+/// Recovers data hidden in an image by `AdaptiveLsbEncoder`, which must have used the same
+/// threshold and channel
+pub struct AdaptiveLsbDecoder {
+    source_image: DynamicImage,
+    rules: AdaptiveRules,
+}
+
+impl AdaptiveLsbDecoder {
+    /// Creates a decoder for an image already loaded in memory, taking ownership of it
+    pub fn from_image(img: DynamicImage) -> Self {
+        Self {
+            source_image: img,
+            rules: AdaptiveRules::default(),
+        }
+    }
+
+    /// Creates a decoder for an encoded image (PNG, BMP...) held in memory
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SteganographyError> {
+        Ok(Self::from_image(image::load_from_memory(data)?))
+    }
+
+    /// Sets the variance threshold the image was encoded with
+    pub fn set_adaptive_threshold(&mut self, threshold: f32) -> &mut Self {
+        self.rules.threshold = threshold;
+        self
+    }
+
+    /// Consuming version of `set_adaptive_threshold`
+    pub fn with_adaptive_threshold(mut self, threshold: f32) -> Self {
+        self.set_adaptive_threshold(threshold);
+        self
+    }
+
+    /// Sets the color channel the image was encoded into
+    pub fn set_channel(&mut self, channel: RgbChannel) -> &mut Self {
+        self.rules.channel = channel;
+        self
+    }
+
+    /// Consuming version of `set_channel`
+    pub fn with_channel(mut self, channel: RgbChannel) -> Self {
+        self.set_channel(channel);
+        self
+    }
+
+    /// Decodes the payload hidden in the image. Fails if the image holds fewer bytes than
+    /// the length read from it, which usually means it holds no payload or was encoded
+    /// with other settings
+    pub fn decode(&self) -> Result<Vec<u8>, SteganographyError> {
+        let channel = self.rules.validate()?;
+        let img = self.source_image.to_rgb8();
+        let depths = self.rules.depth_map(&img, channel);
+
+        let mut bits: BitVec<Lsb0, u8> = BitVec::with_capacity(depths.iter().sum());
+        for (pixel, depth) in img.pixels().zip(depths) {
+            bits.extend_from_bitslice(&pixel[channel].view_bits::<Lsb0>()[..depth]);
+        }
+        Ok(read_length_framed(&complete_bytes(bits))?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+
+    use super::{AdaptiveLsbDecoder, AdaptiveLsbEncoder, AdaptiveRules, MAX_LSB};
+    use crate::{error::SteganographyError, prelude::RgbChannel};
+
+    /// A 64x32 image, flat gray on the left half and noisy on the right half
+    fn half_textured() -> DynamicImage {
+        let mut state = 0x2545_f491_u32;
+        DynamicImage::ImageRgb8(RgbImage::from_fn(64, 32, |x, _| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            if x < 32 {
+                Rgb([128, 128, 128])
+            } else {
+                Rgb([state as u8, (state >> 8) as u8, (state >> 16) as u8])
+            }
+        }))
+    }
+
+    #[test]
+    fn depth_follows_texture() {
+        let img = half_textured().to_rgb8();
+        let depths = AdaptiveRules::default().depth_map(&img, 2);
+
+        assert_eq!(depths[16 * 64 + 8], 1);
+        assert_eq!(depths[16 * 64 + 56], MAX_LSB);
+    }
+
+    #[test]
+    fn encode_decode() {
+        let source = half_textured();
+        let encoder = AdaptiveLsbEncoder::from_image(source.clone());
+        let capacity = encoder.estimate_capacity().unwrap();
+        // The flat half holds 1 bit per pixel and the textured one up to `MAX_LSB`
+        assert!(capacity > 32 * 32 * 2 / 8);
+
+        let payload: Vec<u8> = (0..capacity).map(|i| (i % 251) as u8).collect();
+        let encoded = encoder.encode_bytes(&payload).unwrap();
+        assert_eq!(AdaptiveLsbDecoder::from_image(encoded.clone()).decode().unwrap(), payload);
+
+        // Flat pixels change by one at most
+        for x in 0..28 {
+            let before = source.get_pixel(x, 16)[2] as i32;
+            let after = encoded.get_pixel(x, 16)[2] as i32;
+            assert!((before - after).abs() <= 1);
+        }
+
+        let mut png = vec![];
+        encoded.write_to(&mut png, image::ImageOutputFormat::Png).unwrap();
+        assert_eq!(AdaptiveLsbDecoder::from_bytes(&png).unwrap().decode().unwrap(), payload);
+
+        let other_threshold = AdaptiveLsbDecoder::from_image(encoded)
+            .with_adaptive_threshold(40.0)
+            .decode();
+        assert!(!matches!(other_threshold, Ok(decoded) if decoded == payload));
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let encoders = vec![
+            AdaptiveLsbEncoder::from_image(half_textured()).with_adaptive_threshold(0.0),
+            AdaptiveLsbEncoder::from_image(half_textured()).with_adaptive_threshold(f32::NAN),
+            AdaptiveLsbEncoder::from_image(half_textured()).with_channel(RgbChannel::All),
+        ];
+
+        for encoder in encoders {
+            assert!(matches!(
+                encoder.encode_bytes(b"seagul"),
+                Err(SteganographyError::InvalidConfiguration(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,2 @@
+/// The module holding the encoder that uses more bits of the pixels in textured regions
+pub mod adaptive_lsb;
@@ -0,0 +1,343 @@
+use image::DynamicImage;
+
+use crate::prelude::RgbChannel;
+
+/// Pairs of values whose expected frequency is below this threshold are not
+/// considered by the chi-square attack, as the statistic would not be reliable
+const MIN_EXPECTED_FREQUENCY: f64 = 5.0;
+
+/// The mask applied to groups of pixels during RS analysis
+const RS_MASK: [bool; 4] = [false, true, true, false];
+
+/// Performs the chi-square attack by Westfeld and Pfitzmann on the given `channel` of `img`.
+///
+/// LSB embedding of random-looking data tends to equalize the frequencies of each pair of values
+/// differing only in their least significant bit (`2k` and `2k + 1`). This function compares the
+/// observed frequencies with the ones expected after such an embedding and returns the
+/// resulting p-value, between `0.0` and `1.0`.
+///
+/// A p-value close to `1.0` (above `0.95` or so) indicates a high probability that the channel
+/// carries hidden data in its least significant bits, while values close to `0.0` are typical
+/// of untouched images. Values in between are inconclusive. Images with very few distinct
+/// colors always produce values close to `0.0`.
+pub fn chi_square_attack(img: &DynamicImage, channel: RgbChannel) -> f64 {
+    let mut histogram = [0u64; 256];
+    for value in channel_values(img, channel) {
+        histogram[value as usize] += 1;
+    }
+
+    let mut chi_square = 0.0;
+    let mut categories = 0;
+    for pair in histogram.chunks(2) {
+        let expected = (pair[0] + pair[1]) as f64 / 2.0;
+        if expected >= MIN_EXPECTED_FREQUENCY {
+            let deviation = pair[0] as f64 - expected;
+            chi_square += deviation * deviation / expected;
+            categories += 1;
+        }
+    }
+
+    if categories < 2 {
+        return 0.0;
+    }
+
+    let degrees_of_freedom = (categories - 1) as f64;
+    1.0 - regularized_lower_gamma(degrees_of_freedom / 2.0, chi_square / 2.0)
+}
+
+/// Performs the RS (Regular and Singular groups) analysis by Fridrich et al. on the given
+/// `channel` of `img`, returning the `(R, S)` ratios of regular and singular groups.
+///
+/// The channel values are split into horizontal groups of 4 pixels and each group is measured
+/// for its smoothness before and after flipping the least significant bit of the pixels selected
+/// by the mask `[0, 1, 1, 0]`. Groups that get noisier are regular, groups that get smoother are
+/// singular.
+///
+/// In a natural image `R` is larger than `S`. LSB embedding randomizes the least significant
+/// bits and pulls the two ratios towards each other, so `R - S` close to `0.0` (or negative)
+/// hints at a channel almost fully used to store hidden data. The size of the gap depends on
+/// the image content, so it is most meaningful when compared against a known clean image
+/// of similar nature.
+pub fn rs_analysis(img: &DynamicImage, channel: RgbChannel) -> (f64, f64) {
+    rs_ratios(&channel_rows(img, channel), flip_lsb)
+}
+
+/// Estimates the fraction of the pixels of `img` carrying hidden data in the least
+/// significant bit of `channel`, using the RS analysis by Fridrich et al.
+///
+/// The ratios of regular and singular groups are measured with the flipping function
+/// `F1` (`0 <-> 1`, `2 <-> 3`...) and its shifted version `F-1` (`-1 <-> 0`, `1 <-> 2`...),
+/// both on the image and on the image with every least significant bit flipped. As these
+/// ratios change with the embedding rate in a known way, they are enough to estimate it.
+///
+/// The result is between `0.0`, for a clean channel, and `1.0`, for a channel whose every
+/// pixel holds a bit of random looking data. Only about half of those pixels actually
+/// change, as the other half already had the right least significant bit. Clean images
+/// give small non zero estimates, higher on the channels smoothed by lossy compression.
+pub fn rs_estimate_embedding_rate(img: &DynamicImage, channel: RgbChannel) -> f64 {
+    let rows = channel_rows(img, channel);
+    let flipped_rows: Vec<Vec<i32>> = rows
+        .iter()
+        .map(|row| row.iter().map(|value| flip_lsb(*value)).collect())
+        .collect();
+
+    let difference = |(regular, singular): (f64, f64)| regular - singular;
+    let d0 = difference(rs_ratios(&rows, flip_lsb));
+    let d1 = difference(rs_ratios(&flipped_rows, flip_lsb));
+    let negative_d0 = difference(rs_ratios(&rows, flip_shifted_lsb));
+    let negative_d1 = difference(rs_ratios(&flipped_rows, flip_shifted_lsb));
+
+    // 2(d1 + d0)x² + (d-0 - d-1 - d1 - 3d0)x + d0 - d-0 = 0, taking the root closest to 0
+    let a = 2.0 * (d1 + d0);
+    let b = negative_d0 - negative_d1 - d1 - 3.0 * d0;
+    let c = d0 - negative_d0;
+    let x = if a.abs() < f64::EPSILON {
+        if b.abs() < f64::EPSILON {
+            return 0.0;
+        }
+        -c / b
+    } else {
+        let root = (b * b - 4.0 * a * c).max(0.0).sqrt();
+        let (first, second) = ((-b + root) / (2.0 * a), (-b - root) / (2.0 * a));
+        if first.abs() <= second.abs() {
+            first
+        } else {
+            second
+        }
+    };
+
+    let rate = x / (x - 0.5);
+    if rate.is_finite() {
+        rate.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// The ratios of regular and singular groups among the horizontal groups of `rows`, when
+/// applying `flip` to the values selected by `RS_MASK`
+fn rs_ratios(rows: &[Vec<i32>], flip: fn(i32) -> i32) -> (f64, f64) {
+    let mut regular = 0usize;
+    let mut singular = 0usize;
+    let mut groups = 0usize;
+
+    for values in rows {
+        for group in values.chunks_exact(RS_MASK.len()) {
+            let original_smoothness = smoothness(group);
+            let flipped: Vec<i32> = group
+                .iter()
+                .zip(RS_MASK.iter())
+                .map(|(value, masked)| if *masked { flip(*value) } else { *value })
+                .collect();
+            let flipped_smoothness = smoothness(&flipped);
+
+            if flipped_smoothness > original_smoothness {
+                regular += 1;
+            } else if flipped_smoothness < original_smoothness {
+                singular += 1;
+            }
+            groups += 1;
+        }
+    }
+
+    if groups == 0 {
+        return (0.0, 0.0);
+    }
+
+    (regular as f64 / groups as f64, singular as f64 / groups as f64)
+}
+
+/// The values of `channel` in each row of `img`
+fn channel_rows(img: &DynamicImage, channel: RgbChannel) -> Vec<Vec<i32>> {
+    let channel_index: usize = channel.into();
+    img.to_rgba8()
+        .rows()
+        .map(|row| row.map(|pixel| pixel[channel_index] as i32).collect())
+        .collect()
+}
+
+/// The flipping function `F1`, swapping `2k` and `2k + 1`
+fn flip_lsb(value: i32) -> i32 {
+    value ^ 1
+}
+
+/// The flipping function `F-1`, swapping `2k - 1` and `2k`
+fn flip_shifted_lsb(value: i32) -> i32 {
+    ((value + 1) ^ 1) - 1
+}
+
+/// The values of `channel` in every pixel of `img`. The values of the channels
+/// stood for by `RgbChannel::All` are interleaved
+fn channel_values(img: &DynamicImage, channel: RgbChannel) -> impl Iterator<Item = u8> {
+    let channel_indices = channel.indices();
+    img.to_rgba8()
+        .into_raw()
+        .into_iter()
+        .enumerate()
+        .filter(move |(index, _)| channel_indices.contains(&(index % 4)))
+        .map(|(_, value)| value)
+}
+
+/// Sum of the absolute differences between adjacent values. Lower is smoother
+fn smoothness(group: &[i32]) -> u32 {
+    group
+        .windows(2)
+        .map(|pair| (pair[0] - pair[1]).unsigned_abs())
+        .sum()
+}
+
+/// Natural logarithm of the gamma function, using the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        std::f64::consts::PI.ln() - (std::f64::consts::PI * x).sin().ln() - ln_gamma(1.0 - x)
+    } else {
+        let x = x - 1.0;
+        let t = x + 7.5;
+        let series = COEFFICIENTS[1..]
+            .iter()
+            .enumerate()
+            .fold(COEFFICIENTS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+
+        0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+    }
+}
+
+/// The regularized lower incomplete gamma function `P(a, x)`
+fn regularized_lower_gamma(a: f64, x: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 1000;
+    const EPSILON: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    let log_prefix = -x + a * x.ln() - ln_gamma(a);
+
+    if x < a + 1.0 {
+        // Series expansion
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut denominator = a;
+        for _ in 0..MAX_ITERATIONS {
+            denominator += 1.0;
+            term *= x / denominator;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+
+        (sum * log_prefix.exp()).min(1.0)
+    } else {
+        // Continued fraction for the upper function, using Lentz's method
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut fraction = d;
+        for i in 1..MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            fraction *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+
+        (1.0 - log_prefix.exp() * fraction).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chi_square_attack, regularized_lower_gamma, rs_analysis, rs_estimate_embedding_rate};
+    use crate::{encoder::ImageEncoder, prelude::*};
+
+    /// A pseudo random payload, which is what encrypted or compressed data looks like
+    fn random_payload(len: usize) -> Vec<u8> {
+        let mut state: u32 = 0x5eaf_u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    fn cover_image() -> image::DynamicImage {
+        image::open("tests/images/red_panda.jpg")
+            .expect("Test image not found")
+            .crop_imm(0, 0, 256, 256)
+    }
+
+    fn fully_encoded_image() -> image::DynamicImage {
+        let payload = random_payload(256 * 256 / 8);
+        let encoded = ImageEncoder::from_image(cover_image())
+            .encode_bytes(&payload)
+            .expect("Encoding failed");
+
+        let mut buffer = vec![];
+        encoded.write(&mut buffer, ImageFormat::Png).expect("Write failed");
+        image::load_from_memory(&buffer).unwrap()
+    }
+
+    #[test]
+    fn incomplete_gamma() {
+        // P(1, x) = 1 - e^-x
+        assert!((regularized_lower_gamma(1.0, 2.0) - (1.0 - (-2.0_f64).exp())).abs() < 1e-10);
+        assert!((regularized_lower_gamma(1.0, 0.5) - (1.0 - (-0.5_f64).exp())).abs() < 1e-10);
+        assert_eq!(regularized_lower_gamma(3.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn chi_square_detects_embedding() {
+        assert!(chi_square_attack(&cover_image(), RgbChannel::Blue) < 0.5);
+        assert!(chi_square_attack(&fully_encoded_image(), RgbChannel::Blue) > 0.95);
+    }
+
+    #[test]
+    fn rs_groups_converge_with_embedding() {
+        let (clean_r, clean_s) = rs_analysis(&cover_image(), RgbChannel::Blue);
+        let (stego_r, stego_s) = rs_analysis(&fully_encoded_image(), RgbChannel::Blue);
+
+        assert!(clean_r > clean_s);
+        assert!((stego_r - stego_s).abs() < (clean_r - clean_s).abs());
+    }
+
+    #[test]
+    fn rs_estimates_embedding_rate() {
+        let clean = rs_estimate_embedding_rate(&cover_image(), RgbChannel::Blue);
+        let stego = rs_estimate_embedding_rate(&fully_encoded_image(), RgbChannel::Blue);
+        let half = ImageEncoder::from_image(cover_image())
+            .encode_bytes(random_payload(256 * 256 / 16))
+            .unwrap();
+        let half = rs_estimate_embedding_rate(half.altered_image(), RgbChannel::Blue);
+
+        assert!(clean < 0.2, "{}", clean);
+        assert!((half - 0.5).abs() < 0.15, "{}", half);
+        assert!(stego > 0.9, "{}", stego);
+    }
+}
@@ -0,0 +1,163 @@
+use image::{DynamicImage, GenericImageView};
+
+use crate::{error::SteganographyError, prelude::ImagePosition};
+
+/// The opacity of a watermark, unless configured otherwise
+const DEFAULT_OPACITY: f32 = 0.5;
+
+/// A logo blended into images as a visible watermark.
+///
+/// Unlike the data hidden by `ImageEncoder`, a watermark changes pixel values substantially
+/// and survives lossy formats and conversions. The transparency of the logo is respected,
+/// and scaled by the configured opacity
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    logo: DynamicImage,
+    position: ImagePosition,
+    opacity: f32,
+}
+
+impl Watermark {
+    /// A watermark blending `logo` in the bottom right corner, half transparent
+    pub fn new(logo: DynamicImage) -> Self {
+        Self {
+            logo,
+            position: ImagePosition::BottomRight,
+            opacity: DEFAULT_OPACITY,
+        }
+    }
+
+    /// Sets where the logo is placed on the target image. `ImagePosition::At(x, y)` is
+    /// the top left corner of the logo, other positions align the logo to the matching
+    /// corner or center of the image
+    pub fn with_position(mut self, position: ImagePosition) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Sets the opacity of the logo, from 0.0 (invisible) to 1.0 (opaque). Values out of
+    /// this range are clamped
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn position(&self) -> &ImagePosition {
+        &self.position
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Blends the logo into `target`. Images without transparency become RGB images,
+    /// the other ones RGBA images keeping their alpha channel.
+    ///
+    /// Fails with `SteganographyError::InvalidConfiguration` if the logo, at its position,
+    /// does not fit entirely into `target`
+    pub fn apply(&self, target: &mut DynamicImage) -> Result<(), SteganographyError> {
+        let (x0, y0) = self.origin(target.dimensions())?;
+        let logo = self.logo.to_rgba8();
+        let has_alpha = target.color().has_alpha();
+        let mut blended = target.to_rgba8();
+
+        for (x, y, logo_pixel) in logo.enumerate_pixels() {
+            let alpha = logo_pixel[3] as f32 / 255.0 * self.opacity;
+            let pixel = blended.get_pixel_mut(x0 + x, y0 + y);
+            for channel in 0..3 {
+                let value = pixel[channel] as f32 * (1.0 - alpha) + logo_pixel[channel] as f32 * alpha;
+                pixel[channel] = value.round() as u8;
+            }
+        }
+
+        *target = if has_alpha {
+            DynamicImage::ImageRgba8(blended)
+        } else {
+            DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(blended).to_rgb8())
+        };
+
+        Ok(())
+    }
+
+    /// The coordinates of the top left corner of the logo on an image with the given
+    /// `(width, height)` dimensions
+    fn origin(&self, image_dimensions: (u32, u32)) -> Result<(u32, u32), SteganographyError> {
+        let (width, height) = image_dimensions;
+        let (logo_width, logo_height) = self.logo.dimensions();
+        if logo_width > width || logo_height > height {
+            return Err(SteganographyError::InvalidConfiguration(format!(
+                "The {}x{} watermark does not fit into the {}x{} image",
+                logo_width, logo_height, width, height
+            )));
+        }
+
+        let (right, bottom) = (width - logo_width, height - logo_height);
+        let origin = match self.position {
+            ImagePosition::TopLeft => (0, 0),
+            ImagePosition::TopRight => (right, 0),
+            ImagePosition::BottomLeft => (0, bottom),
+            ImagePosition::BottomRight => (right, bottom),
+            ImagePosition::Center => (right / 2, bottom / 2),
+            ImagePosition::At(x, y) => {
+                if x > right || y > bottom {
+                    return Err(SteganographyError::InvalidConfiguration(format!(
+                        "The watermark at ({}, {}) exceeds the {}x{} image",
+                        x, y, width, height
+                    )));
+                }
+                (x, y)
+            }
+        };
+
+        Ok(origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+    use super::Watermark;
+    use crate::{error::SteganographyError, prelude::ImagePosition};
+
+    fn logo() -> DynamicImage {
+        let mut logo = RgbaImage::from_pixel(2, 2, Rgba([255, 255, 255, 255]));
+        logo.put_pixel(0, 0, Rgba([255, 255, 255, 0]));
+        DynamicImage::ImageRgba8(logo)
+    }
+
+    #[test]
+    fn blends_logo() {
+        let mut target = DynamicImage::new_rgb8(4, 4);
+        Watermark::new(logo()).apply(&mut target).unwrap();
+
+        assert!(matches!(target, DynamicImage::ImageRgb8(_)));
+        assert_eq!(target.get_pixel(3, 3), Rgba([128, 128, 128, 255]));
+        // Transparent pixels of the logo leave the image untouched
+        assert_eq!(target.get_pixel(2, 2), Rgba([0, 0, 0, 255]));
+        assert_eq!(target.get_pixel(1, 1), Rgba([0, 0, 0, 255]));
+
+        let mut target = DynamicImage::new_rgba8(4, 4);
+        Watermark::new(logo())
+            .with_position(ImagePosition::At(1, 0))
+            .with_opacity(2.0)
+            .apply(&mut target)
+            .unwrap();
+        assert_eq!(target.get_pixel(2, 1), Rgba([255, 255, 255, 0]));
+        assert_eq!(target.get_pixel(0, 1), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn logo_out_of_image() {
+        let mut target = DynamicImage::new_rgb8(4, 4);
+        for watermark in [
+            Watermark::new(DynamicImage::new_rgb8(5, 1)),
+            Watermark::new(logo()).with_position(ImagePosition::At(3, 0)),
+        ] {
+            assert!(matches!(
+                watermark.apply(&mut target),
+                Err(SteganographyError::InvalidConfiguration(_))
+            ));
+        }
+    }
+}
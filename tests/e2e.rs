@@ -1,7 +1,14 @@
+// The original assertions compare with literal booleans
+#![allow(clippy::bool_assert_comparison)]
+
 use core::panic;
-use std::fs::File;
+use std::{convert::TryFrom, fs::File};
 
-use seagul_core::{decoder::ImageDecoder, prelude::*};
+use seagul_core::{decoder::ImageDecoder, header::Header, prelude::*};
+use seagul_core::pixel_selectors::{
+    DiagonalPixelSelector, FibonacciPixelSelector, LinearPixelSelector, SpiralPixelSelector,
+    ZigZagPixelSelector,
+};
 use seagul_core::encoder::ImageEncoder;
 
 fn ensure_out_dir() -> std::io::Result<()> {
@@ -25,13 +32,13 @@ I cannot well repeat how there I entered,
 So full was I of slumber at the moment
 In which I had abandoned the true way.--";
 
-    let encode_result = ImageEncoder::from("tests/images/red_panda.jpg")
+    let encode_result = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
         .set_offset(0)
         .set_use_n_lsb(2)
         .encode_bytes(verses);
 
     if let Err(e) = encode_result {
-        panic!("{}", e.as_str());
+        panic!("{}", e);
     }
 
     encode_result
@@ -42,7 +49,7 @@ In which I had abandoned the true way.--";
     let mut created_image =
         File::open("tests/out/red_panda_steg.png").expect("Failed to open created image");
 
-    let decoded = ImageDecoder::from(&mut created_image)
+    let decoded = ImageDecoder::from_reader(&mut created_image).unwrap()
         .set_offset(0)
         .set_use_n_lsb(2)
         .until_marker(Some(b"--"))
@@ -75,14 +82,14 @@ I cannot well repeat how there I entered,
 So full was I of slumber at the moment
 In which I had abandoned the true way.--";
 
-    let encode_result = ImageEncoder::from("tests/images/red_panda.jpg")
+    let encode_result = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
         .set_offset(0)
         .set_spread(true)
         .set_use_n_lsb(2)
         .encode_bytes(verses);
 
     if let Err(e) = encode_result {
-        panic!("{}", e.as_str());
+        panic!("{}", e);
     }
 
     encode_result
@@ -93,17 +100,900 @@ In which I had abandoned the true way.--";
     let mut created_image =
         File::open("tests/out/red_panda_spread.png").expect("Failed to open created image");
 
-    let decoded = ImageDecoder::from(&mut created_image)
-        .set_offset(0)
+    let mut decoder = ImageDecoder::from_reader(&mut created_image).unwrap();
+    decoder.set_offset(0).set_spread(true).set_use_n_lsb(2);
+
+    // The stride depends on the payload length, which must be known
+    assert!(decoder.decode().is_err());
+    let decoded = ImageDecoder::from_image(image::open("tests/out/red_panda_spread.png").unwrap())
+        .with_lsb(2)
+        .decode_spread(verses.len())
+        .expect("Could not decode spread data");
+    assert_eq!(decoded.embedded_data(), verses);
+
+    let decoded = decoder.decode_exact(verses.len()).expect("Could not decode spread data");
+    assert_eq!(decoded.embedded_data(), verses);
+
+    let (width, height) = image::image_dimensions("tests/out/red_panda_spread.png").unwrap();
+    let stride = seagul_core::capacity::spread_stride((width, height), verses.len(), &decoder);
+    assert!(stride > 1);
+    assert!(decoded.decoded_pixel_count() * stride > (width * height) as usize / 2);
+}
+
+#[test]
+fn encode_decode_spread_stride() {
+    let verses = b"Midway upon the journey of our life";
+    let encoder = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+        .with_spread(true)
+        .with_spread_stride(5);
+    let encoded = encoder.encode_bytes(verses).expect("Could not encode spread data");
+
+    let decoder = ImageDecoder::from_image(encoded.altered_image().clone())
+        .with_spread(true)
+        .with_spread_stride(5);
+    let decoded = decoder.decode().expect("Could not decode spread data");
+    assert!(decoded.embedded_data().starts_with(verses));
+
+    let decoded = ImageDecoder::from_image(encoded.altered_image().clone())
+        .with_spread_stride(5)
+        .decode_spread(verses.len())
+        .expect("Could not decode spread data");
+    assert_eq!(decoded.embedded_data(), verses);
+
+    let encoded = ImageEncoder::from_image(encoded.original_image().clone())
+        .with_spread(true)
+        .encode_with_header(verses)
+        .expect("Could not encode spread data with header");
+    let decoded = ImageDecoder::from_image(encoded.into_altered_image())
+        .decode_with_header()
+        .expect("Could not decode spread data with header");
+    assert_eq!(decoded.embedded_data(), verses);
+}
+
+#[test]
+fn encode_decode_positions() {
+    ensure_out_dir().expect("Could not create output directory");
+
+    let verses = b"Midway upon the journey of our life
+I found myself within a forest dark,
+For the straightforward pathway had been lost.--";
+
+    let positions = vec![
+        ("top_left", ImagePosition::TopLeft),
+        ("top_right", ImagePosition::TopRight),
+        ("bottom_left", ImagePosition::BottomLeft),
+        ("bottom_right", ImagePosition::BottomRight),
+        ("center", ImagePosition::Center),
+        ("at", ImagePosition::At(100, 50)),
+    ];
+
+    for (name, position) in positions {
+        let output_path = format!("tests/out/red_panda_position_{}.png", name);
+
+        ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+            .set_use_n_lsb(2)
+            .set_position(position.clone())
+            .encode_bytes(verses)
+            .expect("Encoding failed")
+            .save(output_path.as_str(), ImageFormat::Png)
+            .expect("Could not create output file");
+
+        let decoded = ImageDecoder::try_from(output_path.as_str()).unwrap()
+            .set_use_n_lsb(2)
+            .set_position(position)
+            .until_marker(Some(b"--"))
+            .decode()
+            .expect("Decoding failed");
+
+        assert!(decoded.hit_marker(), "Marker not found for position {}", name);
+        assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+    }
+}
+
+#[test]
+fn encode_decode_channel_order() {
+    ensure_out_dir().expect("Could not create output directory");
+
+    let verses = b"Midway upon the journey of our life
+I found myself within a forest dark,
+For the straightforward pathway had been lost.--";
+
+    let channel_order = vec![RgbChannel::Green, RgbChannel::Red, RgbChannel::Blue];
+
+    ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
         .set_use_n_lsb(2)
-        .decode();
+        .set_channel_order(channel_order.clone())
+        .encode_bytes(verses)
+        .expect("Encoding failed")
+        .save("tests/out/red_panda_channel_order.png", ImageFormat::Png)
+        .expect("Could not create output file");
 
-    assert!(decoded.is_ok());
+    let decoded = ImageDecoder::try_from("tests/out/red_panda_channel_order.png").unwrap()
+        .set_use_n_lsb(2)
+        .set_channel_order(channel_order)
+        .until_marker(Some(b"--"))
+        .decode()
+        .expect("Decoding failed");
 
-    let decoded = decoded.unwrap();
-    let decoded_string = decoded.as_raw();
+    assert!(decoded.hit_marker());
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+}
 
-    println!("Raw decoded:\n{}", decoded_string);
+#[test]
+fn encode_decode_consuming_builder() {
+    ensure_out_dir().expect("Could not create output directory");
+
+    let verses = b"Midway upon the journey of our life--";
+
+    ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+        .with_lsb(2)
+        .with_channel(RgbChannel::Green)
+        .with_step_by_n_pixels(3)
+        .encode_bytes(verses)
+        .expect("Encoding failed")
+        .save("tests/out/red_panda_builder.png", ImageFormat::Png)
+        .expect("Could not create output file");
+
+    let decoded = ImageDecoder::try_from("tests/out/red_panda_builder.png").unwrap()
+        .with_lsb(2)
+        .with_channel(RgbChannel::Green)
+        .with_step_by_n_pixels(3)
+        .with_marker(Some(b"--"))
+        .decode()
+        .expect("Decoding failed");
+
+    assert!(decoded.hit_marker());
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+}
+
+#[test]
+fn encode_decode_password() {
+    let verses = b"Midway upon the journey of our life--";
+    let source = image::open("tests/images/red_panda.jpg").expect("Test image not found");
+
+    let encoder = ImageEncoder::from_image(source).with_lsb(2).with_password("seagul");
+    let encoded = encoder.encode_bytes(verses).expect("Encoding failed");
+    assert!(encoded.verify_roundtrip(verses, &encoder).expect("Decoding failed"));
+
+    let mut buffer = vec![];
+    encoded.write(&mut buffer, ImageFormat::Png).expect("Write failed");
+    let encoded_image = image::load_from_memory(&buffer).unwrap();
+
+    let decoded = ImageDecoder::from_image(encoded_image.clone())
+        .with_lsb(2)
+        .with_password("seagul")
+        .decode_exact(verses.len())
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    for decoder in [
+        ImageDecoder::from_image(encoded_image.clone()).with_lsb(2),
+        ImageDecoder::from_image(encoded_image).with_lsb(2).with_password("seagull"),
+    ] {
+        let decoded = decoder.decode_exact(verses.len()).expect("Decoding failed");
+        assert_ne!(decoded.embedded_data().as_slice(), &verses[..]);
+    }
+}
+
+#[test]
+fn encode_decode_pixel_selectors() {
+    let verses = b"Midway upon the journey of our life";
+    let source = image::open("tests/images/red_panda.jpg").expect("Test image not found");
+    let selectors: Vec<Box<dyn PixelSelector>> = vec![
+        Box::new(LinearPixelSelector),
+        Box::new(DiagonalPixelSelector),
+        Box::new(SpiralPixelSelector),
+        Box::new(ZigZagPixelSelector),
+        Box::new(FibonacciPixelSelector),
+    ];
+
+    for selector in selectors {
+        let encoder = ImageEncoder::from_image(source.clone())
+            .with_lsb(2)
+            .with_step_by_n_pixels(2)
+            .with_pixel_selector(selector.clone());
+        let encoded = encoder.encode_bytes(verses).expect("Encoding failed");
+        assert!(encoded.verify_roundtrip(verses, &encoder).expect("Decoding failed"));
+
+        let mut buffer = vec![];
+        encoded.write(&mut buffer, ImageFormat::Png).expect("Write failed");
+
+        let decoded = ImageDecoder::from_image(image::load_from_memory(&buffer).unwrap())
+            .with_lsb(2)
+            .with_step_by_n_pixels(2)
+            .with_pixel_selector(selector)
+            .decode_exact(verses.len())
+            .expect("Decoding failed");
+        assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+    }
+}
+
+#[test]
+fn encode_decode_padding() {
+    let verses = b"Midway upon the journey of our life";
+
+    let encoded = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+        .with_lsb(2)
+        .with_padding("pad")
+        .encode_bytes(verses)
+        .expect("Encoding failed");
+
+    let decoded = ImageDecoder::from_bytes(&encoded.to_png_bytes().unwrap()).unwrap()
+        .with_lsb(2)
+        .with_padding("pad")
+        .decode()
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+}
+
+#[test]
+fn encode_decode_msb_first() {
+    let verses = b"Midway upon the journey of our life";
+
+    let encoded = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+        .with_lsb(2)
+        .with_bit_order(BitOrder::MsbFirst)
+        .encode_bytes(verses)
+        .expect("Encoding failed");
+    let mut buffer = vec![];
+    encoded.write(&mut buffer, ImageFormat::Png).expect("Write failed");
+    let encoded_image = image::load_from_memory(&buffer).unwrap();
+
+    let decoded = ImageDecoder::from_image(encoded_image.clone())
+        .with_lsb(2)
+        .with_bit_order(BitOrder::MsbFirst)
+        .decode_exact(verses.len())
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    let decoded = ImageDecoder::from_image(encoded_image)
+        .with_lsb(2)
+        .decode_exact(verses.len())
+        .expect("Decoding failed");
+    assert_ne!(decoded.embedded_data().as_slice(), &verses[..]);
+}
+
+#[test]
+fn encode_decode_region() {
+    let verses = b"Midway upon the journey of our life";
+    let source = image::open("tests/images/red_panda.jpg").expect("Test image not found");
+
+    let encoder = ImageEncoder::from_image(source.clone())
+        .with_lsb(2)
+        .with_region(100, 80, 140, 120)
+        .with_padding("pad");
+    assert_eq!(encoder.estimate_capacity(), 400);
+
+    let encoded = encoder.encode_bytes(verses).expect("Encoding failed");
+    let mut buffer = vec![];
+    encoded.write(&mut buffer, ImageFormat::Png).expect("Write failed");
+    let encoded_image = image::load_from_memory(&buffer).unwrap();
+
+    // Nothing outside of the region is modified
+    let (original, altered) = (source.to_rgb8(), encoded_image.to_rgb8());
+    for (x, y, pixel) in altered.enumerate_pixels() {
+        if !(100..140).contains(&x) || !(80..120).contains(&y) {
+            assert_eq!(pixel, original.get_pixel(x, y));
+        }
+    }
+
+    let decoded = ImageDecoder::from_image(encoded_image)
+        .with_lsb(2)
+        .with_region(100, 80, 140, 120)
+        .decode_exact(verses.len())
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    assert!(matches!(
+        ImageEncoder::from_image(source).with_region(10, 10, 10, 20).encode_bytes(verses),
+        Err(SteganographyError::InvalidConfiguration(_))
+    ));
+}
+
+#[test]
+fn encode_decode_in_memory_image() {
+    let verses = b"Midway upon the journey of our life--";
+    let source = image::DynamicImage::new_rgb8(64, 64);
+
+    let mut encoded_bytes = std::io::Cursor::new(Vec::new());
+    ImageEncoder::from(source)
+        .encode_bytes(verses)
+        .expect("Encoding failed")
+        .write(&mut encoded_bytes, ImageFormat::Png)
+        .expect("Could not write encoded image");
+
+    let encoded_image = image::load_from_memory(encoded_bytes.get_ref())
+        .expect("Could not load encoded image");
+
+    let decoded = ImageDecoder::from_image(encoded_image)
+        .with_marker(Some(b"--"))
+        .decode()
+        .expect("Decoding failed");
+
+    assert!(decoded.hit_marker());
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+}
+
+#[test]
+fn encode_decode_with_header() {
+    let verses = b"Midway upon the journey of our life";
+
+    let encode = |data: &[u8], with_header: bool| {
+        let encoder = ImageEncoder::from(image::DynamicImage::new_rgb8(64, 64)).with_lsb(2);
+        let encoded = if with_header {
+            encoder.encode_with_header(data)
+        } else {
+            encoder.encode_bytes(data)
+        };
+
+        let mut encoded_bytes = std::io::Cursor::new(Vec::new());
+        encoded
+            .expect("Encoding failed")
+            .write(&mut encoded_bytes, ImageFormat::Png)
+            .expect("Could not write encoded image");
+        image::load_from_memory(encoded_bytes.get_ref()).expect("Could not load encoded image")
+    };
+
+    let decoded = ImageDecoder::from_image(encode(verses, true))
+        .with_lsb(2)
+        .decode_with_header()
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    // Data encoded without a header is rejected
+    let decoder = ImageDecoder::from_image(encode(verses, false)).with_lsb(2);
+    assert!(matches!(
+        decoder.decode_with_header(),
+        Err(SteganographyError::UnsupportedHeaderVersion(b'M'))
+    ));
+
+    // A length that can not fit in the image is rejected without decoding the payload
+    let mut oversized_header = Header::new(verses, 2, 2, 0).unwrap();
+    oversized_header.payload_len = 0xffff;
+    let decoder = ImageDecoder::from_image(encode(&oversized_header.encode(), false)).with_lsb(2);
+    assert!(matches!(
+        decoder.decode_with_header(),
+        Err(SteganographyError::InsufficientCapacity {
+            required: 65547,
+            available: 1024
+        })
+    ));
+}
+
+#[test]
+fn encode_decode_with_crc() {
+    let verses = b"Midway upon the journey of our life";
+
+    let encode = |data: &[u8], with_crc: bool| {
+        let encoder = ImageEncoder::from(image::DynamicImage::new_rgb8(64, 64)).with_lsb(2);
+        let encoded = if with_crc {
+            encoder.encode_with_crc(data)
+        } else {
+            encoder.encode_bytes(data)
+        };
+
+        let mut encoded_bytes = std::io::Cursor::new(Vec::new());
+        encoded
+            .expect("Encoding failed")
+            .write(&mut encoded_bytes, ImageFormat::Png)
+            .expect("Could not write encoded image");
+        image::load_from_memory(encoded_bytes.get_ref()).expect("Could not load encoded image")
+    };
+
+    let decoded = ImageDecoder::from_image(encode(verses, true))
+        .with_lsb(2)
+        .decode_with_crc()
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    // A payload starting with zeros is not mistaken for an empty one, whose CRC32 is zero
+    let zeros = [0, 0, 0, 0, 42, 43, 44];
+    let decoded = ImageDecoder::from_image(encode(&zeros, true))
+        .with_lsb(2)
+        .decode_with_crc()
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &zeros[..]);
+
+    // Without its length, the first bytes of the data are read as a length that does not fit
+    let decoder = ImageDecoder::from_image(encode(verses, false)).with_lsb(2);
+    assert!(matches!(
+        decoder.decode_with_crc(),
+        Err(SteganographyError::InsufficientCapacity { .. })
+    ));
+
+    let mut corrupted = (verses.len() as u32).to_le_bytes().to_vec();
+    corrupted.extend_from_slice(verses);
+    corrupted.extend_from_slice(&[0; 4]);
+    let decoder = ImageDecoder::from_image(encode(&corrupted, false)).with_lsb(2);
+    assert!(matches!(
+        decoder.decode_with_crc(),
+        Err(SteganographyError::ChecksumMismatch { expected: 0, .. })
+    ));
+}
+
+#[test]
+fn decode_exact_length() {
+    let verses = b"Midway upon the journey of our life";
+    let source = image::DynamicImage::new_rgb8(64, 64);
+
+    let mut encoded_bytes = std::io::Cursor::new(Vec::new());
+    ImageEncoder::from(source)
+        .with_lsb(2)
+        .encode_bytes(verses)
+        .expect("Encoding failed")
+        .write(&mut encoded_bytes, ImageFormat::Png)
+        .expect("Could not write encoded image");
+
+    let encoded_image = image::load_from_memory(encoded_bytes.get_ref())
+        .expect("Could not load encoded image");
+
+    let decoder = ImageDecoder::from_image(encoded_image).with_lsb(2);
+
+    assert!(decoder.decode_exact(0).expect("Decoding failed").embedded_data().is_empty());
+
+    let decoded = decoder.decode_exact(verses.len()).expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
 
-    assert_eq!(decoded.hit_marker(), false);
-}
\ No newline at end of file
+    // 64 * 64 pixels hold 1024 bytes with 2 bits each
+    assert!(matches!(
+        decoder.decode_exact(2048),
+        Err(SteganographyError::InsufficientCapacity {
+            required: 2048,
+            available: 1024
+        })
+    ));
+}
+
+#[test]
+fn encode_decode_alpha_channel() {
+    let verses = b"Midway upon the journey of our life";
+    let source = image::RgbaImage::from_fn(64, 64, |x, y| {
+        image::Rgba([x as u8, y as u8, 128, 100 + (x as u8 % 100)])
+    });
+
+    let mut encoded_bytes = std::io::Cursor::new(Vec::new());
+    ImageEncoder::from(image::DynamicImage::ImageRgba8(source.clone()))
+        .with_channel_order(vec![RgbChannel::Red, RgbChannel::Alpha])
+        .encode_bytes(verses)
+        .expect("Encoding failed")
+        .write(&mut encoded_bytes, ImageFormat::Png)
+        .expect("Could not write encoded image");
+
+    let encoded_image = image::load_from_memory(encoded_bytes.get_ref())
+        .expect("Could not load encoded image");
+    assert_eq!(encoded_image.color(), image::ColorType::Rgba8);
+
+    // Only the first pixels hold data, the remaining ones must be untouched
+    let pixels_used = verses.len() * 8 / 2;
+    let encoded_rgba = encoded_image.to_rgba8();
+    assert!(source
+        .pixels()
+        .zip(encoded_rgba.pixels())
+        .skip(pixels_used)
+        .all(|(original, encoded)| original == encoded));
+
+    let decoded = ImageDecoder::from_image(encoded_image)
+        .with_channel_order(vec![RgbChannel::Red, RgbChannel::Alpha])
+        .decode_exact(verses.len())
+        .expect("Decoding failed");
+
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+}
+
+#[test]
+fn encode_preserves_transparency() {
+    let source = image::RgbaImage::from_fn(32, 32, |x, y| {
+        image::Rgba([x as u8 * 8, y as u8 * 8, 64, if x < 16 { 0 } else { (x * y) as u8 }])
+    });
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgba8(source.clone())
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .expect("Could not write source image");
+
+    let encoded = ImageEncoder::from_bytes(png.get_ref())
+        .expect("Could not load source image")
+        .encode_bytes(b"Midway upon the journey")
+        .expect("Encoding failed");
+    let encoded_image = image::load_from_memory(&encoded.to_png_bytes().unwrap())
+        .expect("Could not load encoded image");
+    assert_eq!(encoded_image.color(), image::ColorType::Rgba8);
+
+    // Only the blue channel holds data, the alpha channel is left untouched everywhere
+    let encoded_rgba = encoded_image.to_rgba8();
+    for (original, encoded) in source.pixels().zip(encoded_rgba.pixels()) {
+        assert_eq!(original[3], encoded[3]);
+        assert_eq!(original.0[..2], encoded.0[..2]);
+    }
+}
+
+#[test]
+fn encode_decode_from_bytes() {
+    let verses = b"Midway upon the journey of our life";
+    let source = std::fs::read("tests/images/red_panda.jpg").expect("Test image not found");
+
+    let encoded = ImageEncoder::from_bytes(&source).unwrap()
+        .encode_bytes(verses)
+        .expect("Encoding failed");
+    let mut buffer = vec![];
+    encoded.write(&mut buffer, ImageFormat::Png).expect("Write failed");
+
+    let decoder = ImageDecoder::try_from(buffer.as_slice()).unwrap();
+    let decoded = decoder.decode_exact(verses.len()).expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+}
+
+#[test]
+fn encode_decode_ecc() {
+    let verses = b"Midway upon the journey of our life--";
+
+    let encoded = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+        .with_ecc()
+        .encode_with_header(verses)
+        .expect("Encoding failed");
+    let mut altered = image::load_from_memory(&encoded.to_png_bytes().unwrap())
+        .unwrap()
+        .to_rgb8();
+
+    // Flip one bit in some of the blocks, each one spans 8 pixels
+    for block in [0, 5, 13, 40] {
+        altered.get_pixel_mut(block * 8 + 3, 0).0[2] ^= 1;
+    }
+    let altered = image::DynamicImage::ImageRgb8(altered);
+
+    let decoded = ImageDecoder::from_image(altered.clone())
+        .with_ecc()
+        .decode_with_header()
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    let decoded = ImageDecoder::from_image(altered.clone())
+        .with_ecc()
+        .with_marker(Some(b"--"))
+        .decode()
+        .expect("Decoding failed");
+    assert!(decoded.hit_marker());
+    assert!(decoded.embedded_data().ends_with(&verses[..]));
+
+    assert!(ImageDecoder::from_image(altered).decode_with_header().is_err());
+}
+
+#[test]
+fn encoded_image_accessors() {
+    let verses = b"Midway upon the journey of our life";
+    let source = image::open("tests/images/red_panda.jpg").expect("Test image not found");
+
+    let encoded = ImageEncoder::from_image(source.clone())
+        .encode_bytes(verses)
+        .expect("Encoding failed");
+    assert!(encoded.original_image() == &source);
+    assert!(encoded.altered_image() != &source);
+    let as_ref: &image::DynamicImage = encoded.as_ref();
+    assert!(as_ref == encoded.altered_image());
+
+    // The altered image can be decoded, or encoded into again, without saving it
+    let reencoded = ImageEncoder::from_image(encoded.into_altered_image())
+        .with_channel(RgbChannel::Red)
+        .encode_bytes(verses)
+        .expect("Encoding failed");
+    for channel in [RgbChannel::Blue, RgbChannel::Red] {
+        let decoded = ImageDecoder::from_image(reencoded.altered_image().clone())
+            .with_channel(channel)
+            .decode_exact(verses.len())
+            .expect("Decoding failed");
+        assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+    }
+}
+
+#[test]
+fn encode_decode_all_channels() {
+    let verses = b"Midway upon the journey of our life";
+    let source = image::open("tests/images/red_panda.jpg").expect("Test image not found");
+
+    let encoded = ImageEncoder::from_image(source.clone())
+        .with_channel(RgbChannel::All)
+        .encode_bytes(verses)
+        .expect("Encoding failed");
+
+    // Each pixel holds 3 bits, one per channel
+    let pixels_used = (verses.len() * 8).div_ceil(3) as u32;
+    assert!(encoded.diff().changed_pixels().iter().all(|(x, y, _, _)| *y == 0 && *x < pixels_used));
+
+    let decoded = ImageDecoder::from_bytes(&encoded.to_png_bytes().unwrap()).unwrap()
+        .with_channel(RgbChannel::All)
+        .decode_exact(verses.len())
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    let decoded = ImageDecoder::from_bytes(&encoded.to_png_bytes().unwrap()).unwrap()
+        .with_channel_order(vec![RgbChannel::Red, RgbChannel::Green, RgbChannel::Blue])
+        .decode_exact(verses.len())
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+}
+
+#[cfg(feature = "crypto")]
+#[test]
+fn encode_decode_with_password() {
+    use seagul_core::error::SteganographyError;
+
+    let verses = b"Midway upon the journey of our life";
+    let salt = *b"saltSALTsaltSALT";
+    let mut encoder = ImageEncoder::from_image(image::DynamicImage::new_rgb8(64, 64));
+    encoder.set_password_with_salt("seagul", &salt);
+    let encoded = encoder.encode_with_header(verses).expect("Encoding failed");
+
+    let mut decoder = ImageDecoder::from_image(encoded.altered_image().clone());
+    decoder.set_password_with_salt("seagul", &salt);
+    let decoded = decoder.decode_with_header().expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    decoder.set_password_with_salt("seagull", &salt);
+    assert!(matches!(decoder.decode_with_header(), Err(SteganographyError::CryptoError(_))));
+}
+
+#[cfg(feature = "crypto")]
+#[test]
+fn encode_decode_encrypted() {
+    use seagul_core::{crypto::OVERHEAD, error::SteganographyError};
+
+    let verses = b"Midway upon the journey of our life";
+    let key = [42u8; 32];
+    let source = image::open("tests/images/red_panda.jpg").expect("Test image not found");
+    let encoder = ImageEncoder::from_image(source).with_key(&key);
+
+    let encoded = encoder.encode_bytes(verses).expect("Encoding failed");
+    let encoded_image = image::load_from_memory(&encoded.to_png_bytes().unwrap()).unwrap();
+
+    let decoded = ImageDecoder::from_image(encoded_image.clone())
+        .with_key(&key)
+        .decode_exact(verses.len())
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    // Without the key only the nonce and the ciphertext are found
+    let raw = ImageDecoder::from_image(encoded_image.clone())
+        .decode_exact(verses.len() + OVERHEAD)
+        .expect("Decoding failed");
+    assert!(!raw.embedded_data().windows(6).any(|window| window == b"Midway"));
+
+    let wrong_key = ImageDecoder::from_image(encoded_image)
+        .with_key(&[43u8; 32])
+        .decode_exact(verses.len());
+    assert!(matches!(wrong_key, Err(SteganographyError::CryptoError(_))));
+
+    let encoded = encoder.encode_with_header(verses).expect("Encoding failed");
+    let encoded_image = image::load_from_memory(&encoded.to_png_bytes().unwrap()).unwrap();
+    let decoded = ImageDecoder::from_image(encoded_image.clone())
+        .with_key(&key)
+        .decode_with_header()
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    let without_key = ImageDecoder::from_image(encoded_image).decode_with_header();
+    assert!(matches!(without_key, Err(SteganographyError::InvalidConfiguration(_))));
+
+    let encoded = encoder.encode_with_crc(verses).expect("Encoding failed");
+    let decoded = ImageDecoder::from_bytes(&encoded.to_png_bytes().unwrap()).unwrap()
+        .with_key(&key)
+        .decode_with_crc()
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+}
+
+#[test]
+fn encode_decode_self_describing() {
+    let verses = b"Midway upon the journey of our life";
+    let source = image::open("tests/images/red_panda.jpg").expect("Test image not found");
+
+    let encoded = ImageEncoder::from_image(source.clone())
+        .with_lsb(2)
+        .with_channel(RgbChannel::Green)
+        .with_position(ImagePosition::BottomLeft)
+        .with_step_by_n_pixels(3)
+        .with_ecc()
+        .encode_self_describing(verses)
+        .expect("Encoding failed");
+
+    // Any decoder configuration is replaced by the one read from the metadata
+    let decoded = ImageDecoder::from_bytes(&encoded.to_png_bytes().unwrap()).unwrap()
+        .with_lsb(4)
+        .with_channel(RgbChannel::Red)
+        .decode_self_describing()
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data().as_slice(), &verses[..]);
+
+    assert!(ImageDecoder::from_image(source).decode_self_describing().is_err());
+}
+
+#[cfg(feature = "hash")]
+#[test]
+fn save_with_hash() {
+    ensure_out_dir().expect("Could not create output directory");
+
+    let encoded = ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+        .encode_bytes(b"Midway upon the journey of our life")
+        .expect("Encoding failed");
+    let hash = encoded
+        .save_with_hash("tests/out/red_panda_hashed.png", ImageFormat::Png)
+        .expect("Could not create output file");
+
+    let saved = std::fs::read("tests/out/red_panda_hashed.png").unwrap();
+    assert_eq!(&hash, blake3::hash(&saved).as_bytes());
+    assert!(encoded.save_with_hash("tests/out/red_panda_hashed.jpg", ImageFormat::Jpeg).is_err());
+}
+
+#[test]
+fn encode_decode_file() {
+    ensure_out_dir().expect("Could not create output directory");
+    std::fs::create_dir_all("tests/out/files").expect("Could not create output directory");
+
+    let verses = "Midway upon the journey of our life\nI found myself within a forest dark,\n";
+    std::fs::write("tests/out/inferno.txt", verses).expect("Could not create input file");
+
+    ImageEncoder::try_from("tests/images/red_panda.jpg").unwrap()
+        .with_lsb(2)
+        .encode_file(std::path::Path::new("tests/out/inferno.txt"))
+        .expect("Encoding failed")
+        .save("tests/out/red_panda_file.png", ImageFormat::Png)
+        .expect("Could not create output file");
+
+    let path = ImageDecoder::try_from("tests/out/red_panda_file.png").unwrap()
+        .with_lsb(2)
+        .decode_file(std::path::Path::new("tests/out/files"))
+        .expect("Decoding failed");
+
+    assert_eq!(path, std::path::Path::new("tests/out/files/inferno.txt"));
+    assert_eq!(std::fs::read_to_string(path).unwrap(), verses);
+}
+
+#[test]
+fn encode_decode_file_with_marker() {
+    ensure_out_dir().expect("Could not create output directory");
+    std::fs::create_dir_all("tests/out/marked_files").expect("Could not create output directory");
+
+    // The file holds the marker, which must not cut it short
+    let verses = "Midway upon the journey of our life\n--\nI found myself within a forest dark,\n";
+    std::fs::write("tests/out/marked_inferno.txt", verses).expect("Could not create input file");
+
+    let encoded = ImageEncoder::from(image::DynamicImage::new_rgb8(64, 64))
+        .with_lsb(2)
+        .encode_file(std::path::Path::new("tests/out/marked_inferno.txt"))
+        .expect("Encoding failed");
+
+    let path = ImageDecoder::from_image(encoded.altered_image().clone())
+        .with_lsb(2)
+        .with_marker(Some("--"))
+        .decode_file(std::path::Path::new("tests/out/marked_files"))
+        .expect("Decoding failed");
+
+    assert_eq!(path, std::path::Path::new("tests/out/marked_files/marked_inferno.txt"));
+    assert_eq!(std::fs::read_to_string(path).unwrap(), verses);
+}
+
+#[test]
+fn encode_decode_split() {
+    use seagul_core::error::SteganographyError;
+
+    let verses: Vec<u8> = b"Midway upon the journey of our life I found myself within a forest dark"
+        .iter()
+        .copied()
+        .cycle()
+        .take(500)
+        .collect();
+    let covers = || {
+        vec![
+            image::DynamicImage::new_rgb8(32, 32),
+            image::DynamicImage::new_rgb8(40, 20),
+            image::DynamicImage::new_rgb8(64, 64),
+        ]
+    };
+
+    let encoder = ImageEncoder::default().with_lsb(2);
+    let shards = encoder.encode_split(&verses, covers()).expect("Encoding failed");
+    assert_eq!(shards.len(), 3);
+
+    let shard_bytes: Vec<Vec<u8>> = shards
+        .iter()
+        .map(|shard| shard.to_png_bytes().expect("Could not write encoded image"))
+        .collect();
+    let decoded = ImageDecoder::new()
+        .with_lsb(2)
+        .decode_multi(shard_bytes.iter().map(Vec::as_slice).collect())
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data(), &verses);
+
+    // A payload fitting into the first cover only uses that one
+    let shards = encoder.encode_split(b"Midway", covers()).expect("Encoding failed");
+    assert_eq!(shards.len(), 1);
+
+    let too_large = vec![0u8; 2048];
+    assert!(matches!(
+        encoder.encode_split(&too_large, covers()),
+        Err(SteganographyError::InsufficientCapacity { required: 2048, .. })
+    ));
+}
+
+#[test]
+fn encode_decode_offset_and_step() {
+    let encoder = ImageEncoder::from_image(image::DynamicImage::new_rgb8(20, 20))
+        .with_offset(10)
+        .with_step_by_n_pixels(3);
+    let encoded = encoder.encode_bytes([0xffu8]).expect("Encoding failed");
+
+    // The offset is in absolute pixels, the step applies from there on
+    let changed: Vec<(u32, u32)> = encoded
+        .diff()
+        .changed_pixels()
+        .iter()
+        .map(|(x, y, _, _)| (*x, *y))
+        .collect();
+    assert_eq!(
+        changed,
+        vec![(10, 0), (13, 0), (16, 0), (19, 0), (2, 1), (5, 1), (8, 1), (11, 1)]
+    );
+
+    let decoded = ImageDecoder::from_image(encoded.altered_image().clone())
+        .with_offset(10)
+        .with_step_by_n_pixels(3)
+        .decode_exact(1)
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data(), &[0xff]);
+}
+
+#[test]
+fn encode_decode_xor_key() {
+    let cover = image::DynamicImage::new_rgb8(64, 64);
+    let key = b"gull";
+    let encoded = ImageEncoder::from_image(cover.clone())
+        .with_xor_key(key)
+        .with_ecc()
+        .encode_string("Midway upon the journey of our life--")
+        .expect("Encoding failed");
+
+    // Without the key, the payload can not be read as is
+    let plain = ImageDecoder::from_image(encoded.altered_image().clone())
+        .with_ecc()
+        .decode_exact(6)
+        .expect("Decoding failed");
+    assert_ne!(plain.embedded_data(), b"Midway");
+
+    // Markers are matched once the data is XORed back
+    let decoded = ImageDecoder::from_image(encoded.altered_image().clone())
+        .with_xor_key(key)
+        .with_ecc()
+        .with_marker(Some("--"))
+        .decode()
+        .expect("Decoding failed");
+    assert_eq!(decoded.as_string().unwrap(), "Midway upon the journey of our life--");
+
+    let encoded = ImageEncoder::from_image(cover)
+        .with_xor_key(key)
+        .with_spread(true)
+        .encode_with_header(b"Midway")
+        .expect("Encoding failed");
+    let decoded = ImageDecoder::from_image(encoded.altered_image().clone())
+        .with_xor_key(key)
+        .decode_with_header()
+        .expect("Decoding failed");
+    assert_eq!(decoded.embedded_data(), b"Midway");
+}
+
+#[test]
+fn save_and_load_paths() {
+    ensure_out_dir().expect("Could not create output directory");
+    let output = std::path::PathBuf::from("tests/out").join("red_panda_path.png");
+
+    ImageEncoder::try_from(std::path::Path::new("tests/images/red_panda.jpg"))
+        .expect("Could not load source image")
+        .encode_string("Midway--")
+        .expect("Encoding failed")
+        .save(&output, ImageFormat::Png)
+        .expect("Could not create output file");
+
+    let decoded = ImageDecoder::try_from(output)
+        .expect("Could not load encoded image")
+        .with_marker(Some("--"))
+        .decode()
+        .expect("Decoding failed");
+    assert_eq!(decoded.as_string().unwrap(), "Midway--");
+}
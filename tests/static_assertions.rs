@@ -0,0 +1,7 @@
+use seagul_core::{decoder::{DecodedImage, ImageDecoder}, encoder::{EncodedImage, ImageEncoder}};
+use static_assertions::assert_impl_all;
+
+assert_impl_all!(ImageEncoder: Send, Sync, Clone);
+assert_impl_all!(ImageDecoder: Send, Sync, Clone);
+assert_impl_all!(EncodedImage: Send, Sync, Clone);
+assert_impl_all!(DecodedImage: Send, Sync, Clone);